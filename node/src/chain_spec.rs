@@ -201,5 +201,6 @@ fn testnet_genesis(
 			key: Some(root_key),
 		},
 		transaction_payment: Default::default(),
+		edge_connect: Default::default(),
 	}
 }
@@ -0,0 +1,396 @@
+//! RPC interface for the edge-connect pallet.
+
+use std::sync::Arc;
+
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use parity_scale_codec::Codec;
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	offchain::{storage::OffchainDb, OffchainDbExt, OffchainStorage},
+	H256,
+};
+use sp_runtime::{traits::Block as BlockT, DispatchError};
+
+pub use pallet_edge_connect_runtime_api::EdgeConnectApi as EdgeConnectRuntimeApi;
+use pallet_edge_connect_runtime_api::{
+	BroadcastId, BroadcastResult, CommandId, CommandStatus, ConnectionId, ConnectionsPage,
+	DispatchLogEntry, EdgeConnectStats, ResponseHash,
+};
+
+#[rpc(client, server)]
+pub trait EdgeConnectApi<BlockHash, AccountId, BlockNumber> {
+	/// Returns a page of the connections owned by `account`, along with their endpoint, status,
+	/// creation block and the block their edge server was last seen at.
+	///
+	/// The page holds up to `limit` connections with an id above `start_after`, pass its `next`
+	/// cursor as `start_after` to get the following page.
+	#[method(name = "edgeConnect_connectionsOf")]
+	fn connections_of(
+		&self,
+		account: AccountId,
+		start_after: Option<ConnectionId>,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<ConnectionsPage<AccountId, BlockNumber>>;
+
+	/// Returns the lifecycle status of `command_id`, or `None` for unknown commands.
+	#[method(name = "edgeConnect_commandStatus")]
+	fn command_status(
+		&self,
+		command_id: CommandId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<CommandStatus>>;
+
+	/// Returns the response CyberHub gave to `command_id`, or `None` if there is none yet.
+	#[method(name = "edgeConnect_commandResponse")]
+	fn command_response(
+		&self,
+		command_id: CommandId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<Vec<u8>>>;
+
+	/// Returns the hash of the response CyberHub gave to `command_id` and the algorithm it was
+	/// computed with, when only the hash is kept on chain.
+	#[method(name = "edgeConnect_responseHash")]
+	fn response_hash(
+		&self,
+		command_id: CommandId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<ResponseHash>>;
+
+	/// Returns the hash of the schema the response to `command_id` follows, so that it can be
+	/// fetched to decode the response. Only commands sent from a template may have one.
+	#[method(name = "edgeConnect_commandSchema")]
+	fn command_schema(
+		&self,
+		command_id: CommandId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<H256>>;
+
+	/// Returns the response body with the given `hash`, if this node indexed it.
+	#[method(name = "edgeConnect_responseBody")]
+	fn response_body(&self, hash: H256) -> RpcResult<Option<Vec<u8>>>;
+
+	/// Returns the connections tagged with `tag`, so that commands can be aimed at a class of
+	/// edge servers.
+	#[method(name = "edgeConnect_connectionsByTag")]
+	fn connections_by_tag(
+		&self,
+		tag: Vec<u8>,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<ConnectionId>>;
+
+	/// Checks whether `account` could send a command over `connection` right now, returning
+	/// the error `send_command` would fail with otherwise.
+	#[method(name = "edgeConnect_canSendCommand")]
+	fn can_send_command(
+		&self,
+		account: AccountId,
+		connection: ConnectionId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Result<(), DispatchError>>;
+
+	/// Returns the number of connections, of active connections and of queued commands.
+	#[method(name = "edgeConnect_stats")]
+	fn stats(&self, at: Option<BlockHash>) -> RpcResult<EdgeConnectStats>;
+
+	/// Returns the commands dispatched from block `from` up to and including block `to`.
+	///
+	/// The dispatch log is kept outside of consensus, only nodes that imported these blocks
+	/// with offchain indexing enabled have it.
+	#[method(name = "edgeConnect_dispatchLog")]
+	fn dispatch_log(
+		&self,
+		from: BlockNumber,
+		to: BlockNumber,
+	) -> RpcResult<Vec<DispatchLogEntry<BlockNumber>>>;
+
+	/// Returns the status and response of each command queued by the broadcast
+	/// `broadcast_id`, in the order they were queued.
+	///
+	/// Commands that were cancelled or pruned are no longer listed.
+	#[method(name = "edgeConnect_broadcastResults")]
+	fn broadcast_results(
+		&self,
+		broadcast_id: BroadcastId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<BroadcastResult>>;
+
+	/// Returns the commands sent over `connection` that are still stored, along with their
+	/// status, by ascending id.
+	///
+	/// The page holds up to `limit` commands with an id above `start_after`, pass the id of its
+	/// last command as `start_after` to get the following page.
+	#[method(name = "edgeConnect_commandsOf")]
+	fn commands_of(
+		&self,
+		connection: ConnectionId,
+		start_after: Option<CommandId>,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(CommandId, CommandStatus)>>;
+}
+
+/// Provides RPC methods to query the edge-connect pallet.
+pub struct EdgeConnect<C, P, S> {
+	/// Shared reference to the client.
+	client: Arc<C>,
+	/// Offchain database response bodies are indexed in.
+	offchain_db: OffchainDb<S>,
+	_marker: std::marker::PhantomData<P>,
+}
+
+impl<C, P, S> EdgeConnect<C, P, S> {
+	/// Creates a new instance of the EdgeConnect RPC helper.
+	pub fn new(client: Arc<C>, offchain_storage: S) -> Self {
+		Self { client, offchain_db: OffchainDb::new(offchain_storage), _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+impl<C, Block, S, AccountId, BlockNumber>
+	EdgeConnectApiServer<<Block as BlockT>::Hash, AccountId, BlockNumber>
+	for EdgeConnect<C, Block, S>
+where
+	Block: BlockT,
+	S: OffchainStorage + 'static,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: EdgeConnectRuntimeApi<Block, AccountId, BlockNumber>,
+	AccountId: Codec + Send + Sync + 'static,
+	BlockNumber: Codec + Send + Sync + 'static,
+{
+	fn connections_of(
+		&self,
+		account: AccountId,
+		start_after: Option<ConnectionId>,
+		limit: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<ConnectionsPage<AccountId, BlockNumber>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.connections_of(at_hash, account, start_after, limit).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query connections.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn command_status(
+		&self,
+		command_id: CommandId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<CommandStatus>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.command_status(at_hash, command_id).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query command status.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn command_response(
+		&self,
+		command_id: CommandId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<Vec<u8>>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.command_response(at_hash, command_id).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query command response.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn response_hash(
+		&self,
+		command_id: CommandId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<ResponseHash>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.response_hash(at_hash, command_id).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query response hash.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn command_schema(
+		&self,
+		command_id: CommandId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<H256>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.command_schema(at_hash, command_id).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query command schema.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn response_body(&self, hash: H256) -> RpcResult<Option<Vec<u8>>> {
+		let mut api = self.client.runtime_api();
+		// Bodies live in the offchain database, which runtime API calls can't see otherwise.
+		api.register_extension(OffchainDbExt::new(self.offchain_db.clone()));
+		let best_hash = self.client.info().best_hash;
+
+		api.response_body(best_hash, hash).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query response body.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn connections_by_tag(
+		&self,
+		tag: Vec<u8>,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<ConnectionId>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.connections_by_tag(at_hash, tag).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query connections by tag.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn can_send_command(
+		&self,
+		account: AccountId,
+		connection: ConnectionId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Result<(), DispatchError>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.can_send_command(at_hash, account, connection).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to check command.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn stats(&self, at: Option<Block::Hash>) -> RpcResult<EdgeConnectStats> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.stats(at_hash).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query stats.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn dispatch_log(
+		&self,
+		from: BlockNumber,
+		to: BlockNumber,
+	) -> RpcResult<Vec<DispatchLogEntry<BlockNumber>>> {
+		let mut api = self.client.runtime_api();
+		// The log lives in the offchain database, which runtime API calls can't see otherwise.
+		api.register_extension(OffchainDbExt::new(self.offchain_db.clone()));
+		let best_hash = self.client.info().best_hash;
+
+		api.dispatch_log(best_hash, from, to).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query dispatch log.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn broadcast_results(
+		&self,
+		broadcast_id: BroadcastId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<BroadcastResult>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.broadcast_results(at_hash, broadcast_id).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query broadcast results.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+
+	fn commands_of(
+		&self,
+		connection: ConnectionId,
+		start_after: Option<CommandId>,
+		limit: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<(CommandId, CommandStatus)>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.commands_of(at_hash, connection, start_after, limit).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query commands.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+}
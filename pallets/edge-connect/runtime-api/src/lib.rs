@@ -0,0 +1,89 @@
+//! Runtime API definition for the edge-connect pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use parity_scale_codec::Codec;
+use sp_core::H256;
+use sp_runtime::DispatchError;
+use sp_std::vec::Vec;
+
+pub use pallet_edge_connect::{
+	BroadcastId, BroadcastResult, CommandId, CommandStatus, ConnectionDetails, ConnectionHealth,
+	ConnectionId, ConnectionsPage, DispatchLogEntry, EdgeConnectStats, MAX_COMMANDS_PAGE,
+	MAX_CONNECTIONS_PAGE, MAX_DISPATCH_LOG_BLOCKS, ResponseHash,
+};
+
+sp_api::decl_runtime_apis! {
+	/// Queries over the connections registered in the edge-connect pallet.
+	pub trait EdgeConnectApi<AccountId, BlockNumber>
+	where
+		AccountId: Codec,
+		BlockNumber: Codec,
+	{
+		/// Returns up to `limit` of the connections owned by `account` with an id above
+		/// `start_after`, along with the cursor of the next page.
+		///
+		/// `limit` is capped at `MAX_CONNECTIONS_PAGE`.
+		fn connections_of(
+			account: AccountId,
+			start_after: Option<ConnectionId>,
+			limit: u32,
+		) -> ConnectionsPage<AccountId, BlockNumber>;
+
+		/// Returns the lifecycle status of `command_id`, if the command is known.
+		fn command_status(command_id: CommandId) -> Option<CommandStatus>;
+
+		/// Returns the response CyberHub gave to `command_id`, if there is one.
+		fn command_response(command_id: CommandId) -> Option<Vec<u8>>;
+
+		/// Returns the hash of the response CyberHub gave to `command_id` and the algorithm it
+		/// was computed with, if only the hash is kept on chain.
+		fn response_hash(command_id: CommandId) -> Option<ResponseHash>;
+
+		/// Returns the hash of the schema the response to `command_id` follows, if it was sent
+		/// from a template that names one.
+		fn command_schema(command_id: CommandId) -> Option<H256>;
+
+		/// Returns the response body with the given `hash` from offchain-indexed storage.
+		///
+		/// Callers must register an `OffchainDbExt` for the call.
+		fn response_body(hash: H256) -> Option<Vec<u8>>;
+
+		/// Returns the connections tagged with `tag`.
+		fn connections_by_tag(tag: Vec<u8>) -> Vec<ConnectionId>;
+
+		/// Checks whether `send_command` from `account` over `connection` would be accepted,
+		/// without queueing anything.
+		fn can_send_command(
+			account: AccountId,
+			connection: ConnectionId,
+		) -> Result<(), DispatchError>;
+
+		/// Returns how many connections there are, how many of them are active and how many
+		/// commands are queued.
+		///
+		/// The totals are kept up to date as things change, so this is cheap to call.
+		fn stats() -> EdgeConnectStats;
+
+		/// Returns the commands dispatched from block `from` up to and including block `to`,
+		/// from the offchain-indexed dispatch log.
+		///
+		/// At most `MAX_DISPATCH_LOG_BLOCKS` blocks are read. Callers must register an
+		/// `OffchainDbExt` for the call.
+		fn dispatch_log(from: BlockNumber, to: BlockNumber) -> Vec<DispatchLogEntry<BlockNumber>>;
+
+		/// Returns the status and response of each command of `broadcast_id` that is still
+		/// stored.
+		fn broadcast_results(broadcast_id: BroadcastId) -> Vec<BroadcastResult>;
+
+		/// Returns up to `limit` of the commands stored for `connection` with an id above
+		/// `start_after`, along with their status.
+		///
+		/// `limit` is capped at `MAX_COMMANDS_PAGE`.
+		fn commands_of(
+			connection: ConnectionId,
+			start_after: Option<CommandId>,
+			limit: u32,
+		) -> Vec<(CommandId, CommandStatus)>;
+	}
+}
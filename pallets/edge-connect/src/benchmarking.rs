@@ -0,0 +1,925 @@
+//! Benchmarking setup for pallet-edge-connect
+#![cfg(feature = "runtime-benchmarks")]
+use super::*;
+
+#[allow(unused)]
+use crate::Pallet as EdgeConnect;
+use frame_benchmarking::v2::*;
+use frame_support::traits::{Currency, EnsureOrigin, EnsureOriginWithArg};
+use frame_system::RawOrigin;
+use sp_runtime::traits::{Bounded, TrailingZeroInput};
+use sp_std::vec;
+
+/// An endpoint URL of the maximum allowed length.
+fn endpoint<T: Config>() -> Vec<u8> {
+	let mut url = b"https://".to_vec();
+	url.resize(T::MaxUrlLength::get() as usize, b'a');
+	url
+}
+
+/// A domain of the maximum allowed length.
+fn domain<T: Config>() -> Vec<u8> {
+	vec![b'd'; T::MaxUrlLength::get() as usize]
+}
+
+/// Authorizes the domain `endpoint` is hosted on.
+fn authorize<T: Config>() {
+	let host = EdgeConnect::<T>::url_host(&endpoint::<T>()).to_vec();
+	let host = EdgeConnect::<T>::bound_domain(host).expect("endpoint host fits");
+	AuthorizedDomains::<T>::insert(host, ());
+}
+
+/// Gives `who` plenty of funds to cover connection deposits.
+fn fund<T: Config>(who: &T::AccountId) {
+	T::Currency::make_free_balance_be(who, BalanceOf::<T>::max_value() / 2u32.into());
+}
+
+/// A label of the maximum allowed length.
+fn label<T: Config>() -> Vec<u8> {
+	vec![b'l'; T::MaxLabelLength::get() as usize]
+}
+
+/// A response of the maximum allowed length.
+fn response<T: Config>() -> Vec<u8> {
+	vec![b'r'; T::MaxResponseLength::get() as usize]
+}
+
+/// A command of the maximum allowed length.
+fn command<T: Config>() -> Vec<u8> {
+	vec![b'c'; T::MaxCommandLength::get() as usize]
+}
+
+/// Queues a command of the maximum allowed length on `connection` and returns its id.
+fn queue<T: Config>(owner: &T::AccountId, connection: ConnectionId) -> CommandId {
+	let command_id = NextCommandId::<T>::get();
+	EdgeConnect::<T>::send_command(
+		RawOrigin::Signed(owner.clone()).into(),
+		connection,
+		command::<T>(),
+	)
+	.expect("command can be queued");
+	// Pretend every command came in a block of its own, so `CommandsPerConnectionPerBlock`
+	// does not get in the way of filling the queue.
+	CommandsInBlock::<T>::remove(connection);
+	command_id
+}
+
+/// Moves `command_id` to `Dispatched`, as the offchain worker does once CyberHub took it.
+fn dispatch<T: Config>(command_id: CommandId) {
+	let worker: T::AccountId = account("worker", 0, 0);
+	OcwAuthorities::<T>::insert(&worker, ());
+	EdgeConnect::<T>::update_command_status(
+		RawOrigin::Signed(worker).into(),
+		command_id,
+		CommandStatus::Dispatched,
+	)
+	.expect("command can be dispatched");
+}
+
+/// Creates a connection owned by `owner` and returns its id.
+fn create<T: Config>(owner: &T::AccountId) -> ConnectionId {
+	authorize::<T>();
+	fund::<T>(owner);
+	let connection = NextConnectionId::<T>::get();
+	EdgeConnect::<T>::create_connection(
+		RawOrigin::Signed(owner.clone()).into(),
+		endpoint::<T>(),
+		Some(label::<T>()),
+		None,
+		PollMode::Push,
+	)
+	.expect("connection can be created");
+	connection
+}
+
+/// The `n`th endpoint URL of the maximum allowed length, on an authorized domain.
+fn nth_endpoint<T: Config>(n: u32) -> Vec<u8> {
+	// Every byte of `n` becomes two letters of the endpoint path.
+	let mut path = vec![b'/'];
+	path.extend(n.to_le_bytes().iter().flat_map(|b| [b'a' + b / 16, b'a' + b % 16]));
+	let mut url = endpoint::<T>();
+	url.truncate(url.len() - path.len());
+	let host = EdgeConnect::<T>::bound_domain(EdgeConnect::<T>::url_host(&url).to_vec())
+		.expect("endpoint host fits");
+	AuthorizedDomains::<T>::insert(host, ());
+	url.extend(path);
+	url
+}
+
+/// Gives `connection` fallback endpoints until it has `count` of them in total.
+fn fill_endpoints<T: Config>(connection: ConnectionId, count: u32) {
+	Connection::<T>::mutate(connection, |info| {
+		let info = info.as_mut().expect("connection exists");
+		for n in info.endpoints.len() as u32..count {
+			let url = nth_endpoint::<T>(n).try_into().expect("endpoint fits");
+			info.endpoints.try_push(url).expect("endpoint count is bounded");
+		}
+	});
+}
+
+/// Creates the `n`th connection owned by `owner`, on an endpoint of its own, and returns its id.
+fn create_nth<T: Config>(owner: &T::AccountId, n: u32) -> ConnectionId {
+	let url = nth_endpoint::<T>(n);
+	fund::<T>(owner);
+	let connection = NextConnectionId::<T>::get();
+	EdgeConnect::<T>::create_connection(
+		RawOrigin::Signed(owner.clone()).into(),
+		url,
+		None,
+		None,
+		PollMode::Push,
+	)
+	.expect("connection can be created");
+	// Pretend every connection was created in a block of its own, so that
+	// `ConnectionCreationCooldown` does not get in the way of creating more.
+	LastConnectionCreatedAt::<T>::remove(owner);
+	connection
+}
+
+/// Registers a fresh server key for `connection` and returns its signature over `response` to
+/// `command_id`.
+fn sign_response<T: Config>(
+	connection: ConnectionId,
+	command_id: CommandId,
+	response: &[u8],
+) -> sr25519::Signature {
+	let pubkey = sp_io::crypto::sr25519_generate(KEY_TYPE, None);
+	Connection::<T>::mutate(connection, |info| {
+		info.as_mut().expect("connection exists").server_pubkey = Some(pubkey)
+	});
+	let message = EdgeConnect::<T>::response_signing_payload(command_id, response);
+	sp_io::crypto::sr25519_sign(KEY_TYPE, &pubkey, &message).expect("key is in the keystore")
+}
+
+/// The `n`th metadata key of the maximum allowed length.
+fn metadata_key<T: Config>(n: u32) -> MetadataKeyOf<T> {
+	let mut key = n.to_le_bytes().to_vec();
+	key.resize(T::MaxMetadataKeyLength::get() as usize, b'k');
+	BoundedVec::truncate_from(key)
+}
+
+/// Gives `connection` as many metadata entries of the maximum allowed size as it can carry.
+fn fill_metadata<T: Config>(connection: ConnectionId) {
+	let value: MetadataValueOf<T> =
+		BoundedVec::truncate_from(vec![b'v'; T::MaxMetadataValueLength::get() as usize]);
+	for n in 0..T::MaxMetadataEntries::get() {
+		ConnectionMetadata::<T>::insert(connection, metadata_key::<T>(n), &value);
+	}
+	MetadataEntries::<T>::insert(connection, T::MaxMetadataEntries::get());
+}
+
+/// Frees all but `room` of the ids `FreeConnectionIds` can hold, taking them from the top of
+/// the id range so that no connection has them.
+fn fill_free_ids<T: Config>(room: u32) {
+	let count = T::MaxFreeConnectionIds::get().saturating_sub(room);
+	let ids = (0..count).rev().map(|n| ConnectionId(u32::MAX - n)).collect();
+	FreeConnectionIds::<T>::put(BoundedVec::truncate_from(ids));
+}
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn create_connection() {
+		let caller: T::AccountId = whitelisted_caller();
+		authorize::<T>();
+		fund::<T>(&caller);
+		// The new connection reuses the lowest id of a full free list.
+		let free = T::MaxFreeConnectionIds::get();
+		let ids = (0..free).map(ConnectionId).collect();
+		FreeConnectionIds::<T>::put(BoundedVec::truncate_from(ids));
+		NextConnectionId::<T>::put(ConnectionId(free));
+
+		#[extrinsic_call]
+		create_connection(
+			RawOrigin::Signed(caller.clone()),
+			endpoint::<T>(),
+			Some(label::<T>()),
+			Some(BlockNumberFor::<T>::max_value()),
+			PollMode::LongPoll,
+		);
+
+		assert_eq!(Connection::<T>::get(ConnectionId(0)).map(|info| info.owner), Some(caller));
+	}
+
+	#[benchmark]
+	fn remove_connection(c: Linear<0, { T::MaxQueuedCommands::get() }>) {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		for _ in 0..c {
+			queue::<T>(&caller, connection);
+		}
+		fill_metadata::<T>(connection);
+		fill_free_ids::<T>(1);
+
+		#[extrinsic_call]
+		remove_connection(RawOrigin::Signed(caller), connection);
+
+		assert!(!Connection::<T>::contains_key(connection));
+		assert!(CommandQueue::<T>::get(connection).is_empty());
+	}
+
+	#[benchmark]
+	fn remove_connections(
+		n: Linear<1, { T::MaxBulkRemove::get().min(T::MaxConnectionsPerAccount::get()) }>,
+	) {
+		let caller: T::AccountId = whitelisted_caller();
+		let connections: Vec<_> = (0..n).map(|n| create_nth::<T>(&caller, n)).collect();
+		for &connection in &connections {
+			for _ in 0..T::MaxQueuedCommands::get() {
+				queue::<T>(&caller, connection);
+			}
+			fill_metadata::<T>(connection);
+		}
+		fill_free_ids::<T>(n);
+		let ids: BoundedVec<_, _> = connections.clone().try_into().unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), ids);
+
+		for connection in connections {
+			assert!(!Connection::<T>::contains_key(connection));
+		}
+	}
+
+	#[benchmark]
+	fn send_command() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let command = command::<T>();
+
+		// `send_raw_command` shares this weight, so every option is given.
+		let options = SendOptions {
+			priority: u8::MAX,
+			valid_until: Some(BlockNumberFor::<T>::max_value()),
+			client_ref: Some([0; 32]),
+			ack_only: false,
+			seq: Some(0),
+		};
+
+		#[extrinsic_call]
+		send_raw_command(RawOrigin::Signed(caller), connection, command, options);
+
+		assert_eq!(CommandQueue::<T>::get(connection).len(), 1);
+	}
+
+	#[benchmark]
+	fn register_template() {
+		let caller: T::AccountId = whitelisted_caller();
+		let payload: BoundedVec<_, _> = command::<T>().try_into().unwrap();
+
+		#[extrinsic_call]
+		register_template(RawOrigin::Signed(caller.clone()), payload, Some(H256::repeat_byte(1)));
+
+		assert_eq!(CommandTemplates::<T>::get(0).map(|template| template.owner), Some(caller));
+	}
+
+	#[benchmark]
+	fn remove_template() {
+		let caller: T::AccountId = whitelisted_caller();
+		let payload: BoundedVec<_, _> = command::<T>().try_into().unwrap();
+		EdgeConnect::<T>::register_template(RawOrigin::Signed(caller.clone()).into(), payload, None)
+			.expect("template can be registered");
+
+		#[extrinsic_call]
+		remove_template(RawOrigin::Signed(caller), 0);
+
+		assert!(CommandTemplates::<T>::get(0).is_none());
+	}
+
+	#[benchmark]
+	fn send_command_from_template() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		// A single placeholder filled with a parameter of the maximum length.
+		let payload: BoundedVec<_, _> = b"{0}".to_vec().try_into().unwrap();
+		let schema_hash = Some(H256::repeat_byte(1));
+		EdgeConnect::<T>::register_template(
+			RawOrigin::Signed(caller.clone()).into(),
+			payload,
+			schema_hash,
+		)
+		.expect("template can be registered");
+		let params: BoundedVec<_, _> =
+			vec![command::<T>().try_into().unwrap()].try_into().unwrap();
+
+		#[extrinsic_call]
+		send_command_from_template(RawOrigin::Signed(caller), connection, 0, params);
+
+		assert_eq!(CommandQueue::<T>::get(connection).len(), 1);
+	}
+
+	#[benchmark]
+	fn send_command_rejected() {
+		let owner: T::AccountId = account("owner", 0, 0);
+		let connection = create::<T>(&owner);
+		// The signer is checked against every authorized sender before being turned down.
+		Connection::<T>::mutate(connection, |info| {
+			let info = info.as_mut().expect("connection exists");
+			for i in 0..T::MaxAuthorizedSenders::get() {
+				let sender = account("sender", i, 0);
+				info.authorized_senders.try_push(sender).expect("sender count is bounded");
+			}
+		});
+		let caller: T::AccountId = whitelisted_caller();
+		let command = command::<T>();
+
+		#[block]
+		{
+			assert!(EdgeConnect::<T>::send_command(
+				RawOrigin::Signed(caller).into(),
+				connection,
+				command,
+			)
+			.is_err());
+		}
+
+		assert!(CommandQueue::<T>::get(connection).is_empty());
+	}
+
+	#[benchmark]
+	fn set_connection_status() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+
+		#[extrinsic_call]
+		set_connection_status(
+			RawOrigin::Signed(caller),
+			connection,
+			ConnectionStatus::Paused,
+			None,
+		);
+
+		assert_eq!(
+			Connection::<T>::get(connection).map(|info| info.status),
+			Some(ConnectionStatus::Paused)
+		);
+	}
+
+	#[benchmark]
+	fn receive_response() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let command_id = queue::<T>(&caller, connection);
+		dispatch::<T>(command_id);
+		OcwAuthorities::<T>::insert(&caller, ());
+		let response: BoundedVec<_, _> = response::<T>().try_into().unwrap();
+		let signature = sign_response::<T>(connection, command_id, &response);
+
+		#[extrinsic_call]
+		receive_response(RawOrigin::Signed(caller), command_id, response, Some(signature));
+
+		assert_eq!(CommandStatusOf::<T>::get(command_id), Some(CommandStatus::Completed));
+	}
+
+	#[benchmark]
+	fn submit_server_response() -> Result<(), BenchmarkError> {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let command_id = queue::<T>(&caller, connection);
+		dispatch::<T>(command_id);
+		let response: BoundedVec<_, _> = response::<T>().try_into().unwrap();
+		// Registers a key for the edge server, which the server origin is derived from.
+		sign_response::<T>(connection, command_id, &response);
+		let origin = T::ServerOrigin::try_successful_origin(&connection)
+			.map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, command_id, response);
+
+		assert_eq!(CommandStatusOf::<T>::get(command_id), Some(CommandStatus::Completed));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn submit_response_unsigned_with_signed_payload() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let command_id = queue::<T>(&caller, connection);
+		dispatch::<T>(command_id);
+		// The signature is checked when validating the transaction, not when dispatching it.
+		let public = T::Public::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+		let signature = T::Signature::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+		let server_signature = sign_response::<T>(connection, command_id, &response::<T>());
+		let payload = ResponsePayload {
+			block_number: Zero::zero(),
+			command_id,
+			response: response::<T>(),
+			server_signature: Some(server_signature),
+			public,
+		};
+
+		#[extrinsic_call]
+		submit_response_unsigned_with_signed_payload(RawOrigin::None, payload, signature);
+
+		assert_eq!(CommandStatusOf::<T>::get(command_id), Some(CommandStatus::Completed));
+	}
+
+	#[benchmark]
+	fn pause_connection() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+
+		#[extrinsic_call]
+		pause_connection(RawOrigin::Signed(caller), connection, None);
+
+		assert_eq!(
+			Connection::<T>::get(connection).map(|info| info.status),
+			Some(ConnectionStatus::Paused)
+		);
+	}
+
+	#[benchmark]
+	fn resume_connection() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		EdgeConnect::<T>::pause_connection(
+			RawOrigin::Signed(caller.clone()).into(),
+			connection,
+			None,
+		)
+		.expect("connection can be paused");
+
+		#[extrinsic_call]
+		resume_connection(RawOrigin::Signed(caller), connection, None);
+
+		assert_eq!(
+			Connection::<T>::get(connection).map(|info| info.status),
+			Some(ConnectionStatus::Active)
+		);
+	}
+
+	#[benchmark]
+	fn rename_connection() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let new_label = vec![b'n'; T::MaxLabelLength::get() as usize];
+
+		#[extrinsic_call]
+		rename_connection(RawOrigin::Signed(caller), connection, Some(new_label.clone()), None);
+
+		assert_eq!(
+			Connection::<T>::get(connection).and_then(|info| info.label).map(|label| label.to_vec()),
+			Some(new_label)
+		);
+	}
+
+	#[benchmark]
+	fn set_connection_tags() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let tags: Vec<Vec<u8>> = (0..T::MaxTagsPerConnection::get())
+			.map(|i| {
+				let mut tag = i.to_le_bytes().to_vec();
+				tag.resize(T::MaxTagLength::get() as usize, b't');
+				tag
+			})
+			.collect();
+
+		#[extrinsic_call]
+		set_connection_tags(RawOrigin::Signed(caller), connection, tags.clone(), None);
+
+		assert_eq!(
+			Connection::<T>::get(connection).map(|info| info.tags.len()),
+			Some(tags.len())
+		);
+	}
+
+	#[benchmark]
+	fn set_server_pubkey() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let pubkey = sr25519::Public([1; 32]);
+
+		#[extrinsic_call]
+		set_server_pubkey(RawOrigin::Signed(caller), connection, Some(pubkey), None);
+
+		assert_eq!(
+			Connection::<T>::get(connection).and_then(|info| info.server_pubkey),
+			Some(pubkey)
+		);
+	}
+
+	#[benchmark]
+	fn set_metadata() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let key = metadata_key::<T>(0);
+		let value = vec![b'v'; T::MaxMetadataValueLength::get() as usize];
+
+		#[extrinsic_call]
+		set_metadata(RawOrigin::Signed(caller), connection, key.to_vec(), value, None);
+
+		assert_eq!(MetadataEntries::<T>::get(connection), 1);
+	}
+
+	#[benchmark]
+	fn clear_metadata() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		fill_metadata::<T>(connection);
+		let key = metadata_key::<T>(0);
+
+		#[extrinsic_call]
+		clear_metadata(RawOrigin::Signed(caller), connection, key.to_vec(), None);
+
+		assert!(!ConnectionMetadata::<T>::contains_key(connection, key));
+	}
+
+	#[benchmark]
+	fn transfer_connection() {
+		let caller: T::AccountId = whitelisted_caller();
+		let new_owner: T::AccountId = account("new_owner", 0, 0);
+		let connection = create::<T>(&caller);
+		fund::<T>(&new_owner);
+		// A running lease ends with the transfer.
+		Leases::<T>::insert(connection, (caller.clone(), BlockNumberFor::<T>::max_value()));
+
+		#[extrinsic_call]
+		transfer_connection(RawOrigin::Signed(caller), connection, new_owner.clone(), None);
+
+		assert_eq!(Connection::<T>::get(connection).map(|info| info.owner), Some(new_owner));
+		assert!(Leases::<T>::get(connection).is_none());
+	}
+
+	#[benchmark]
+	fn update_command_status() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let command_id = queue::<T>(&caller, connection);
+		OcwAuthorities::<T>::insert(&caller, ());
+
+		#[extrinsic_call]
+		update_command_status(RawOrigin::Signed(caller), command_id, CommandStatus::Dispatched);
+
+		assert_eq!(CommandStatusOf::<T>::get(command_id), Some(CommandStatus::Dispatched));
+	}
+
+	#[benchmark]
+	fn add_authorized_domain() -> Result<(), BenchmarkError> {
+		let origin =
+			T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let bounded = EdgeConnect::<T>::bound_domain(domain::<T>()).expect("domain fits");
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, domain::<T>());
+
+		assert!(AuthorizedDomains::<T>::contains_key(bounded));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn remove_authorized_domain() -> Result<(), BenchmarkError> {
+		let origin =
+			T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let bounded = EdgeConnect::<T>::bound_domain(domain::<T>()).expect("domain fits");
+		AuthorizedDomains::<T>::insert(&bounded, ());
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, domain::<T>());
+
+		assert!(!AuthorizedDomains::<T>::contains_key(bounded));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn set_ocw_paused() -> Result<(), BenchmarkError> {
+		let origin =
+			T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, true);
+
+		assert!(OcwPaused::<T>::get());
+		Ok(())
+	}
+
+	#[benchmark]
+	fn add_ocw_authority() -> Result<(), BenchmarkError> {
+		let origin =
+			T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let who: T::AccountId = account("worker", 0, 0);
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, who.clone());
+
+		assert!(OcwAuthorities::<T>::contains_key(who));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn remove_ocw_authority() -> Result<(), BenchmarkError> {
+		let origin =
+			T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let who: T::AccountId = account("worker", 0, 0);
+		OcwAuthorities::<T>::insert(&who, ());
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, who.clone());
+
+		assert!(!OcwAuthorities::<T>::contains_key(who));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn grant_command_access() {
+		let caller: T::AccountId = whitelisted_caller();
+		let sender: T::AccountId = account("sender", 0, 0);
+		let connection = create::<T>(&caller);
+
+		#[extrinsic_call]
+		grant_command_access(RawOrigin::Signed(caller), connection, sender.clone(), None);
+
+		assert!(Connection::<T>::get(connection)
+			.is_some_and(|info| info.authorized_senders.contains(&sender)));
+	}
+
+	#[benchmark]
+	fn revoke_command_access() -> Result<(), BenchmarkError> {
+		let caller: T::AccountId = whitelisted_caller();
+		let sender: T::AccountId = account("sender", 0, 0);
+		let connection = create::<T>(&caller);
+		EdgeConnect::<T>::grant_command_access(
+			RawOrigin::Signed(caller.clone()).into(),
+			connection,
+			sender.clone(),
+			None,
+		)
+		.map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		revoke_command_access(RawOrigin::Signed(caller), connection, sender.clone(), None);
+
+		assert!(Connection::<T>::get(connection)
+			.is_some_and(|info| !info.authorized_senders.contains(&sender)));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn lease_connection() -> Result<(), BenchmarkError> {
+		let owner: T::AccountId = account("owner", 0, 0);
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&owner);
+		EdgeConnect::<T>::grant_command_access(
+			RawOrigin::Signed(owner).into(),
+			connection,
+			caller.clone(),
+			None,
+		)
+		.map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		lease_connection(RawOrigin::Signed(caller.clone()), connection, 10u32.into());
+
+		assert_eq!(Leases::<T>::get(connection).map(|(lessee, _)| lessee), Some(caller));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn release_lease() -> Result<(), BenchmarkError> {
+		// The owner ends the lease of another account.
+		let caller: T::AccountId = whitelisted_caller();
+		let lessee: T::AccountId = account("lessee", 0, 0);
+		let connection = create::<T>(&caller);
+		EdgeConnect::<T>::grant_command_access(
+			RawOrigin::Signed(caller.clone()).into(),
+			connection,
+			lessee.clone(),
+			None,
+		)
+		.map_err(|_| BenchmarkError::Weightless)?;
+		let until = 10u32.into();
+		EdgeConnect::<T>::lease_connection(RawOrigin::Signed(lessee).into(), connection, until)
+			.map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		release_lease(RawOrigin::Signed(caller), connection);
+
+		assert!(Leases::<T>::get(connection).is_none());
+		Ok(())
+	}
+
+	#[benchmark]
+	fn cancel_command() -> Result<(), BenchmarkError> {
+		// The owner cancels the last command of a full queue, queued by another sender.
+		let caller: T::AccountId = whitelisted_caller();
+		let sender: T::AccountId = account("sender", 0, 0);
+		fund::<T>(&sender);
+		let connection = create::<T>(&caller);
+		EdgeConnect::<T>::grant_command_access(
+			RawOrigin::Signed(caller.clone()).into(),
+			connection,
+			sender.clone(),
+			None,
+		)
+		.map_err(|_| BenchmarkError::Weightless)?;
+		let mut command_id = 0;
+		for _ in 0..T::MaxQueuedCommands::get() {
+			command_id = queue::<T>(&sender, connection);
+		}
+
+		#[extrinsic_call]
+		cancel_command(RawOrigin::Signed(caller), command_id);
+
+		assert!(!CommandStatusOf::<T>::contains_key(command_id));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn heartbeat() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		OcwAuthorities::<T>::insert(&caller, ());
+		frame_system::Pallet::<T>::set_block_number(10u32.into());
+
+		#[extrinsic_call]
+		heartbeat(RawOrigin::Signed(caller), connection);
+
+		assert!(Connection::<T>::get(connection).is_some_and(|info| info.last_seen == 10u32.into()));
+	}
+
+	#[benchmark]
+	fn report_unreachable() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		OcwAuthorities::<T>::insert(&caller, ());
+		frame_system::Pallet::<T>::set_block_number(10u32.into());
+
+		#[extrinsic_call]
+		report_unreachable(RawOrigin::Signed(caller), connection);
+
+		assert_eq!(LastUnreachableReport::<T>::get(connection), Some(10u32.into()));
+	}
+
+	#[benchmark]
+	fn add_endpoint() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let max = T::MaxEndpoints::get();
+		fill_endpoints::<T>(connection, max - 1);
+		let url = nth_endpoint::<T>(max);
+
+		#[extrinsic_call]
+		add_endpoint(RawOrigin::Signed(caller), connection, url, None);
+
+		assert_eq!(
+			Connection::<T>::get(connection).map(|info| info.endpoints.len() as u32),
+			Some(max)
+		);
+	}
+
+	#[benchmark]
+	fn remove_endpoint() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let max = T::MaxEndpoints::get();
+		fill_endpoints::<T>(connection, max);
+
+		#[extrinsic_call]
+		remove_endpoint(RawOrigin::Signed(caller), connection, 0, None);
+
+		assert_eq!(
+			Connection::<T>::get(connection).map(|info| info.endpoints.len() as u32),
+			Some(max - 1)
+		);
+	}
+
+	#[benchmark]
+	fn record_endpoint_failover() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		OcwAuthorities::<T>::insert(&caller, ());
+		fill_endpoints::<T>(connection, 2);
+
+		#[extrinsic_call]
+		record_endpoint_failover(RawOrigin::Signed(caller), connection, 1);
+
+		assert_eq!(Connection::<T>::get(connection).map(|info| info.active_endpoint), Some(1));
+	}
+
+	#[benchmark]
+	fn flush_command_queue(c: Linear<0, { T::MaxFlushPerCall::get() }>) {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		// The whole queue is looked at, only the commands that were not dispatched yet are
+		// cancelled.
+		let max = T::MaxQueuedCommands::get();
+		let flushed = c.min(max);
+		for n in 0..max {
+			let command_id = queue::<T>(&caller, connection);
+			if n >= flushed {
+				CommandStatusOf::<T>::insert(command_id, CommandStatus::Dispatched);
+			}
+		}
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), connection);
+
+		let cancelled = CommandQueue::<T>::get(connection)
+			.iter()
+			.filter(|command| {
+				CommandStatusOf::<T>::get(command.id) == Some(CommandStatus::Cancelled)
+			})
+			.count();
+		assert_eq!(cancelled as u32, flushed);
+	}
+
+	#[benchmark]
+	fn retry_command() -> Result<(), BenchmarkError> {
+		// The owner retries the last command of a full queue, queued by another sender.
+		let caller: T::AccountId = whitelisted_caller();
+		let sender: T::AccountId = account("sender", 0, 0);
+		fund::<T>(&sender);
+		let connection = create::<T>(&caller);
+		EdgeConnect::<T>::grant_command_access(
+			RawOrigin::Signed(caller.clone()).into(),
+			connection,
+			sender.clone(),
+			None,
+		)
+		.map_err(|_| BenchmarkError::Weightless)?;
+		let mut command_id = 0;
+		for _ in 0..T::MaxQueuedCommands::get() {
+			command_id = queue::<T>(&sender, connection);
+		}
+		CommandStatusOf::<T>::insert(command_id, CommandStatus::Failed);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), command_id);
+
+		assert_eq!(CommandStatusOf::<T>::get(command_id), Some(CommandStatus::Queued));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn update_endpoint() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let max = T::MaxEndpoints::get();
+		fill_endpoints::<T>(connection, max);
+		let url = nth_endpoint::<T>(max);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), connection, url.clone(), Some(PollMode::LongPoll), None);
+
+		assert_eq!(
+			Connection::<T>::get(connection).and_then(|info| info.endpoints.first().cloned()),
+			Some(url.try_into().expect("endpoint fits"))
+		);
+	}
+
+	#[benchmark]
+	fn force_remove_connection(
+		c: Linear<0, { T::MaxQueuedCommands::get() }>,
+	) -> Result<(), BenchmarkError> {
+		let origin =
+			T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let owner: T::AccountId = account("owner", 0, 0);
+		let connection = create::<T>(&owner);
+		for _ in 0..c {
+			queue::<T>(&owner, connection);
+		}
+		fill_metadata::<T>(connection);
+		fill_free_ids::<T>(1);
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, connection);
+
+		assert!(!Connection::<T>::contains_key(connection));
+		assert!(CommandQueue::<T>::get(connection).is_empty());
+		Ok(())
+	}
+
+	#[benchmark]
+	fn report_command_error() {
+		let caller: T::AccountId = whitelisted_caller();
+		let connection = create::<T>(&caller);
+		let command_id = queue::<T>(&caller, connection);
+		OcwAuthorities::<T>::insert(&caller, ());
+		let error: BoundedVec<u8, T::MaxResponseLength> =
+			vec![b'e'; T::MaxResponseLength::get() as usize].try_into().expect("error fits");
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), command_id, error);
+
+		assert_eq!(CommandStatusOf::<T>::get(command_id), Some(CommandStatus::Failed));
+		assert!(CommandErrors::<T>::contains_key(command_id));
+	}
+
+	#[benchmark]
+	fn broadcast_command(c: Linear<0, { T::MaxConnectionsPerAccount::get() }>) {
+		let caller: T::AccountId = whitelisted_caller();
+		let connections: Vec<_> = (0..c).map(|n| create_nth::<T>(&caller, n)).collect();
+		let command = command::<T>();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), command);
+
+		for connection in connections {
+			assert_eq!(CommandQueue::<T>::get(connection).len(), 1);
+		}
+	}
+
+	impl_benchmark_test_suite!(EdgeConnect, crate::mock::new_test_ext(), crate::mock::Test);
+}
@@ -19,46 +19,109 @@
 //! * `create_connection` - Creates a connection between Cyborg blockchain and an external edge
 //!   server.
 //! * `send_command` - Sends a command to CyberHub.
-//! * `receive_response` - Receives a response from CyberHub.
+//! * `send_raw_command` - Sends a command to CyberHub with the given `SendOptions`.
+//! * `receive_response` - Receives a response from CyberHub, submitted by an offchain worker
+//!   authority.
+//! * `submit_response_unsigned_with_signed_payload` - Receives a response from CyberHub in an
+//!   unsigned transaction signed by an offchain worker authority.
+//! * `submit_server_response` - Receives a response submitted by the edge server itself.
 //! * `remove_connection` - Removes a connection between Cyborg blockchain and an external edge
 //!   server.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use parity_scale_codec::{
-	Decode, Encode,
-	alloc::string::ToString,
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{
+	traits::{
+		BalanceStatus, Currency, EnsureOriginWithArg, ExistenceRequirement, Get,
+		ReservableCurrency, WithdrawReasons,
+	},
+	dispatch::{DispatchResultWithPostInfo, WithPostDispatchInfo},
+	ensure,
+	weights::Weight,
+	CloneNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound,
 };
-use frame_support::{traits::Get, ensure};
 use frame_system::{
 	self as system,
 	offchain::{
 		AppCrypto, CreateSignedTransaction, SendSignedTransaction, SendUnsignedTransaction,
-		SignedPayload, Signer, SigningTypes, SubmitTransaction,
+		SignedPayload, Signer, SigningTypes,
 	},
 	pallet_prelude::BlockNumberFor,
 };
-use scale_info::prelude::string::String;
-use sp_core::crypto::KeyTypeId;
+use scale_info::{prelude::string::String, TypeInfo};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::{crypto::KeyTypeId, sr25519, H256};
 use sp_runtime::{
 	offchain::{
 		http,
 		storage::{MutateStorageError, StorageRetrievalError, StorageValueRef},
-		Duration,
+		storage_lock::{StorageLock, Time},
+		Duration, StorageKind, Timestamp,
+	},
+	traits::{
+		BlakeTwo256, CheckedAdd, Hash as HashT, IdentifyAccount, Keccak256, Saturating, Zero,
 	},
-	traits::Zero,
 	transaction_validity::{InvalidTransaction, TransactionValidity, ValidTransaction},
-	RuntimeDebug, BoundedVec,
+	BoundedVec, Percent, RuntimeDebug, StateVersion,
 };
-use sp_std::vec::Vec;
+use sp_std::{cmp::Reverse, marker::PhantomData, vec, vec::Vec};
+use sp_trie::{LayoutV0, LayoutV1, TrieConfiguration};
+
+#[cfg(any(feature = "try-runtime", test))]
+use sp_std::collections::btree_map::BTreeMap;
+
+#[cfg(test)]
+mod mock;
 
-// #[cfg(test)]
-// mod tests;
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+pub mod migrations;
+pub mod weights;
+pub mod wire;
+pub use weights::*;
+
+/// Balance type of the currency deposits are reserved in.
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
 pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"edge");
 
 const UNSIGNED_TXS_PRIORITY: u64 = 100;
 
+/// Log target of the offchain worker, so that node operators can filter its logs with
+/// `-l edge-connect-ocw=debug`.
+///
+/// Messages are followed by `key=value` fields: `block`, `connection`, `command`, `attempt`
+/// and `outcome`, along with whatever else explains the message.
+const LOG_TARGET: &str = "edge-connect-ocw";
+
+/// How long, in milliseconds, an offchain worker may hold the lock on a connection.
+///
+/// This leaves room for a backlog of requests running into the `HttpRequestTimeout` deadline.
+const LOCK_TIMEOUT_EXPIRATION: u64 = 60_000;
+
+/// The longest, in blocks, the offchain worker waits before retrying a failed CyberHub request.
+const MAX_RETRY_BACKOFF: u32 = 64;
+
+/// How many blocks a connection may go unseen before the offchain worker pings its edge server.
+const HEARTBEAT_INTERVAL: u32 = 10;
+
+/// The most connections returned in a single page of the runtime API, whatever limit is asked.
+pub const MAX_CONNECTIONS_PAGE: u32 = 100;
+
+/// The most commands returned in a single page of `commands_of`, whatever limit is asked.
+pub const MAX_COMMANDS_PAGE: u32 = 100;
+
+/// The most blocks of the dispatch log read in a single runtime API call, whatever range is
+/// asked.
+pub const MAX_DISPATCH_LOG_BLOCKS: u32 = 1_000;
+
 pub mod crypto {
 	use super::KEY_TYPE;
 	use sp_core::sr25519::Signature as Sr25519Signature;
@@ -95,7 +158,11 @@ pub mod pallet {
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
 
+	/// The in-code storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(6);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// Configure the pallet by specifying the parameters and types on which it depends.
@@ -135,56 +202,401 @@ pub mod pallet {
 		#[pallet::constant]
 		type UnsignedPriority: Get<TransactionPriority>;
 
-		/// Max number of commands sent per request
+		/// Maximum number of commands that can be queued on a single connection.
+		#[pallet::constant]
+		type MaxQueuedCommands: Get<u32>;
+
+		/// The maximum length of a command payload.
+		#[pallet::constant]
+		type MaxCommandLength: Get<u32>;
+
+		/// The maximum number of parameters `send_command_from_template` fills a template with.
+		#[pallet::constant]
+		type MaxTemplateParams: Get<u32>;
+
+		/// The maximum length of a response received from CyberHub.
+		///
+		/// Longer responses are rejected, and the offchain worker fails their command.
+		#[pallet::constant]
+		type MaxResponseLength: Get<u32>;
+
+		/// Whether only the hash of a response is stored on chain.
+		///
+		/// The full body goes to offchain-indexed storage, which nodes only keep when started
+		/// with `--enable-offchain-indexing true`. See [`Pallet::response_body`].
+		#[pallet::constant]
+		type HashResponses: Get<bool>;
+
+		/// Hashes the responses kept as hashes while `HashResponses` is set.
+		///
+		/// Every hash is stored along with the algorithm of the hasher, so that verifiers know
+		/// how to check it against the body. `BlakeTwo256` matches the rest of Substrate,
+		/// `Keccak256` and `Sha256` are there for CyberHubs that hash with those.
+		type ResponseHasher: HashT<Output = H256> + ResponseHashAlgorithm;
+
+		/// The maximum length of an edge server endpoint URL.
+		#[pallet::constant]
+		type MaxUrlLength: Get<u32>;
+
+		/// The maximum number of endpoints, the primary one included, a single connection can
+		/// fail over between.
+		#[pallet::constant]
+		type MaxEndpoints: Get<u32>;
+
+		/// The maximum number of connections a single account can own.
+		#[pallet::constant]
+		type MaxConnectionsPerAccount: Get<u32>;
+
+		/// The maximum number of connections a single `remove_connections` call removes.
+		#[pallet::constant]
+		type MaxBulkRemove: Get<u32>;
+
+		/// The maximum number of ids of removed connections kept around to be handed out again.
+		#[pallet::constant]
+		type MaxFreeConnectionIds: Get<u32>;
+
+		/// The maximum length of a connection label.
+		#[pallet::constant]
+		type MaxLabelLength: Get<u32>;
+
+		/// The maximum number of accounts, besides the owner, allowed to send commands over a
+		/// single connection.
+		#[pallet::constant]
+		type MaxAuthorizedSenders: Get<u32>;
+
+		/// The maximum length of a connection tag.
+		#[pallet::constant]
+		type MaxTagLength: Get<u32>;
+
+		/// The maximum number of tags a single connection can carry.
+		#[pallet::constant]
+		type MaxTagsPerConnection: Get<u32>;
+
+		/// The maximum length of a connection metadata key.
+		#[pallet::constant]
+		type MaxMetadataKeyLength: Get<u32>;
+
+		/// The maximum length of a connection metadata value.
+		#[pallet::constant]
+		type MaxMetadataValueLength: Get<u32>;
+
+		/// The maximum number of metadata entries a single connection can carry.
+		#[pallet::constant]
+		type MaxMetadataEntries: Get<u32>;
+
+		/// How many times a failed CyberHub request is retried before the command is failed.
+		#[pallet::constant]
+		type MaxCommandRetries: Get<u32>;
+
+		/// The maximum number of queued commands of a connection sent to CyberHub in a single
+		/// request. With `1` every command is sent on its own.
+		#[pallet::constant]
+		type MaxCommandsPerBatch: Get<u32>;
+
+		/// How long, in milliseconds, the offchain worker waits for an HTTP request to CyberHub
+		/// or an edge server before abandoning it.
+		#[pallet::constant]
+		type HttpRequestTimeout: Get<u64>;
+
+		/// How the offchain worker submits the responses it fetched from CyberHub.
+		#[pallet::constant]
+		type OcwSubmitStrategy: Get<SubmitStrategy>;
+
+		/// Whether the offchain worker also runs on nodes that are not validators.
+		///
+		/// Otherwise only validators deliver commands, so that the rest of the network does not
+		/// send the same HTTP requests again.
+		#[pallet::constant]
+		type OcwRunOnNonAuthorities: Get<bool>;
+
+		/// Number of blocks a dispatched command may wait for its response before timing out.
 		#[pallet::constant]
-		type MaxCommand: Get<u32>;
+		type CommandTimeout: Get<BlockNumberFor<Self>>;
 
-		/// Maximum number of responses received per request
+		/// Number of blocks a settled command is kept around before it is pruned.
 		#[pallet::constant]
-		type MaxResponses: Get<u32>;
+		type CommandRetentionBlocks: Get<BlockNumberFor<Self>>;
 
-		/// The maximum length of a response string.
+		/// The maximum number of commands timed out, of settled commands pruned, and of
+		/// connections checked for staleness, in a single block.
 		#[pallet::constant]
-		type MaxStringLength: Get<u32>;
+		type MaxPrunePerBlock: Get<u32>;
+
+		/// The maximum number of queued commands a single `flush_command_queue` call cancels.
+		#[pallet::constant]
+		type MaxFlushPerCall: Get<u32>;
+
+		/// The maximum number of commands that can be queued on a single connection in one
+		/// block.
+		#[pallet::constant]
+		type CommandsPerConnectionPerBlock: Get<u32>;
+
+		/// The maximum number of commands awaiting their response on a single connection. The
+		/// offchain worker holds further commands back until some of them are answered.
+		#[pallet::constant]
+		type MaxInFlightPerConnection: Get<u32>;
+
+		/// Number of the latest settled commands of a connection its health is judged on.
+		#[pallet::constant]
+		type HealthWindow: Get<u32>;
+
+		/// Share of the latest settled commands that have to complete for a connection to be
+		/// `Healthy`, below it the connection is `Degraded`.
+		#[pallet::constant]
+		type DegradedBelow: Get<Percent>;
+
+		/// Share of the latest settled commands below which a connection is `Unhealthy`.
+		#[pallet::constant]
+		type UnhealthyBelow: Get<Percent>;
+
+		/// Number of blocks an `Active` connection may go without a response or heartbeat before
+		/// it is disconnected.
+		///
+		/// The offchain worker pings edge servers that have been quiet for 10 blocks, this should
+		/// leave it a few attempts.
+		#[pallet::constant]
+		type StaleConnectionBlocks: Get<BlockNumberFor<Self>>;
+
+		/// The minimum number of blocks between two reports of the same connection being
+		/// unreachable.
+		#[pallet::constant]
+		type UnreachableReportInterval: Get<BlockNumberFor<Self>>;
+
+		/// Number of commands in the queue of a connection from which `QueueDepthHigh` is
+		/// raised. Commands count until they are pruned, like for `MaxQueuedCommands`.
+		#[pallet::constant]
+		type QueueDepthAlertThreshold: Get<u32>;
+
+		/// The minimum number of blocks between two `QueueDepthHigh` alerts of the same
+		/// connection.
+		#[pallet::constant]
+		type QueueDepthAlertInterval: Get<BlockNumberFor<Self>>;
+
+		/// The currency connection deposits and command fees are paid in.
+		type Currency: ReservableCurrency<Self::AccountId>;
+
+		/// Number of blocks an account has to wait between creating two connections, zero lets
+		/// it create them back to back.
+		#[pallet::constant]
+		type ConnectionCreationCooldown: Get<BlockNumberFor<Self>>;
+
+		/// The amount reserved from the owner for as long as a connection exists.
+		#[pallet::constant]
+		type ConnectionDeposit: Get<BalanceOf<Self>>;
+
+		/// The fee charged to the submitter of every command.
+		#[pallet::constant]
+		type CommandFee: Get<BalanceOf<Self>>;
+
+		/// The account command fees are paid to, they are burnt if there is none.
+		type FeeDestination: Get<Option<Self::AccountId>>;
+
+		/// Origin allowed to manage the edge-server domains connections may point at, and the
+		/// offchain worker authorities.
+		type GovernanceOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Origin of the edge server of a connection, allowed to submit the responses to its
+		/// commands directly, see [`EnsureConnectionOrigin`].
+		type ServerOrigin: EnsureOriginWithArg<
+			Self::RuntimeOrigin,
+			ConnectionId,
+			Success = Self::AccountId,
+		>;
+
+		/// Notified of every command response recorded on chain.
+		type OnResponse: OnCommandResponse<Self>;
+
+		/// Encodes command payloads before the offchain worker sends them to CyberHub, `()`
+		/// sends them as they are.
+		type PayloadCodec: PayloadCodec;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
 	}
 
 	// The pallet's hooks for offchain worker
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
-		fn offchain_worker(block_number: BlockNumberFor<T>) {
-			log::info!("Hello from offchain workers!");
+		fn on_initialize(block_number: BlockNumberFor<T>) -> Weight {
+			Self::reset_commands_in_block()
+				.saturating_add(Self::time_out_commands(block_number))
+				.saturating_add(Self::prune_commands(block_number))
+				.saturating_add(Self::disconnect_stale_connections(block_number))
+				.saturating_add(Self::expire_connections(block_number))
+				.saturating_add(Self::expire_leases(block_number))
+		}
 
-			let signer = Signer::<T, T::AuthorityId>::all_accounts();
-			if !signer.can_sign() {
-				log::error!("No local accounts available");
-				return
-			}
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: BlockNumberFor<T>) -> Result<(), sp_runtime::TryRuntimeError> {
+			Self::do_try_state()
+		}
 
+		fn offchain_worker(block_number: BlockNumberFor<T>) {
 			// Import `frame_system` and retrieve a block hash of the parent block.
 			let parent_hash = <system::Pallet<T>>::block_hash(block_number - 1u32.into());
-			log::debug!("Current block: {:?} (parent hash: {:?})", block_number, parent_hash);
+			log::debug!(
+				target: LOG_TARGET,
+				"worker started block={:?} parent_hash={:?}",
+				block_number,
+				parent_hash
+			);
 
-			let response: String = Self::fetch_response().unwrap_or_else(|e| {
-				log::error!("fetch_response error: {:?}", e);
-				"Failed".into()
-			});
-			log::info!("Response: {}", response);
+			if <OcwPaused<T>>::get() {
+				log::debug!(target: LOG_TARGET, "worker skipped outcome=paused");
+				return
+			}
 
-			// This will send both signed and unsigned transactions
-			// depending on the block number.
-			// Usually it's enough to choose one or the other.
+			if !T::OcwRunOnNonAuthorities::get() && !sp_io::offchain::is_validator() {
+				log::debug!(target: LOG_TARGET, "worker skipped outcome=not_a_validator");
+				return
+			}
+
+			// Responses are submitted the way `OcwSubmitStrategy` says.
 			let should_send = Self::choose_transaction_type(block_number);
-			let res = match should_send {
-				TransactionType::Signed => Self::fetch_response_and_send_signed(),
-				TransactionType::UnsignedForAny =>
-					Self::fetch_response_and_send_unsigned_for_any_account(block_number),
-				TransactionType::UnsignedForAll =>
-					Self::fetch_response_and_send_unsigned_for_all_accounts(block_number),
-				TransactionType::Raw => Self::fetch_response_and_send_raw_unsigned(block_number),
-				TransactionType::None => Ok(()),
-			};
-			if let Err(e) = res {
-				log::error!("Error: {}", e);
+			if let TransactionType::None = should_send {
+				return
+			}
+
+			// Deliver every pending command to the edge server its connection points at.
+			for (connection, info) in <Connection<T>>::iter() {
+				if info.status != ConnectionStatus::Active {
+					continue
+				}
+
+				let Ok(endpoints) = info
+					.endpoints
+					.iter()
+					.map(|url| sp_std::str::from_utf8(url))
+					.collect::<Result<Vec<_>, _>>()
+				else {
+					log::warn!(
+						target: LOG_TARGET,
+						"connection skipped connection={} outcome=non_utf8_endpoint",
+						connection.0
+					);
+					continue
+				};
+
+				// Make sure overlapping runs don't deliver the same commands twice. The lock is
+				// released when the guard goes out of scope at the end of this iteration.
+				let lock_key = Self::lock_key(connection);
+				let mut lock = StorageLock::<Time>::with_deadline(
+					&lock_key,
+					Duration::from_millis(LOCK_TIMEOUT_EXPIRATION),
+				);
+				let Ok(_guard) = lock.try_lock() else {
+					log::debug!(
+						target: LOG_TARGET,
+						"connection skipped connection={} outcome=locked",
+						connection.0
+					);
+					continue
+				};
+
+				// Check on edge servers that have not been heard from in a while, through the
+				// endpoint that last answered.
+				if block_number >= info.last_seen + HEARTBEAT_INTERVAL.into() {
+					let active = endpoints.get(info.active_endpoint as usize).or(endpoints.first());
+					if let Some(url) = active {
+						if let Err(e) = Self::ping_and_send_heartbeat(url, connection) {
+							log::warn!(
+								target: LOG_TARGET,
+								"heartbeat failed connection={} outcome=error error={:?}",
+								connection.0,
+								e
+							);
+						}
+					}
+				}
+
+				// Commands at or below the watermark were already answered by CyberHub, their
+				// response is on its way on chain. Once that command is settled there is nothing
+				// left to guard against and the watermark can go.
+				let mut watermark = Self::dispatched_watermark(connection);
+				if let Some(command_id) = watermark {
+					if !matches!(<CommandStatusOf<T>>::get(command_id), Some(s) if !s.is_final()) {
+						Self::clear_dispatched_watermark(connection);
+						watermark = None;
+					}
+				}
+
+				// Commands that missed their delivery window are pointless now, they are expired
+				// instead of being sent.
+				let (expired, pending): (Vec<_>, Vec<_>) =
+					Self::pending_commands(connection).into_iter().partition(|command| {
+						command.has_expired(block_number) &&
+							<CommandStatusOf<T>>::get(command.id) == Some(CommandStatus::Queued)
+					});
+				for command in expired {
+					if let Err(e) = Self::send_status_update(command.id, CommandStatus::Expired) {
+						log::error!(
+							target: LOG_TARGET,
+							"expiry failed connection={} command={} outcome=error error={:?}",
+							connection.0,
+							command.id,
+							e
+						);
+					}
+				}
+
+				let waiting = !pending.is_empty();
+				let due: Vec<_> = pending
+					.into_iter()
+					.filter(|command| {
+						!matches!(watermark, Some(command_id) if command.id <= command_id)
+					})
+					// Give CyberHub some rest after a failed request.
+					.filter(|command| Self::retry_due(command.id, block_number))
+					// Long-polled edge servers are only sent each command once.
+					.filter(|command| {
+						info.poll_mode == PollMode::Push ||
+							<CommandStatusOf<T>>::get(command.id) == Some(CommandStatus::Queued)
+					})
+					// Hold commands back while the edge server is still working on
+					// `MaxInFlightPerConnection` of them.
+					.take(
+						T::MaxInFlightPerConnection::get()
+							.saturating_sub(<InFlightCommands<T>>::get(connection)) as usize,
+					)
+					.collect();
+
+				// Backlogs are sent in batches of up to `MaxCommandsPerBatch` commands, each
+				// command of a batch still moves through its own statuses.
+				let batch_size = T::MaxCommandsPerBatch::get().max(1) as usize;
+				for batch in due.chunks(batch_size) {
+					let res = match info.poll_mode {
+						PollMode::Push => Self::submit_responses(should_send, block_number, || {
+							Self::fetch_responses(&endpoints, batch)
+						}),
+						PollMode::LongPoll => Self::dispatch_commands(&endpoints, batch),
+					};
+					if let Err(e) = res {
+						let ids: Vec<_> = batch.iter().map(|command| command.id).collect();
+						log::error!(
+							target: LOG_TARGET,
+							"delivery failed connection={} commands={:?} outcome=error error={:?}",
+							connection.0,
+							ids,
+							e
+						);
+					}
+				}
+
+				// Long-polled edge servers hand the results out on their own, for as long as
+				// commands are waiting on them.
+				if info.poll_mode == PollMode::LongPoll && waiting {
+					let res = Self::submit_responses(should_send, block_number, || {
+						Self::drain_responses(&endpoints, connection)
+					});
+					if let Err(e) = res {
+						log::error!(
+							target: LOG_TARGET,
+							"drain failed connection={} outcome=error error={:?}",
+							connection.0,
+							e
+						);
+					}
+				}
 			}
 		}
 	}
@@ -192,18 +604,41 @@ pub mod pallet {
 	// Public part of the pallet.
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		/// Create connection
+		/// Create a connection to the edge server reachable at `url`.
+		///
+		/// The URL must use the `http` or `https` scheme and fit in `MaxUrlLength` bytes. An
+		/// optional `label` helps the owner tell their connections apart. `ConnectionDeposit` is
+		/// reserved from the signer until the connection is removed. An account may create one
+		/// connection every `ConnectionCreationCooldown` blocks.
+		///
+		/// A connection given an `expires_at` block is removed once that block is reached,
+		/// which suits short compute sessions. The block must be in the future. `poll_mode`
+		/// says whether the results of its commands are pushed back by CyberHub or long-polled
+		/// by the offchain worker.
 		#[pallet::call_index(0)]
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1).ref_time())]
-		pub fn create_connection(origin: OriginFor<T>, connection: u32) -> DispatchResult {
+		#[pallet::weight(T::WeightInfo::create_connection())]
+		pub fn create_connection(
+			origin: OriginFor<T>,
+			url: Vec<u8>,
+			label: Option<Vec<u8>>,
+			expires_at: Option<BlockNumberFor<T>>,
+			poll_mode: PollMode,
+		) -> DispatchResult {
 			// Check that the extrinsic was signed and get the signer.
 			let who = ensure_signed(origin)?;
+			let now = <system::Pallet<T>>::block_number();
+			let cooldown = T::ConnectionCreationCooldown::get();
+			ensure!(
+				<LastConnectionCreatedAt<T>>::get(&who)
+					.is_none_or(|last| now.saturating_sub(last) >= cooldown),
+				Error::<T>::CreationOnCooldown
+			);
+			ensure!(expires_at.is_none_or(|at| at > now), Error::<T>::ExpiryInPast);
 
-			// Check that the connection does not already exist.
-			ensure!(!<Connection<T>>::exists(), Error::<T>::ConnectionAlreadyExists);
-
-			// Update storage.
-			<Connection<T>>::put(connection);
+			let deposit = T::ConnectionDeposit::get();
+			let connection =
+				Self::do_create_connection(&who, url, label, deposit, expires_at, poll_mode)?;
+			<LastConnectionCreatedAt<T>>::insert(&who, now);
 
 			// Emit an event.
 			Self::deposit_event(Event::ConnectionCreated { connection, who });
@@ -212,108 +647,266 @@ pub mod pallet {
 			Ok(())
 		}
 
-		// TODO:
-		// Create functions for:
-		// 1. send command
+		/// Queue a command to be sent to CyberHub over an existing connection.
+		///
+		/// The command is picked up from the queue by the offchain worker and sent with the
+		/// default `SendOptions`, at the lowest priority and without expiring. The signer pays
+		/// `CommandFee` for it.
+		///
+		/// Commands turned down early, because the connection does not exist or the signer may
+		/// not use it, are only charged for that check.
+		///
+		/// Delivery options are given to `send_raw_command` instead.
 		#[pallet::call_index(1)]
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1).ref_time())]
-		pub fn send_command(origin: OriginFor<T>, command: String) -> DispatchResult {
+		#[pallet::weight(T::WeightInfo::send_command())]
+		pub fn send_command(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			command: Vec<u8>,
+		) -> DispatchResultWithPostInfo {
+			// Retrieve the signer and check it is valid.
+			let who = ensure_signed(origin)?;
+			let command = Self::bound_command(command)?;
+
+			Self::do_send_command(who, connection, command, SendOptions::default())
+		}
+
+		/// Queue `payload` as a command over an existing connection, with every delivery option
+		/// given in one `SendOptions`.
+		///
+		/// Works like `send_command`, besides the `options`. Commands with a higher `priority`
+		/// are delivered first. A command that is still queued after `valid_until` is expired
+		/// instead of delivered, commands without it never expire. Commands that already expired
+		/// are only charged for that check.
+		///
+		/// Clients that resubmit on timeout can pass a `client_ref` of their choosing. As long as
+		/// the command first queued with it on `connection` is stored, submissions with the same
+		/// `client_ref` are not queued again nor charged, `CommandDeduplicated` points them at
+		/// that command instead. This holds even once the queue is full or the connection is
+		/// rate limited.
+		///
+		/// Fire-and-forget commands are sent `ack_only`: the edge server only acknowledges them,
+		/// which settles them as `Acknowledged` instead of waiting on a response.
+		///
+		/// Clients that need their commands queued in order number them with a `seq`, starting
+		/// at 0 and going up by one with every command they send, over any connection. A `seq`
+		/// the signer already used is turned down with `StaleSequence`, one that skips ahead with
+		/// `SequenceGap`.
+		#[pallet::call_index(39)]
+		#[pallet::weight(T::WeightInfo::send_command())]
+		pub fn send_raw_command(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			payload: Vec<u8>,
+			options: SendOptionsOf<T>,
+		) -> DispatchResultWithPostInfo {
 			// Retrieve the signer and check it is valid.
 			let who = ensure_signed(origin)?;
+			let payload = Self::bound_command(payload)?;
 
-			// Check that the connection exists.
-			ensure!(<Connection<T>>::exists(), Error::<T>::ConnectionDoesNotExist);
+			Self::do_send_command(who, connection, payload, options)
+		}
+
+		/// Register `payload` as a command template, owned by the signer, that commands can be
+		/// sent from with `send_command_from_template`.
+		///
+		/// Every `{n}` in the payload, with `n` a decimal index, stands for the `n`th parameter
+		/// the command is sent with. Other braces are kept as they are.
+		///
+		/// A `schema_hash` names the shape of the responses to the template's commands. It is
+		/// not checked on chain, but handed to CyberHub with every command and kept next to its
+		/// response, so that clients can fetch the matching schema and decode the response.
+		#[pallet::call_index(33)]
+		#[pallet::weight(T::WeightInfo::register_template())]
+		pub fn register_template(
+			origin: OriginFor<T>,
+			payload: BoundedVec<u8, T::MaxCommandLength>,
+			schema_hash: Option<H256>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
 
-			// Make sure the `command` == `ping`
-			ensure!(command == "ping", Error::<T>::InvalidCommand);
+			// Make sure there is something to send.
+			ensure!(!payload.is_empty(), Error::<T>::InvalidCommand);
 
-			// Convert the command to BoundedVec<u8, T::MaxStringLength>
-			let command_bounded_vec: BoundedVec<u8, T::MaxStringLength> =
-				command.clone().into_bytes().try_into().map_err(|_| Error::<T>::CommandTooLong)?;
+			let template_id = <NextTemplateId<T>>::get();
+			<NextTemplateId<T>>::put(template_id.wrapping_add(1));
+			let template = CommandTemplate { owner: who.clone(), payload, schema_hash };
+			<CommandTemplates<T>>::insert(template_id, template);
 
-			// Create the command tuple (Option<T::AccountId>, BoundedVec<u8, T::MaxStringLength>)
-			let command_tuple = (Some(who.clone()), command_bounded_vec);
+			// Emit an event.
+			Self::deposit_event(Event::TemplateRegistered { template_id, who });
 
-			// Try to get the current commands
-			let mut current_commands = <Commands<T>>::get();
+			// Return a successful DispatchResult
+			Ok(())
+		}
 
-			// Try to push the new command tuple, if there's room
-			if !current_commands.try_push(command_tuple).is_ok() {
-				return Err(Error::<T>::TooManyCommands.into());
-			}
+		/// Remove a command template owned by the signer.
+		///
+		/// Commands already sent from it are not affected.
+		#[pallet::call_index(34)]
+		#[pallet::weight(T::WeightInfo::remove_template())]
+		pub fn remove_template(origin: OriginFor<T>, template_id: TemplateId) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
 
-			// Update the storage.
-			<Commands<T>>::put(current_commands);
+			let template =
+				<CommandTemplates<T>>::get(template_id).ok_or(Error::<T>::UnknownTemplate)?;
+			ensure!(template.owner == who, Error::<T>::NotTemplateOwner);
+			<CommandTemplates<T>>::remove(template_id);
 
 			// Emit an event.
-			Self::deposit_event(Event::CommandSent { command, who });
+			Self::deposit_event(Event::TemplateRemoved { template_id, who });
 
 			// Return a successful DispatchResult
 			Ok(())
 		}
 
-		#[pallet::call_index(2)]
-		#[pallet::weight({0})]
-		/// Submit new response to the list.
+		/// Queue the command of template `template_id`, filled with `params`, over an existing
+		/// connection.
 		///
-		/// This method is a public function of the module and can be called from within
-		/// a transaction. It appends given `response` to current list of responses.
-		/// The `offchain worker` will create, sign & submit a transaction that
-		/// calls this function passing the response.
+		/// Any account may send commands from any template, the command is then queued like
+		/// with `send_command`, at the lowest priority.
+		#[pallet::call_index(35)]
+		#[pallet::weight(T::WeightInfo::send_command_from_template())]
+		pub fn send_command_from_template(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			template_id: TemplateId,
+			params: BoundedVec<BoundedVec<u8, T::MaxCommandLength>, T::MaxTemplateParams>,
+		) -> DispatchResultWithPostInfo {
+			// Retrieve the signer and check it is valid.
+			let who = ensure_signed(origin)?;
+
+			let template =
+				<CommandTemplates<T>>::get(template_id).ok_or(Error::<T>::UnknownTemplate)?;
+			let command = Self::fill_template(&template.payload, &params)?;
+
+			// Without a `client_ref`, the command is queued under the next id if at all.
+			let command_id = <NextCommandId<T>>::get();
+			let post_info =
+				Self::do_send_command(who, connection, command, SendOptions::default())?;
+			if let Some(schema_hash) = template.schema_hash {
+				<CommandSchemas<T>>::insert(command_id, schema_hash);
+			}
+
+			// Return a successful DispatchResultWithPostInfo
+			Ok(post_info)
+		}
+
+		/// Queue the same command on every active connection owned by the signer.
 		///
-		/// The transaction needs to be signed (see `ensure_signed`) check, so that the caller
-		/// pays a fee to execute it.
-		/// This makes sure that it's not easy (or rather cheap) to attack the chain by submitting
-		/// excessive transactions.
-		pub fn submit_response(origin: OriginFor<T>, response: String) -> DispatchResult {
+		/// Connections that are not active are left out, those whose queue is full or that hit
+		/// `CommandsPerConnectionPerBlock` are skipped and listed in the `CommandBroadcast` event.
+		/// The signer pays `CommandFee` for every command queued, and the commands go out at the
+		/// lowest priority.
+		///
+		/// The queued commands are grouped under a new `BroadcastId`, `BroadcastCompleted` is
+		/// emitted once all of them reached a final status.
+		#[pallet::call_index(21)]
+		#[pallet::weight(T::WeightInfo::broadcast_command(T::MaxConnectionsPerAccount::get()))]
+		pub fn broadcast_command(
+			origin: OriginFor<T>,
+			command: Vec<u8>,
+		) -> DispatchResultWithPostInfo {
 			// Retrieve the signer and check it is valid.
 			let who = ensure_signed(origin)?;
+			let command = Self::bound_command(command)?;
 
-			// Check that the connection exists.
-			ensure!(<Connection<T>>::exists(), Error::<T>::ConnectionDoesNotExist);
+			// Make sure there is something to send.
+			ensure!(!command.is_empty(), Error::<T>::InvalidCommand);
 
-			// Submit response received from CyberHub
-			Self::add_response(Some(who), response);
+			let connections = <ConnectionsOf<T>>::get(&who);
+			let (mut commands, mut skipped) = (Vec::new(), Vec::new());
+			for &connection in connections.iter() {
+				let Some(info) = <Connection<T>>::get(connection) else { continue };
+				if info.status != ConnectionStatus::Active {
+					continue
+				}
+				let queued = <CommandQueue<T>>::decode_len(connection).unwrap_or_default();
+				if queued >= T::MaxQueuedCommands::get() as usize ||
+					<CommandsInBlock<T>>::get(connection) >=
+						T::CommandsPerConnectionPerBlock::get()
+				{
+					skipped.push(connection);
+					continue
+				}
+				Self::charge_command_fee(&who)?;
+				let command_id = Self::do_queue_command(
+					who.clone(),
+					connection,
+					command.clone(),
+					0,
+					None,
+					false,
+				)?;
+				commands.push(command_id);
+			}
 
-			// Return a successful DispatchResult
-			Ok(().into())
+			let broadcast_id = <NextBroadcastId<T>>::get();
+			<NextBroadcastId<T>>::put(broadcast_id.wrapping_add(1));
+			let count = commands.len() as u32;
+			for &command_id in &commands {
+				<CommandBroadcast<T>>::insert(command_id, broadcast_id);
+			}
+
+			// Emit an event.
+			Self::deposit_event(Event::CommandBroadcast { broadcast_id, count, skipped });
+			if commands.is_empty() {
+				Self::deposit_event(Event::BroadcastCompleted { broadcast_id });
+			} else {
+				// Every connection gets at most one command, so this fits.
+				let commands = BoundedVec::truncate_from(commands);
+				<Broadcasts<T>>::insert(broadcast_id, Broadcast { commands, pending: count });
+			}
+
+			// Only the connections the signer owns were visited.
+			Ok(Some(T::WeightInfo::broadcast_command(connections.len() as u32)).into())
 		}
 
-		/// Submit new response to the list via unsigned transaction.
+		/// Receive the response to a queued command.
+		///
+		/// This method is a public function of the module and can be called from within
+		/// a transaction. It stores the given `response` against the command it answers.
+		/// The `offchain worker` will create, sign & submit a transaction that
+		/// calls this function passing the response.
 		///
-		/// Works exactly like the `submit_response` function, but since we allow sending the
-		/// transaction without a signature, and hence without paying any fees,
-		/// we need a way to make sure that only some transactions are accepted.
-		/// This function can be called only once every `T::UnsignedInterval` blocks.
-		/// Transactions that call that function are de-duplicated on the pool level
-		/// via `validate_unsigned` implementation and also are rendered invalid if
-		/// the function has already been called in current "session".
+		/// The transaction needs to be signed (see `ensure_signed`) check, so that the caller
+		/// pays a fee to execute it.
+		/// This makes sure that it's not easy (or rather cheap) to attack the chain by submitting
+		/// excessive transactions.
 		///
-		/// It's important to specify `weight` for unsigned calls as well, because even though
-		/// they don't charge fees, we still don't want a single block to contain unlimited
-		/// number of such transactions.
+		/// If the edge server registered a key, `server_signature` must be its signature over the
+		/// response, see `ConnectionInfo::server_pubkey`.
 		///
-		/// This example is not focused on correctness of the oracle itself, but rather its
-		/// purpose is to showcase offchain worker capabilities.
-		#[pallet::call_index(3)]
-		#[pallet::weight({0})]
-		pub fn submit_response_unsigned(
+		/// Only `OcwAuthorities` may submit responses, and only to commands that were dispatched.
+		#[pallet::call_index(2)]
+		#[pallet::weight(
+			T::WeightInfo::receive_response().saturating_add(T::OnResponse::weight())
+		)]
+		pub fn receive_response(
 			origin: OriginFor<T>,
-			_block_number: BlockNumberFor<T>,
-			response: String,
-		) -> DispatchResultWithPostInfo {
-			// This ensures that the function can only be called via unsigned transaction.
-			ensure_none(origin)?;
-			// Add the response to the on-chain list, but mark it as coming from an empty address.
-			Self::add_response(None, response);
-			// now increment the block number at which we expect next unsigned transaction.
-			let current_block = <system::Pallet<T>>::block_number();
-			<NextUnsignedAt<T>>::put(current_block + T::UnsignedInterval::get());
-			Ok(().into())
+			command_id: CommandId,
+			response: BoundedVec<u8, T::MaxResponseLength>,
+			server_signature: Option<sr25519::Signature>,
+		) -> DispatchResult {
+			// Only offchain worker authorities relay responses.
+			let who = Self::ensure_ocw_authority(origin)?;
+
+			// Store the response received from CyberHub
+			Self::add_response(Some(who), command_id, response, server_signature)
 		}
 
+		/// Submit the response to a command via an unsigned transaction carrying a signed payload.
+		///
+		/// Works like `receive_response`, but without fees. The payload is checked in
+		/// `validate_unsigned`, so only responses signed by one of the `OcwAuthorities` ever make
+		/// it into a block, and only one every `UnsignedInterval` blocks.
 		#[pallet::call_index(4)]
-		#[pallet::weight({0})]
+		#[pallet::weight(
+			T::WeightInfo::submit_response_unsigned_with_signed_payload()
+				.saturating_add(T::OnResponse::weight())
+		)]
 		pub fn submit_response_unsigned_with_signed_payload(
 			origin: OriginFor<T>,
 			response_payload: ResponsePayload<T::Public, BlockNumberFor<T>>,
@@ -321,26 +914,36 @@ pub mod pallet {
 		) -> DispatchResultWithPostInfo {
 			// This ensures that the function can only be called via unsigned transaction.
 			ensure_none(origin)?;
-			// Add the response to the on-chain list, but mark it as coming from an empty address.
-			Self::add_response(None, response_payload.response);
+			// Store the response, but mark it as coming from an empty address.
+			let response =
+				response_payload.response.try_into().map_err(|_| Error::<T>::ResponseTooLong)?;
+			Self::add_response(
+				None,
+				response_payload.command_id,
+				response,
+				response_payload.server_signature,
+			)?;
 			// now increment the block number at which we expect next unsigned transaction.
 			let current_block = <system::Pallet<T>>::block_number();
 			<NextUnsignedAt<T>>::put(current_block + T::UnsignedInterval::get());
 			Ok(().into())
 		}
 
-		// 3. remove_connection
+		/// Remove a connection owned by the signer, returning its deposit.
+		///
+		/// Commands still queued on the connection are dropped along with their responses, as is its
+		/// metadata.
 		#[pallet::call_index(5)]
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1).ref_time())]
-		pub fn remove_connection(origin: OriginFor<T>, connection: u32) -> DispatchResult {
+		#[pallet::weight(T::WeightInfo::remove_connection(T::MaxQueuedCommands::get()))]
+		pub fn remove_connection(origin: OriginFor<T>, connection: ConnectionId) -> DispatchResult {
 			// Check that the extrinsic was signed and get the signer.
 			let who = ensure_signed(origin)?;
 
-			// Check that the connection exists.
-			ensure!(<Connection<T>>::exists(), Error::<T>::ConnectionDoesNotExist);
+			// Check that the connection exists and belongs to the signer.
+			let info = <Connection<T>>::get(connection).ok_or(Error::<T>::ConnectionDoesNotExist)?;
+			ensure!(info.owner == who, Error::<T>::NotConnectionOwner);
 
-			// Update storage.
-			<Connection<T>>::kill();
+			Self::do_remove_connection(connection, info);
 
 			// Emit an event.
 			Self::deposit_event(Event::ConnectionRemoved { connection, who });
@@ -348,420 +951,4718 @@ pub mod pallet {
 			// Return a successful DispatchResult
 			Ok(())
 		}
-	}
 
-	// The pallet's runtime storage items.
-	#[pallet::storage]
-	#[pallet::getter(fn connection)]
-	pub type Connection<T> = StorageValue<_, u32, ValueQuery>;
+		/// Remove several connections owned by the signer at once, returning their deposits.
+		///
+		/// Every connection removed gets a `ConnectionRemoved` event, connections that do not exist
+		/// or belong to someone else are skipped and listed in the `ConnectionsRemoved` event that
+		/// follows.
+		#[pallet::call_index(32)]
+		#[pallet::weight(T::WeightInfo::remove_connections(connections.len() as u32))]
+		pub fn remove_connections(
+			origin: OriginFor<T>,
+			connections: BoundedVec<ConnectionId, T::MaxBulkRemove>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
 
-	/// A vector of recently submitted commands.
-	#[pallet::storage]
-	#[pallet::getter(fn commands)]
-	pub type Commands<T: Config> =
-		StorageValue<_, BoundedVec<(Option<T::AccountId>, BoundedVec<u8, T::MaxStringLength>), T::MaxCommand>, ValueQuery>;
+			let (mut count, mut skipped) = (0u32, Vec::new());
+			for connection in connections {
+				match <Connection<T>>::get(connection) {
+					Some(info) if info.owner == who => {
+						Self::do_remove_connection(connection, info);
+						let who = who.clone();
+						Self::deposit_event(Event::ConnectionRemoved { connection, who });
+						count.saturating_inc();
+					},
+					_ => skipped.push(connection),
+				}
+			}
 
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionsRemoved { who, count, skipped });
 
-	/// A vector of recently submitted responses.
-	#[pallet::storage]
-	#[pallet::getter(fn responses)]
-	pub(super) type Responses<T: Config> =
-		StorageValue<_, BoundedVec<(Option<T::AccountId>, BoundedVec<u8, T::MaxStringLength>), T::MaxResponses>, ValueQuery>;
+			// Return a successful DispatchResult
+			Ok(())
+		}
 
-	/// Defines the block when next unsigned transaction will be accepted.
-	///
-	/// To prevent spam of unsigned (and unpayed!) transactions on the network,
-	/// we only allow one transaction every `T::UnsignedInterval` blocks.
-	/// This storage entry defines when new transaction is going to be accepted.
-	#[pallet::storage]
-	#[pallet::getter(fn next_unsigned_at)]
-	pub(super) type NextUnsignedAt<T: Config> = StorageValue<_, BlockNumberFor<T>, ValueQuery>;
+		/// Change the status of a connection owned by the signer.
+		///
+		/// Only `Active` connections accept new commands and are served by the offchain worker.
+		#[pallet::call_index(7)]
+		#[pallet::weight(T::WeightInfo::set_connection_status())]
+		pub fn set_connection_status(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			status: ConnectionStatus,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
 
-	// Pallets use events to inform users when important changes are made.
-	#[pallet::event]
-	#[pallet::generate_deposit(pub(super) fn deposit_event)]
-	pub enum Event<T: Config> {
-		/// Event documentation should end with an array that provides descriptive names for event
-		/// parameters. [connection, who]
-		ConnectionCreated { connection: u32, who: T::AccountId },
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				// Give reactivated connections a fresh start before they can go stale again.
+				if status == ConnectionStatus::Active && info.status != ConnectionStatus::Active {
+					info.last_seen = <system::Pallet<T>>::block_number();
+				}
+				info.status = status;
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionStatusChanged { connection, who, status });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Temporarily stop an active connection from receiving commands.
+		#[pallet::call_index(8)]
+		#[pallet::weight(T::WeightInfo::pause_connection())]
+		pub fn pause_connection(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				ensure!(info.status != ConnectionStatus::Paused, Error::<T>::ConnectionAlreadyPaused);
+				ensure!(info.status == ConnectionStatus::Active, Error::<T>::ConnectionNotActive);
+				info.status = ConnectionStatus::Paused;
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionStatusChanged {
+				connection,
+				who,
+				status: ConnectionStatus::Paused,
+			});
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Let a paused connection receive commands again.
+		#[pallet::call_index(9)]
+		#[pallet::weight(T::WeightInfo::resume_connection())]
+		pub fn resume_connection(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				ensure!(info.status == ConnectionStatus::Paused, Error::<T>::ConnectionNotPaused);
+				info.status = ConnectionStatus::Active;
+				info.last_seen = <system::Pallet<T>>::block_number();
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionStatusChanged {
+				connection,
+				who,
+				status: ConnectionStatus::Active,
+			});
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Set or clear the label of a connection owned by the signer.
+		#[pallet::call_index(11)]
+		#[pallet::weight(T::WeightInfo::rename_connection())]
+		pub fn rename_connection(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			label: Option<Vec<u8>>,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+			let label = Self::bound_label(label)?;
+
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				Self::ensure_no_clash(&who, &info.endpoints, &label, Some(connection))?;
+				info.label = label.clone();
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionRenamed { connection, label });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Replace the tags of a connection owned by the signer.
+		///
+		/// Tags group edge servers by what they offer, such as `gpu` or `storage`, so that
+		/// commands can be aimed at a class of servers. An empty list clears them.
+		#[pallet::call_index(20)]
+		#[pallet::weight(T::WeightInfo::set_connection_tags())]
+		pub fn set_connection_tags(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			tags: Vec<Vec<u8>>,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+			let tags = Self::bound_tags(tags)?;
+
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				info.tags = tags.clone();
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionTagsSet { connection, tags });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Register the key the edge server behind a connection owned by the signer signs its
+		/// responses with, or `None` to accept unsigned responses again.
+		#[pallet::call_index(25)]
+		#[pallet::weight(T::WeightInfo::set_server_pubkey())]
+		pub fn set_server_pubkey(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			pubkey: Option<sr25519::Public>,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				info.server_pubkey = pubkey;
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::ServerPubkeySet { connection, pubkey });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Add `url` as the last fallback endpoint of a connection owned by the signer.
+		///
+		/// The URL is checked like the one a connection is created with. The offchain worker
+		/// only falls over to it once every endpoint before it failed to answer.
+		#[pallet::call_index(26)]
+		#[pallet::weight(T::WeightInfo::add_endpoint())]
+		pub fn add_endpoint(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			url: Vec<u8>,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+			let url = Self::bound_url(url)?;
+
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				ensure!(!info.endpoints.contains(&url), Error::<T>::DuplicateEndpoint);
+				// No other connection of the owner may point at the same edge server.
+				Self::ensure_no_clash(&who, sp_std::slice::from_ref(&url), &None, Some(connection))?;
+				info.endpoints.try_push(url.clone()).map_err(|_| Error::<T>::TooManyEndpoints)?;
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::EndpointAdded { connection, url });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Remove the endpoint at `index` from a connection owned by the signer.
+		///
+		/// The last endpoint of a connection cannot be removed. If the offchain worker was being
+		/// answered by the removed endpoint, it starts over from the primary one.
+		#[pallet::call_index(27)]
+		#[pallet::weight(T::WeightInfo::remove_endpoint())]
+		pub fn remove_endpoint(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			index: u32,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+
+			let mut url = Default::default();
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				ensure!((index as usize) < info.endpoints.len(), Error::<T>::UnknownEndpoint);
+				ensure!(info.endpoints.len() > 1, Error::<T>::LastEndpoint);
+				url = info.endpoints.remove(index as usize);
+				if info.active_endpoint > index {
+					info.active_endpoint -= 1;
+				} else if info.active_endpoint == index {
+					info.active_endpoint = 0;
+				}
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::EndpointRemoved { connection, url });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Record that the edge server behind a connection answered on the endpoint at `index`
+		/// instead of the one that answered before.
+		///
+		/// The offchain worker calls this when it had to fall over to another endpoint, or when
+		/// an earlier endpoint answers again. Only `OcwAuthorities` may.
+		#[pallet::call_index(28)]
+		#[pallet::weight(T::WeightInfo::record_endpoint_failover())]
+		pub fn record_endpoint_failover(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			index: u32,
+		) -> DispatchResult {
+			// Only offchain worker authorities see which endpoint answered.
+			Self::ensure_ocw_authority(origin)?;
+
+			let from_index =
+				<Connection<T>>::try_mutate(connection, |info| -> Result<u32, DispatchError> {
+					let info = info.as_mut().ok_or(Error::<T>::ConnectionDoesNotExist)?;
+					ensure!((index as usize) < info.endpoints.len(), Error::<T>::UnknownEndpoint);
+					ensure!(info.active_endpoint != index, Error::<T>::EndpointAlreadyActive);
+					Ok(sp_std::mem::replace(&mut info.active_endpoint, index))
+				})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::EndpointFailover { connection, from_index, to_index: index });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Set the metadata entry `key` of a connection owned by the signer to `value`.
+		///
+		/// A connection carries at most `MaxMetadataEntries` entries, overwriting an existing
+		/// key does not count towards the limit.
+		#[pallet::call_index(22)]
+		#[pallet::weight(T::WeightInfo::set_metadata())]
+		pub fn set_metadata(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			key: Vec<u8>,
+			value: Vec<u8>,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+			// Metadata edits count as changes to the connection.
+			Self::mutate_owned_connection(&who, connection, expected_version, |_| Ok(()))?;
+			let key = Self::bound_metadata_key(key)?;
+			let value: MetadataValueOf<T> =
+				value.try_into().map_err(|_| Error::<T>::MetadataValueTooLong)?;
+
+			if !<ConnectionMetadata<T>>::contains_key(connection, &key) {
+				<MetadataEntries<T>>::try_mutate(connection, |entries| {
+					ensure!(
+						*entries < T::MaxMetadataEntries::get(),
+						Error::<T>::TooManyMetadataEntries
+					);
+					*entries += 1;
+					Ok::<_, Error<T>>(())
+				})?;
+			}
+			<ConnectionMetadata<T>>::insert(connection, &key, &value);
+
+			// Emit an event.
+			Self::deposit_event(Event::MetadataUpdated { connection, key, value: Some(value) });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Remove the metadata entry `key` of a connection owned by the signer.
+		#[pallet::call_index(23)]
+		#[pallet::weight(T::WeightInfo::clear_metadata())]
+		pub fn clear_metadata(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			key: Vec<u8>,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+			// Metadata edits count as changes to the connection.
+			Self::mutate_owned_connection(&who, connection, expected_version, |_| Ok(()))?;
+			let key = Self::bound_metadata_key(key)?;
+
+			<ConnectionMetadata<T>>::take(connection, &key)
+				.ok_or(Error::<T>::UnknownMetadataKey)?;
+			<MetadataEntries<T>>::mutate(connection, |entries| *entries = entries.saturating_sub(1));
+
+			// Emit an event.
+			Self::deposit_event(Event::MetadataUpdated { connection, key, value: None });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Hand a connection owned by the signer over to `new_owner`.
+		///
+		/// Command access granted by the previous owner is revoked, and a lease they granted
+		/// ends. The connection deposit moves along with the connection and is returned to
+		/// whoever removes it. Should part of it no longer be reserved, only what is left moves.
+		#[pallet::call_index(10)]
+		#[pallet::weight(T::WeightInfo::transfer_connection())]
+		pub fn transfer_connection(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			new_owner: T::AccountId,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+			ensure!(who != new_owner, Error::<T>::CannotTransferToSelf);
+
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				// The new owner must not already have a connection to this edge server.
+				Self::ensure_no_clash(&new_owner, &info.endpoints, &info.label, None)?;
+				let remainder = T::Currency::repatriate_reserved(
+					&who,
+					&new_owner,
+					info.deposit,
+					BalanceStatus::Reserved,
+				)?;
+				info.deposit = info.deposit.saturating_sub(remainder);
+				info.owner = new_owner.clone();
+				info.authorized_senders = Default::default();
+				Ok(())
+			})?;
+
+			// Update storage.
+			<ConnectionsOf<T>>::mutate(&who, |connections| connections.retain(|id| *id != connection));
+			<ConnectionsOf<T>>::try_mutate(&new_owner, |connections| {
+				connections.try_push(connection).map_err(|_| Error::<T>::TooManyConnections)
+			})?;
+			let lease = <Leases<T>>::take(connection);
+
+			// Emit an event.
+			if let Some((lessee, _)) = lease {
+				Self::deposit_event(Event::LeaseReleased { connection, lessee });
+			}
+			Self::deposit_event(Event::ConnectionTransferred { connection, from: who, to: new_owner });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Move a command to the next step of its lifecycle.
+		///
+		/// The offchain worker calls this as it delivers the command to CyberHub. Commands can
+		/// only move forward, and `Completed` is reached by submitting a response instead.
+		/// Commands sent `ack_only` are `Acknowledged` instead of awaiting a response, the others
+		/// cannot be. Only their owner cancels commands, by flushing the queue.
+		///
+		/// Only `OcwAuthorities` may move commands along.
+		#[pallet::call_index(6)]
+		#[pallet::weight(T::WeightInfo::update_command_status())]
+		pub fn update_command_status(
+			origin: OriginFor<T>,
+			command_id: CommandId,
+			status: CommandStatus,
+		) -> DispatchResult {
+			// Only offchain worker authorities report on deliveries.
+			Self::ensure_ocw_authority(origin)?;
+
+			let current = <CommandStatusOf<T>>::get(command_id).ok_or(Error::<T>::UnknownCommandId)?;
+			ensure!(
+				!matches!(status, CommandStatus::Completed | CommandStatus::Cancelled) &&
+					current.can_transition_to(status),
+				Error::<T>::InvalidStatusTransition
+			);
+
+			let now = <system::Pallet<T>>::block_number();
+			if status == CommandStatus::Expired {
+				// Only commands that missed their delivery window can expire.
+				ensure!(
+					Self::queued_command(command_id).is_some_and(|command| command.has_expired(now)),
+					Error::<T>::InvalidStatusTransition
+				);
+			}
+			if matches!(status, CommandStatus::AwaitingResponse | CommandStatus::Acknowledged) {
+				let ack_only =
+					Self::queued_command(command_id).is_some_and(|command| command.ack_only);
+				ensure!(
+					ack_only == (status == CommandStatus::Acknowledged),
+					Error::<T>::InvalidStatusTransition
+				);
+			}
+			if status == CommandStatus::Dispatched {
+				Self::mutate_queued_command(command_id, |command| {
+					command.dispatched_at = Some(now);
+					Self::log_dispatch(now, command);
+				});
+			}
+			Self::set_command_status(command_id, status);
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Allow connections to point at edge servers hosted on `domain`.
+		///
+		/// Domains are matched case-insensitively against the host of the endpoint URL.
+		#[pallet::call_index(12)]
+		#[pallet::weight(T::WeightInfo::add_authorized_domain())]
+		pub fn add_authorized_domain(origin: OriginFor<T>, domain: Vec<u8>) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			let domain = Self::bound_domain(domain)?;
+			ensure!(
+				!<AuthorizedDomains<T>>::contains_key(&domain),
+				Error::<T>::DomainAlreadyAuthorized
+			);
+			<AuthorizedDomains<T>>::insert(&domain, ());
+
+			// Emit an event.
+			Self::deposit_event(Event::DomainAuthorized { domain });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Stop accepting new connections to edge servers hosted on `domain`.
+		///
+		/// Existing connections to the domain are left untouched.
+		#[pallet::call_index(13)]
+		#[pallet::weight(T::WeightInfo::remove_authorized_domain())]
+		pub fn remove_authorized_domain(origin: OriginFor<T>, domain: Vec<u8>) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			let domain = Self::bound_domain(domain)?;
+			ensure!(<AuthorizedDomains<T>>::contains_key(&domain), Error::<T>::DomainNotAuthorized);
+			<AuthorizedDomains<T>>::remove(&domain);
+
+			// Emit an event.
+			Self::deposit_event(Event::DomainRevoked { domain });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Allow the offchain worker signing as `who` to report on commands and connections.
+		#[pallet::call_index(41)]
+		#[pallet::weight(T::WeightInfo::add_ocw_authority())]
+		pub fn add_ocw_authority(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ensure!(!<OcwAuthorities<T>>::contains_key(&who), Error::<T>::AlreadyOcwAuthority);
+			<OcwAuthorities<T>>::insert(&who, ());
+
+			// Emit an event.
+			Self::deposit_event(Event::OcwAuthorityAdded { who });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Stop accepting reports from the offchain worker signing as `who`.
+		#[pallet::call_index(42)]
+		#[pallet::weight(T::WeightInfo::remove_ocw_authority())]
+		pub fn remove_ocw_authority(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ensure!(<OcwAuthorities<T>>::contains_key(&who), Error::<T>::NotOcwAuthority);
+			<OcwAuthorities<T>>::remove(&who);
+
+			// Emit an event.
+			Self::deposit_event(Event::OcwAuthorityRemoved { who });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Stop the offchain worker on every node, or start it again.
+		///
+		/// While paused, the offchain worker neither reaches out to edge servers nor submits
+		/// transactions. Commands stay queued and are picked up once it is started again.
+		#[pallet::call_index(40)]
+		#[pallet::weight(T::WeightInfo::set_ocw_paused())]
+		pub fn set_ocw_paused(origin: OriginFor<T>, paused: bool) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			<OcwPaused<T>>::put(paused);
+
+			// Emit an event.
+			Self::deposit_event(Event::OcwPauseToggled { paused });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Allow `who` to send commands over a connection owned by the signer.
+		#[pallet::call_index(14)]
+		#[pallet::weight(T::WeightInfo::grant_command_access())]
+		pub fn grant_command_access(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			who: T::AccountId,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let owner = ensure_signed(origin)?;
+
+			Self::mutate_owned_connection(&owner, connection, expected_version, |info| {
+				ensure!(
+					info.owner != who && !info.authorized_senders.contains(&who),
+					Error::<T>::AlreadyAuthorizedToSend
+				);
+				info.authorized_senders
+					.try_push(who.clone())
+					.map_err(|_| Error::<T>::TooManyAuthorizedSenders.into())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::CommandAccessGranted { connection, who });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Stop `who` from sending commands over a connection owned by the signer.
+		///
+		/// Commands `who` already queued are still delivered.
+		#[pallet::call_index(15)]
+		#[pallet::weight(T::WeightInfo::revoke_command_access())]
+		pub fn revoke_command_access(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			who: T::AccountId,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let owner = ensure_signed(origin)?;
+
+			Self::mutate_owned_connection(&owner, connection, expected_version, |info| {
+				let before = info.authorized_senders.len();
+				info.authorized_senders.retain(|sender| *sender != who);
+				ensure!(info.authorized_senders.len() != before, Error::<T>::NotAuthorizedToSend);
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::CommandAccessRevoked { connection, who });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Lease a connection to the signer until block `until`, for workloads that need it to
+		/// themselves.
+		///
+		/// Up to `until`, only the signer and the owner of the connection may send commands
+		/// over it. The signer must be allowed to send commands over the connection, and the
+		/// connection must not be leased already.
+		#[pallet::call_index(36)]
+		#[pallet::weight(T::WeightInfo::lease_connection())]
+		pub fn lease_connection(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			until: BlockNumberFor<T>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+
+			let info = <Connection<T>>::get(connection).ok_or(Error::<T>::ConnectionDoesNotExist)?;
+			ensure!(
+				info.owner == who || info.authorized_senders.contains(&who),
+				Error::<T>::NotAuthorizedToSend
+			);
+			ensure!(until > <system::Pallet<T>>::block_number(), Error::<T>::LeaseInPast);
+			ensure!(Self::active_lease(connection).is_none(), Error::<T>::ConnectionLeased);
+			<Leases<T>>::insert(connection, (&who, until));
+			Self::schedule::<LeaseExpiries<T>, _>(until, connection);
+
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionLeased { connection, lessee: who, until });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// End the lease of a connection before it runs out.
+		///
+		/// May be called by the lessee or by the owner of the connection.
+		#[pallet::call_index(37)]
+		#[pallet::weight(T::WeightInfo::release_lease())]
+		pub fn release_lease(origin: OriginFor<T>, connection: ConnectionId) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+
+			let (lessee, _) = Self::active_lease(connection).ok_or(Error::<T>::NotLeased)?;
+			ensure!(lessee == who || Self::owns(&who, connection), Error::<T>::NotLessee);
+			<Leases<T>>::remove(connection);
+
+			// Emit an event.
+			Self::deposit_event(Event::LeaseReleased { connection, lessee });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Receive the response to a command straight from the edge server of its connection.
+		///
+		/// Must come from the `ServerOrigin` of the connection, by default a transaction signed
+		/// with its `server_pubkey`, which then vouches for the response in place of the
+		/// `server_signature` of `receive_response`. This lets edge servers that can reach the
+		/// chain push their responses without the offchain worker.
+		#[pallet::call_index(38)]
+		#[pallet::weight(
+			T::WeightInfo::submit_server_response().saturating_add(T::OnResponse::weight())
+		)]
+		pub fn submit_server_response(
+			origin: OriginFor<T>,
+			command_id: CommandId,
+			response: BoundedVec<u8, T::MaxResponseLength>,
+		) -> DispatchResult {
+			let connection =
+				<CommandConnection<T>>::get(command_id).ok_or(Error::<T>::UnknownCommandId)?;
+			// Only the edge server of the command's connection may answer it.
+			let server = T::ServerOrigin::ensure_origin(origin, &connection)?;
+
+			Self::accept_response(Some(server), command_id, response)
+		}
+
+		/// Take a command off its connection's queue before it is sent to CyberHub.
+		///
+		/// May be called by whoever submitted the command or by the owner of its connection,
+		/// as long as the command is still `Queued`. The command fee is not refunded.
+		#[pallet::call_index(16)]
+		#[pallet::weight(T::WeightInfo::cancel_command())]
+		pub fn cancel_command(origin: OriginFor<T>, command_id: CommandId) -> DispatchResult {
+			// Retrieve the signer and check it is valid.
+			let who = ensure_signed(origin)?;
+
+			let connection =
+				<CommandConnection<T>>::get(command_id).ok_or(Error::<T>::UnknownCommandId)?;
+			let mut queue = <CommandQueue<T>>::get(connection);
+			let position = queue
+				.iter()
+				.position(|command| command.id == command_id)
+				.ok_or(Error::<T>::UnknownCommandId)?;
+			ensure!(
+				queue[position].submitter == who || Self::owns(&who, connection),
+				Error::<T>::NotCommandSubmitter
+			);
+			ensure!(
+				<CommandStatusOf<T>>::get(command_id) == Some(CommandStatus::Queued),
+				Error::<T>::CommandNotCancellable
+			);
+
+			queue.remove(position);
+			let depth = queue.len() as u32;
+			<CommandQueue<T>>::insert(connection, queue);
+			Self::remove_command(command_id);
+
+			// Emit an event.
+			Self::deposit_event(Event::CommandCancelled { command_id, connection });
+			Self::note_queue_depth(connection, depth);
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Cancel the commands of a connection that are still `Queued`, in queue order.
+		///
+		/// May be called by the owner of the connection or by `GovernanceOrigin`. Commands that
+		/// already left for CyberHub are left alone. At most `MaxFlushPerCall` commands are
+		/// cancelled per call, larger queues take several calls.
+		///
+		/// Cancelled commands settle as `Cancelled` and are pruned like any other settled
+		/// command, `CommandRetentionBlocks` later.
+		#[pallet::call_index(29)]
+		#[pallet::weight(T::WeightInfo::flush_command_queue(T::MaxFlushPerCall::get()))]
+		pub fn flush_command_queue(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+		) -> DispatchResultWithPostInfo {
+			let maybe_who = match T::GovernanceOrigin::try_origin(origin) {
+				Ok(_) => None,
+				Err(origin) => Some(ensure_signed(origin)?),
+			};
+			let info = <Connection<T>>::get(connection).ok_or(Error::<T>::ConnectionDoesNotExist)?;
+			if let Some(who) = maybe_who {
+				ensure!(info.owner == who, Error::<T>::NotConnectionOwner);
+			}
+
+			let flushed: Vec<_> = <CommandQueue<T>>::get(connection)
+				.iter()
+				.map(|command| command.id)
+				.filter(|&command_id| {
+					<CommandStatusOf<T>>::get(command_id) == Some(CommandStatus::Queued)
+				})
+				.take(T::MaxFlushPerCall::get() as usize)
+				.collect();
+			for &command_id in &flushed {
+				Self::set_command_status(command_id, CommandStatus::Cancelled);
+				Self::deposit_event(Event::CommandCancelled { command_id, connection });
+			}
+
+			// Emit an event.
+			let count = flushed.len() as u32;
+			Self::deposit_event(Event::QueueFlushed { connection, count });
+
+			// Only charge for the commands that were actually cancelled.
+			Ok(Some(T::WeightInfo::flush_command_queue(count)).into())
+		}
+
+		/// Point the primary endpoint of a connection owned by the signer at `new_url`, for an
+		/// edge server that moved hosts.
+		///
+		/// The URL is checked like the one a connection is created with. The offchain worker
+		/// starts over from the new primary endpoint on its next run, commands that are still
+		/// pending go there. A `poll_mode` switches the connection over to it, for an edge
+		/// server that moved to a host serving results another way. Disconnected connections
+		/// cannot be updated.
+		#[pallet::call_index(31)]
+		#[pallet::weight(T::WeightInfo::update_endpoint())]
+		pub fn update_endpoint(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+			new_url: Vec<u8>,
+			poll_mode: Option<PollMode>,
+			expected_version: Option<u32>,
+		) -> DispatchResult {
+			// Check that the extrinsic was signed and get the signer.
+			let who = ensure_signed(origin)?;
+			let new_url = Self::bound_url(new_url)?;
+
+			let mut old_url = Default::default();
+			Self::mutate_owned_connection(&who, connection, expected_version, |info| {
+				ensure!(
+					info.status != ConnectionStatus::Disconnected,
+					Error::<T>::ConnectionNotActive
+				);
+				ensure!(!info.endpoints.contains(&new_url), Error::<T>::DuplicateEndpoint);
+				// No other connection of the owner may point at the same edge server.
+				Self::ensure_no_clash(
+					&who,
+					sp_std::slice::from_ref(&new_url),
+					&None,
+					Some(connection),
+				)?;
+				let primary = info.endpoints.get_mut(0).ok_or(Error::<T>::UnknownEndpoint)?;
+				old_url = sp_std::mem::replace(primary, new_url.clone());
+				info.active_endpoint = 0;
+				if let Some(poll_mode) = poll_mode {
+					info.poll_mode = poll_mode;
+				}
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::EndpointUpdated { connection, old_url, new_url });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Put a command that `Failed` or `TimedOut` back on its connection's queue.
+		///
+		/// May be called by whoever submitted the command or by the owner of its connection. The
+		/// command is sent again as it was, without charging the command fee again, and its
+		/// failed requests are forgotten.
+		#[pallet::call_index(30)]
+		#[pallet::weight(T::WeightInfo::retry_command())]
+		pub fn retry_command(origin: OriginFor<T>, command_id: CommandId) -> DispatchResult {
+			// Retrieve the signer and check it is valid.
+			let who = ensure_signed(origin)?;
+
+			let connection =
+				<CommandConnection<T>>::get(command_id).ok_or(Error::<T>::UnknownCommandId)?;
+			let command = Self::queued_command(command_id).ok_or(Error::<T>::UnknownCommandId)?;
+			ensure!(
+				command.submitter == who || Self::owns(&who, connection),
+				Error::<T>::NotCommandSubmitter
+			);
+			ensure!(
+				matches!(
+					<CommandStatusOf<T>>::get(command_id),
+					Some(CommandStatus::Failed | CommandStatus::TimedOut)
+				),
+				Error::<T>::CommandNotRetryable
+			);
+
+			Self::mutate_queued_command(command_id, |command| {
+				command.dispatched_at = None;
+				command.settled_at = None;
+			});
+			<Responses<T>>::remove(command_id);
+			<ResponseHashes<T>>::remove(command_id);
+			<CommandErrors<T>>::remove(command_id);
+			// The broadcast the command belongs to waits for it again.
+			if let Some(broadcast_id) = <CommandBroadcast<T>>::get(command_id) {
+				<Broadcasts<T>>::mutate(broadcast_id, |broadcast| {
+					if let Some(broadcast) = broadcast {
+						broadcast.pending.saturating_inc();
+					}
+				});
+			}
+			Self::set_command_status(command_id, CommandStatus::Queued);
+			// Failed requests are counted off chain, the count goes once this block is imported.
+			sp_io::offchain_index::clear(&Self::retry_key(command_id));
+
+			// Emit an event.
+			Self::deposit_event(Event::CommandRequeued { command_id, connection });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Remove any connection, whoever owns it.
+		///
+		/// Meant for tearing down malicious or dead connections. The deposit goes back to the
+		/// owner and the queued commands are dropped, as with `remove_connection`.
+		#[pallet::call_index(18)]
+		#[pallet::weight(T::WeightInfo::force_remove_connection(T::MaxQueuedCommands::get()))]
+		pub fn force_remove_connection(
+			origin: OriginFor<T>,
+			connection: ConnectionId,
+		) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			let info = <Connection<T>>::get(connection).ok_or(Error::<T>::ConnectionDoesNotExist)?;
+			let owner = info.owner.clone();
+			Self::do_remove_connection(connection, info);
+
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionForceRemoved { connection, owner });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Record that the edge server behind a connection is alive.
+		///
+		/// The offchain worker calls this once the endpoint answered its health check, so only
+		/// `OcwAuthorities` may.
+		#[pallet::call_index(17)]
+		#[pallet::weight(T::WeightInfo::heartbeat())]
+		pub fn heartbeat(origin: OriginFor<T>, connection: ConnectionId) -> DispatchResult {
+			// Only offchain worker authorities vouch for edge servers.
+			Self::ensure_ocw_authority(origin)?;
+
+			let block = <system::Pallet<T>>::block_number();
+			<Connection<T>>::try_mutate(connection, |info| -> DispatchResult {
+				let info = info.as_mut().ok_or(Error::<T>::ConnectionDoesNotExist)?;
+				info.last_seen = block;
+				Ok(())
+			})?;
+
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionHeartbeat { connection, block });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Record that the edge server behind a connection could not be reached.
+		///
+		/// The offchain worker calls this when a request to the endpoint fails before getting an
+		/// answer. A connection is reported at most once every `UnreachableReportInterval`
+		/// blocks, and only by `OcwAuthorities`.
+		#[pallet::call_index(24)]
+		#[pallet::weight(T::WeightInfo::report_unreachable())]
+		pub fn report_unreachable(origin: OriginFor<T>, connection: ConnectionId) -> DispatchResult {
+			// Only offchain worker authorities speak for the edge servers they could not reach.
+			Self::ensure_ocw_authority(origin)?;
+			ensure!(<Connection<T>>::contains_key(connection), Error::<T>::ConnectionDoesNotExist);
+
+			let block = <system::Pallet<T>>::block_number();
+			ensure!(
+				Self::unreachable_report_due(<LastUnreachableReport<T>>::get(connection), block),
+				Error::<T>::UnreachableReportTooSoon
+			);
+			<LastUnreachableReport<T>>::insert(connection, block);
+
+			// Emit an event.
+			Self::deposit_event(Event::ConnectionUnreachable { connection, block });
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+
+		/// Fail a command with the error CyberHub reported for it.
+		///
+		/// The offchain worker calls this when CyberHub answers a command with an error
+		/// envelope. The error body is kept in `CommandErrors` until the command is pruned.
+		///
+		/// Only `OcwAuthorities` may fail commands.
+		#[pallet::call_index(19)]
+		#[pallet::weight(T::WeightInfo::report_command_error())]
+		pub fn report_command_error(
+			origin: OriginFor<T>,
+			command_id: CommandId,
+			error: BoundedVec<u8, T::MaxResponseLength>,
+		) -> DispatchResult {
+			// Only offchain worker authorities relay what CyberHub answered.
+			Self::ensure_ocw_authority(origin)?;
+
+			let current = <CommandStatusOf<T>>::get(command_id).ok_or(Error::<T>::UnknownCommandId)?;
+			ensure!(
+				current.can_transition_to(CommandStatus::Failed),
+				Error::<T>::InvalidStatusTransition
+			);
+
+			<CommandErrors<T>>::insert(command_id, error);
+			Self::set_command_status(command_id, CommandStatus::Failed);
+
+			// Return a successful DispatchResult
+			Ok(())
+		}
+	}
+
+	// The pallet's runtime storage items.
+
+	/// Connections between Cyborg blockchain and external edge servers, keyed by id.
+	///
+	/// NOTE: this used to be a `StorageValue` holding a bare `u32`, see
+	/// [`migrations::v1::MigrateToV1`].
+	#[pallet::storage]
+	pub type Connection<T: Config> =
+		StorageMap<_, Blake2_128Concat, ConnectionId, ConnectionInfo<T>, OptionQuery>;
+
+	/// Index of the connections owned by each account.
+	#[pallet::storage]
+	#[pallet::getter(fn connections_of)]
+	pub type ConnectionsOf<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<ConnectionId, T::MaxConnectionsPerAccount>,
+		ValueQuery,
+	>;
+
+	/// Block in which each account last created a connection, for `ConnectionCreationCooldown`.
+	#[pallet::storage]
+	pub type LastConnectionCreatedAt<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumberFor<T>, OptionQuery>;
+
+	/// Free-form metadata the owners attach to their connections, such as the region an edge
+	/// server runs in.
+	#[pallet::storage]
+	#[pallet::getter(fn connection_metadata)]
+	pub type ConnectionMetadata<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		ConnectionId,
+		Blake2_128Concat,
+		MetadataKeyOf<T>,
+		MetadataValueOf<T>,
+		OptionQuery,
+	>;
+
+	/// Block at which each connection was last reported unreachable.
+	#[pallet::storage]
+	pub type LastUnreachableReport<T: Config> =
+		StorageMap<_, Blake2_128Concat, ConnectionId, BlockNumberFor<T>, OptionQuery>;
+
+	/// Block at which each connection last raised `QueueDepthHigh`, and whether its queue is
+	/// still above `QueueDepthAlertThreshold` since.
+	#[pallet::storage]
+	pub type QueueDepthAlert<T: Config> =
+		StorageMap<_, Blake2_128Concat, ConnectionId, (BlockNumberFor<T>, bool), OptionQuery>;
+
+	/// How many `ConnectionMetadata` entries each connection has.
+	#[pallet::storage]
+	pub type MetadataEntries<T: Config> =
+		StorageMap<_, Blake2_128Concat, ConnectionId, u32, ValueQuery>;
+
+	/// Lowercased edge-server domains that connections may point at.
+	#[pallet::storage]
+	pub type AuthorizedDomains<T: Config> =
+		StorageMap<_, Blake2_128Concat, BoundedVec<u8, T::MaxUrlLength>, (), OptionQuery>;
+
+	/// Accounts of the offchain worker keys allowed to submit responses and report on commands
+	/// and connections.
+	#[pallet::storage]
+	pub type OcwAuthorities<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
+
+	/// Whether the offchain worker is stopped on every node, see `set_ocw_paused`.
+	#[pallet::storage]
+	#[pallet::getter(fn ocw_paused)]
+	pub type OcwPaused<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+	/// Number of connections, whatever their status.
+	#[pallet::storage]
+	pub type TotalConnectionCount<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// Number of `Active` connections.
+	#[pallet::storage]
+	pub type ActiveConnectionCount<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// Number of commands whose status is `Queued`, over all connections.
+	#[pallet::storage]
+	pub type TotalQueuedCommands<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// Id of the next connection to be created.
+	#[pallet::storage]
+	#[pallet::getter(fn next_connection_id)]
+	pub type NextConnectionId<T: Config> = StorageValue<_, ConnectionId, ValueQuery>;
+
+	/// Ids of removed connections that new connections are given before `NextConnectionId`,
+	/// lowest first.
+	#[pallet::storage]
+	#[pallet::getter(fn free_connection_ids)]
+	pub type FreeConnectionIds<T: Config> =
+		StorageValue<_, BoundedVec<ConnectionId, T::MaxFreeConnectionIds>, ValueQuery>;
+
+	/// Generation the next connection given each id belongs to, bumped whenever the id is freed.
+	#[pallet::storage]
+	#[pallet::getter(fn connection_generation)]
+	pub type ConnectionGeneration<T: Config> =
+		StorageMap<_, Blake2_128Concat, ConnectionId, u32, ValueQuery>;
+
+	/// Commands waiting to be sent to CyberHub, per connection.
+	#[pallet::storage]
+	#[pallet::getter(fn command_queue)]
+	pub type CommandQueue<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		ConnectionId,
+		BoundedVec<Command<T>, T::MaxQueuedCommands>,
+		ValueQuery,
+	>;
+
+	/// Id of the next command to be queued.
+	#[pallet::storage]
+	#[pallet::getter(fn next_command_id)]
+	pub type NextCommandId<T: Config> = StorageValue<_, CommandId, ValueQuery>;
+
+	/// Id of the next broadcast.
+	#[pallet::storage]
+	#[pallet::getter(fn next_broadcast_id)]
+	pub type NextBroadcastId<T: Config> = StorageValue<_, BroadcastId, ValueQuery>;
+
+	/// The commands of each broadcast that are still stored.
+	#[pallet::storage]
+	#[pallet::getter(fn broadcast)]
+	pub type Broadcasts<T: Config> =
+		StorageMap<_, Blake2_128Concat, BroadcastId, Broadcast<T>, OptionQuery>;
+
+	/// The account each leased connection is leased to, and the block the lease runs until.
+	#[pallet::storage]
+	#[pallet::getter(fn lease)]
+	pub type Leases<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		ConnectionId,
+		(T::AccountId, BlockNumberFor<T>),
+		OptionQuery,
+	>;
+
+	/// Id of the next command template.
+	#[pallet::storage]
+	#[pallet::getter(fn next_template_id)]
+	pub type NextTemplateId<T: Config> = StorageValue<_, TemplateId, ValueQuery>;
+
+	/// Command templates registered with `register_template`.
+	#[pallet::storage]
+	#[pallet::getter(fn command_template)]
+	pub type CommandTemplates<T: Config> =
+		StorageMap<_, Blake2_128Concat, TemplateId, CommandTemplate<T>, OptionQuery>;
+
+	/// Schema of the responses to each command sent from a template with a `schema_hash`.
+	#[pallet::storage]
+	#[pallet::getter(fn command_schema)]
+	pub type CommandSchemas<T: Config> =
+		StorageMap<_, Blake2_128Concat, CommandId, H256, OptionQuery>;
+
+	/// Broadcast each command queued by `broadcast_command` belongs to.
+	#[pallet::storage]
+	#[pallet::getter(fn command_broadcast)]
+	pub type CommandBroadcast<T: Config> =
+		StorageMap<_, Blake2_128Concat, CommandId, BroadcastId, OptionQuery>;
+
+	/// Nonce of the next command sent over each connection.
+	#[pallet::storage]
+	#[pallet::getter(fn next_command_nonce)]
+	pub type NextCommandNonce<T: Config> =
+		StorageMap<_, Blake2_128Concat, ConnectionId, u64, ValueQuery>;
+
+	/// Number of commands `AwaitingResponse` on each connection.
+	#[pallet::storage]
+	#[pallet::getter(fn in_flight_commands)]
+	pub type InFlightCommands<T: Config> =
+		StorageMap<_, Blake2_128Concat, ConnectionId, u32, ValueQuery>;
+
+	/// Outcomes of the latest `HealthWindow` commands settled on each connection, oldest first
+	/// and `true` for those that completed.
+	#[pallet::storage]
+	#[pallet::getter(fn recent_outcomes)]
+	pub type RecentOutcomes<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		ConnectionId,
+		BoundedVec<bool, T::HealthWindow>,
+		ValueQuery,
+	>;
+
+	/// Connection each queued command was sent over.
+	#[pallet::storage]
+	#[pallet::getter(fn command_connection)]
+	pub type CommandConnection<T: Config> =
+		StorageMap<_, Blake2_128Concat, CommandId, ConnectionId, OptionQuery>;
+
+	/// The commands stored for each connection, whatever their status, so that the history of
+	/// a connection can be listed. Entries go along with the command itself.
+	#[pallet::storage]
+	pub type ConnectionCommands<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		ConnectionId,
+		Twox64Concat,
+		CommandId,
+		(),
+		OptionQuery,
+	>;
+
+	/// Command queued with each `client_ref` passed to `send_raw_command`, per connection.
+	#[pallet::storage]
+	#[pallet::getter(fn command_by_client_ref)]
+	pub type CommandByClientRef<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		ConnectionId,
+		Blake2_128Concat,
+		[u8; 32],
+		CommandId,
+		OptionQuery,
+	>;
+
+	/// `client_ref` each command was queued with, so that it is forgotten with the command.
+	#[pallet::storage]
+	pub type CommandClientRef<T: Config> =
+		StorageMap<_, Blake2_128Concat, CommandId, [u8; 32], OptionQuery>;
+
+	/// The last `seq` each account queued a command with, see `send_raw_command`.
+	#[pallet::storage]
+	#[pallet::getter(fn last_seq)]
+	pub type LastSeq<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, u64, OptionQuery>;
+
+	/// Number of commands queued on each connection in the current block, cleared in
+	/// `on_initialize`.
+	#[pallet::storage]
+	pub type CommandsInBlock<T: Config> =
+		StorageMap<_, Blake2_128Concat, ConnectionId, u32, ValueQuery>;
+
+	/// Commands awaiting a response that time out in each block, checked in `on_initialize`.
+	///
+	/// A block that is full pushes later commands to the next one. Entries of commands that got
+	/// dispatched again since are left for the hook to skip.
+	#[pallet::storage]
+	pub type CommandDeadlines<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<CommandId, T::MaxPrunePerBlock>,
+		ValueQuery,
+	>;
+
+	/// Settled commands that are pruned in each block, once `CommandRetentionBlocks` passed.
+	///
+	/// Like `CommandDeadlines`, a block that is full pushes later commands to the next one.
+	#[pallet::storage]
+	pub type CommandPrunes<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<CommandId, T::MaxPrunePerBlock>,
+		ValueQuery,
+	>;
+
+	/// The last connection `on_initialize` checked for staleness, the next block goes on from
+	/// there. Unset once every connection was checked, to start over.
+	#[pallet::storage]
+	pub type StaleCheckCursor<T: Config> = StorageValue<_, ConnectionId, OptionQuery>;
+
+	/// Connections that expire in each block, removed in `on_initialize`.
+	///
+	/// Like `CommandDeadlines`, a block that is full pushes later connections to the next one.
+	#[pallet::storage]
+	pub type ConnectionExpiries<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<ConnectionId, T::MaxPrunePerBlock>,
+		ValueQuery,
+	>;
+
+	/// Leases that run out in each block, released in `on_initialize`.
+	///
+	/// Like `CommandDeadlines`, a block that is full pushes later leases to the next one.
+	/// Entries of leases released or replaced since are left for the hook to skip.
+	#[pallet::storage]
+	pub type LeaseExpiries<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<ConnectionId, T::MaxPrunePerBlock>,
+		ValueQuery,
+	>;
+
+	/// Block of the latest dispatch log entry, and how many entries that block has.
+	#[pallet::storage]
+	pub type DispatchLogCursor<T: Config> =
+		StorageValue<_, (BlockNumberFor<T>, u32), OptionQuery>;
+
+	/// Current lifecycle status of each command.
+	#[pallet::storage]
+	#[pallet::getter(fn command_status)]
+	pub type CommandStatusOf<T: Config> =
+		StorageMap<_, Blake2_128Concat, CommandId, CommandStatus, OptionQuery>;
+
+	/// Responses received from CyberHub, keyed by the command they answer.
+	#[pallet::storage]
+	#[pallet::getter(fn responses)]
+	pub type Responses<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		CommandId,
+		BoundedVec<u8, T::MaxResponseLength>,
+		OptionQuery,
+	>;
+
+	/// Errors CyberHub reported for failed commands, keyed by the command they belong to.
+	#[pallet::storage]
+	#[pallet::getter(fn command_error)]
+	pub type CommandErrors<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		CommandId,
+		BoundedVec<u8, T::MaxResponseLength>,
+		OptionQuery,
+	>;
+
+	/// Hashes of the responses received from CyberHub while `HashResponses` is set, keyed by
+	/// the command they answer.
+	#[pallet::storage]
+	#[pallet::getter(fn response_hash)]
+	pub type ResponseHashes<T: Config> =
+		StorageMap<_, Blake2_128Concat, CommandId, ResponseHash, OptionQuery>;
+
+	/// Defines the block when next unsigned transaction will be accepted.
+	///
+	/// To prevent spam of unsigned (and unpayed!) transactions on the network,
+	/// we only allow one transaction every `T::UnsignedInterval` blocks.
+	/// This storage entry defines when new transaction is going to be accepted.
+	#[pallet::storage]
+	#[pallet::getter(fn next_unsigned_at)]
+	pub(super) type NextUnsignedAt<T: Config> = StorageValue<_, BlockNumberFor<T>, ValueQuery>;
+
+	/// Connections to seed at genesis.
+	#[pallet::genesis_config]
+	#[derive(frame_support::DefaultNoBound)]
+	pub struct GenesisConfig<T: Config> {
+		/// Domains edge servers may be hosted on.
+		pub authorized_domains: Vec<Vec<u8>>,
+		/// Accounts of the offchain worker keys allowed to submit responses.
+		pub ocw_authorities: Vec<T::AccountId>,
+		/// Pre-authorized edge servers, as `(owner, url)` pairs. No deposit is reserved for them.
+		pub connections: Vec<(T::AccountId, Vec<u8>)>,
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			for domain in &self.authorized_domains {
+				let domain = Pallet::<T>::bound_domain(domain.clone())
+					.expect("genesis domains must fit in MaxUrlLength");
+				<AuthorizedDomains<T>>::insert(domain, ());
+			}
+			for who in &self.ocw_authorities {
+				<OcwAuthorities<T>>::insert(who, ());
+			}
+			for (owner, url) in &self.connections {
+				Pallet::<T>::do_create_connection(
+					owner,
+					url.clone(),
+					None,
+					Zero::zero(),
+					None,
+					PollMode::Push,
+				)
+				.expect("genesis connections must be valid and unique per owner");
+			}
+		}
+	}
+
+	// Pallets use events to inform users when important changes are made.
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// Event documentation should end with an array that provides descriptive names for event
+		/// parameters. [connection, who]
+		ConnectionCreated { connection: ConnectionId, who: T::AccountId },
 		/// Event documentation should end with an array that provides descriptive names for event
 		/// parameters. [connection, who]
-		ConnectionRemoved { connection: u32, who: T::AccountId },
-		/// Event generated when a new command is sent to CyberHub.
-		/// [command, who]
-		CommandSent { command: String, who: T::AccountId },
+		ConnectionRemoved { connection: ConnectionId, who: T::AccountId },
+		/// Event generated when an account removed several of its connections at once.
+		/// [who, count, skipped]
+		ConnectionsRemoved { who: T::AccountId, count: u32, skipped: Vec<ConnectionId> },
+		/// Event generated when the label of a connection changes.
+		/// [connection, label]
+		ConnectionRenamed {
+			connection: ConnectionId,
+			label: Option<BoundedVec<u8, T::MaxLabelLength>>,
+		},
+		/// Event generated when a connection is handed over to another account.
+		/// [connection, from, to]
+		ConnectionTransferred { connection: ConnectionId, from: T::AccountId, to: T::AccountId },
+		/// Event generated when the owner changes the status of a connection.
+		/// [connection, who, status]
+		ConnectionStatusChanged {
+			connection: ConnectionId,
+			who: T::AccountId,
+			status: ConnectionStatus,
+		},
+		/// Event generated when a new command is queued for CyberHub.
+		/// [connection, command_id, nonce]
+		CommandQueued { connection: ConnectionId, command_id: CommandId, nonce: u64 },
+		/// Event generated when a command was submitted again with the `client_ref` of a command
+		/// that is still stored, and was not queued a second time.
+		/// [connection, client_ref, command_id]
+		CommandDeduplicated {
+			connection: ConnectionId,
+			client_ref: [u8; 32],
+			command_id: CommandId,
+		},
+		/// Event generated when the submitter of a command paid the command fee.
+		/// [who, amount]
+		CommandFeePaid { who: T::AccountId, amount: BalanceOf<T> },
 		/// Event generated when a response is received from CyberHub.
-		/// [response, maybe_who]
-		NewResponse { response: String, maybe_who: Option<T::AccountId> },
+		///
+		/// `response_hash` is the `ResponseHasher` hash of the body, against which light clients
+		/// can check a body fetched off chain.
+		/// [command_id, maybe_who, response_hash]
+		ResponseReceived {
+			command_id: CommandId,
+			maybe_who: Option<T::AccountId>,
+			response_hash: H256,
+		},
+		/// Event generated when a command moves to a new lifecycle status.
+		/// [command_id, status]
+		CommandStatusChanged { command_id: CommandId, status: CommandStatus },
+		/// Event generated when a command could not be handled by CyberHub.
+		/// [command_id]
+		CommandFailed { command_id: CommandId },
+		/// Event generated when a command got no response within `CommandTimeout` blocks.
+		/// [command_id]
+		CommandTimedOut { command_id: CommandId },
+		/// Event generated when a command was not dispatched before its `valid_until` block.
+		/// [command_id]
+		CommandExpired { command_id: CommandId },
+		/// Event generated when CyberHub acknowledged a command sent `ack_only`. [command_id]
+		CommandAcknowledged { command_id: CommandId },
+		/// Event generated when the offchain worker delivered a command to CyberHub.
+		/// [command_id, connection]
+		CommandDispatched { command_id: CommandId, connection: ConnectionId },
+		/// Event generated when connections may point at a new domain.
+		/// [domain]
+		DomainAuthorized { domain: BoundedVec<u8, T::MaxUrlLength> },
+		/// Event generated when a domain is no longer accepted for new connections.
+		/// [domain]
+		DomainRevoked { domain: BoundedVec<u8, T::MaxUrlLength> },
+		/// Event generated when an account may send commands over a connection.
+		/// [connection, who]
+		CommandAccessGranted { connection: ConnectionId, who: T::AccountId },
+		/// Event generated when an account may no longer send commands over a connection.
+		/// [connection, who]
+		CommandAccessRevoked { connection: ConnectionId, who: T::AccountId },
+		/// Event generated when a command was taken off the queue before being delivered.
+		/// [command_id, connection]
+		CommandCancelled { command_id: CommandId, connection: ConnectionId },
+		/// Event generated when the edge server behind a connection answered its health check.
+		/// [connection, block]
+		ConnectionHeartbeat { connection: ConnectionId, block: BlockNumberFor<T> },
+		/// Event generated when the offchain worker could not reach the edge server behind a
+		/// connection.
+		/// [connection, block]
+		ConnectionUnreachable { connection: ConnectionId, block: BlockNumberFor<T> },
+		/// Event generated when a connection was disconnected because its edge server has not
+		/// been seen for `StaleConnectionBlocks` blocks.
+		/// [connection, last_seen]
+		ConnectionStaled { connection: ConnectionId, last_seen: BlockNumberFor<T> },
+		/// Event generated when a connection was removed because its `expires_at` block was
+		/// reached.
+		/// [connection, who]
+		ConnectionExpired { connection: ConnectionId, who: T::AccountId },
+		/// Event generated when the queued commands of a connection were cancelled at once.
+		/// [connection, count]
+		QueueFlushed { connection: ConnectionId, count: u32 },
+		/// Event generated when the queue of a connection reached `QueueDepthAlertThreshold`
+		/// commands.
+		/// [connection, depth]
+		QueueDepthHigh { connection: ConnectionId, depth: u32 },
+		/// Event generated when the queue of a connection fell back below
+		/// `QueueDepthAlertThreshold` commands.
+		/// [connection, depth]
+		QueueDepthRecovered { connection: ConnectionId, depth: u32 },
+		/// Event generated when a failed command was put back on its connection's queue.
+		/// [command_id, connection]
+		CommandRequeued { command_id: CommandId, connection: ConnectionId },
+		/// Event generated when governance removed a connection.
+		/// [connection, owner]
+		ConnectionForceRemoved { connection: ConnectionId, owner: T::AccountId },
+		/// Event generated when a command was queued on the active connections of an account.
+		/// [broadcast_id, count, skipped]
+		CommandBroadcast { broadcast_id: BroadcastId, count: u32, skipped: Vec<ConnectionId> },
+		/// Event generated when every command of a broadcast reached a final status.
+		/// [broadcast_id]
+		BroadcastCompleted { broadcast_id: BroadcastId },
+		/// Event generated when a command template was registered.
+		/// [template_id, who]
+		TemplateRegistered { template_id: TemplateId, who: T::AccountId },
+		/// Event generated when a command template was removed.
+		/// [template_id, who]
+		TemplateRemoved { template_id: TemplateId, who: T::AccountId },
+		/// Event generated when a connection was leased.
+		/// [connection, lessee, until]
+		ConnectionLeased {
+			connection: ConnectionId,
+			lessee: T::AccountId,
+			until: BlockNumberFor<T>,
+		},
+		/// Event generated when the lease of a connection was released or ran out.
+		/// [connection, lessee]
+		LeaseReleased { connection: ConnectionId, lessee: T::AccountId },
+		/// Event generated when the tags of a connection changed.
+		/// [connection, tags]
+		ConnectionTagsSet { connection: ConnectionId, tags: TagsOf<T> },
+		/// Event generated when the key the edge server of a connection signs its responses with
+		/// was set or cleared.
+		/// [connection, pubkey]
+		ServerPubkeySet { connection: ConnectionId, pubkey: Option<sr25519::Public> },
+		/// Event generated when a fallback endpoint was added to a connection.
+		/// [connection, url]
+		EndpointAdded { connection: ConnectionId, url: UrlOf<T> },
+		/// Event generated when an endpoint was removed from a connection.
+		/// [connection, url]
+		EndpointRemoved { connection: ConnectionId, url: UrlOf<T> },
+		/// Event generated when the primary endpoint of a connection was pointed at another URL.
+		/// [connection, old_url, new_url]
+		EndpointUpdated { connection: ConnectionId, old_url: UrlOf<T>, new_url: UrlOf<T> },
+		/// Event generated when the edge server behind a connection answered on another
+		/// endpoint than the one that answered before.
+		/// [connection, from_index, to_index]
+		EndpointFailover { connection: ConnectionId, from_index: u32, to_index: u32 },
+		/// Event generated when a metadata entry of a connection was set, or cleared if `value`
+		/// is `None`.
+		/// [connection, key, value]
+		MetadataUpdated {
+			connection: ConnectionId,
+			key: MetadataKeyOf<T>,
+			value: Option<MetadataValueOf<T>>,
+		},
+		/// Event generated when the offchain worker is stopped or started again.
+		/// [paused]
+		OcwPauseToggled { paused: bool },
+		/// Event generated when an offchain worker key may submit responses.
+		/// [who]
+		OcwAuthorityAdded { who: T::AccountId },
+		/// Event generated when an offchain worker key may no longer submit responses.
+		/// [who]
+		OcwAuthorityRemoved { who: T::AccountId },
+	}
+
+	// Errors inform users that something went wrong.
+	#[pallet::error]
+	pub enum Error<T> {
+		/// Returned if the connection already exists.
+		ConnectionAlreadyExists,
+		/// Returned if the connection does not exist.
+		ConnectionDoesNotExist,
+		/// Returned if the signer does not own the connection.
+		NotConnectionOwner,
+		/// Returned if the account already owns `MaxConnectionsPerAccount` connections.
+		TooManyConnections,
+		/// Returned if the account created a connection less than `ConnectionCreationCooldown`
+		/// blocks ago.
+		CreationOnCooldown,
+		/// Returned if every connection id has been handed out.
+		ConnectionIdExhausted,
+		/// Returned if a connection is created with an `expires_at` block that is not in the
+		/// future.
+		ExpiryInPast,
+		/// Returned if a connection is transferred to its current owner.
+		CannotTransferToSelf,
+		/// Returned if the signer cannot afford the connection deposit or the command fee.
+		InsufficientBalance,
+		/// Returned if the signer is neither the owner nor an authorized sender.
+		NotAuthorizedToSend,
+		/// Returned if the account may already send commands over the connection.
+		AlreadyAuthorizedToSend,
+		/// Returned if the connection already has `MaxAuthorizedSenders` authorized senders.
+		TooManyAuthorizedSenders,
+		/// Returned if the connection is not accepting commands.
+		ConnectionNotActive,
+		/// Returned if the connection is already paused.
+		ConnectionAlreadyPaused,
+		/// Returned if the connection is not paused.
+		ConnectionNotPaused,
+		/// Returned if the endpoint URL is longer than `MaxUrlLength`.
+		UrlTooLong,
+		/// Returned if the endpoint URL is not an `http` or `https` URL.
+		InvalidUrl,
+		/// Returned if the endpoint URL is not hosted on an authorized domain.
+		DomainNotAuthorized,
+		/// Returned if the domain is already authorized.
+		DomainAlreadyAuthorized,
+		/// Returned if the account is already an offchain worker authority.
+		AlreadyOcwAuthority,
+		/// Returned if the account is not an offchain worker authority.
+		NotOcwAuthority,
+		/// Returned if the domain is empty or longer than `MaxUrlLength`.
+		InvalidDomain,
+		/// Returned if the label is longer than `MaxLabelLength`.
+		LabelTooLong,
+		/// Returned if the command is longer than `MaxCommandLength`.
+		CommandTooLong,
+		/// Returned if the owner already has a connection with the same label.
+		DuplicateLabel,
+		/// Returned if the response is longer than `MaxResponseLength`.
+		ResponseTooLong,
+		/// Return error if the command is not valid.
+		InvalidCommand,
+		/// Returned if no command was queued with the given id.
+		UnknownCommandId,
+		/// Returned if the command has already reached a final status.
+		CommandAlreadyCompleted,
+		/// Returned if a response is submitted for a command that was not dispatched yet.
+		CommandNotDispatched,
+		/// Returned if the connection's command queue is full.
+		CommandQueueFull,
+		/// Returned if the command cannot move to the requested status.
+		InvalidStatusTransition,
+		/// Returned if the signer neither submitted the command nor owns its connection.
+		NotCommandSubmitter,
+		/// Returned if the command already left the queue for CyberHub.
+		CommandNotCancellable,
+		/// Returned if the command neither `Failed` nor `TimedOut`, so there is nothing to retry.
+		CommandNotRetryable,
+		/// Returned if a command is submitted with a `valid_until` block that already passed.
+		CommandAlreadyExpired,
+		/// Returned if a tag is empty or longer than `MaxTagLength`.
+		InvalidTag,
+		/// Returned if more than `MaxTagsPerConnection` tags are given.
+		TooManyTags,
+		/// Returned if the same tag is given twice.
+		DuplicateTag,
+		/// Returned if a metadata key is empty or longer than `MaxMetadataKeyLength`.
+		InvalidMetadataKey,
+		/// Returned if a metadata value is longer than `MaxMetadataValueLength`.
+		MetadataValueTooLong,
+		/// Returned if the connection already has `MaxMetadataEntries` metadata entries.
+		TooManyMetadataEntries,
+		/// Returned if the connection has no metadata under the given key.
+		UnknownMetadataKey,
+		/// Returned if `CommandsPerConnectionPerBlock` commands were already queued on the
+		/// connection in this block.
+		RateLimited,
+		/// Returned if the connection was reported unreachable less than
+		/// `UnreachableReportInterval` blocks ago.
+		UnreachableReportTooSoon,
+		/// Returned if a response is not signed by the key registered for the edge server.
+		InvalidResponseSignature,
+		/// Returned if the connection already has `MaxEndpoints` endpoints.
+		TooManyEndpoints,
+		/// Returned if the connection already has the endpoint.
+		DuplicateEndpoint,
+		/// Returned if the connection has no endpoint at the given index.
+		UnknownEndpoint,
+		/// Returned if the only endpoint of a connection is removed.
+		LastEndpoint,
+		/// Returned if the endpoint is already the one answering for the connection.
+		EndpointAlreadyActive,
+		/// Returned if the command template does not exist.
+		UnknownTemplate,
+		/// Returned if the signer does not own the command template.
+		NotTemplateOwner,
+		/// Returned if a command template refers to a parameter it was not given.
+		MissingTemplateParam,
+		/// Returned if a filled command template is longer than `MaxCommandLength`.
+		TemplateCommandTooLong,
+		/// Returned if the connection is leased to another account.
+		ConnectionLeased,
+		/// Returned if a lease is asked to run until a block that is not in the future.
+		LeaseInPast,
+		/// Returned if the connection is not leased.
+		NotLeased,
+		/// Returned if the signer is neither the lessee nor the owner of the connection.
+		NotLessee,
+		/// Returned if the connection changed since the version the signer expected.
+		VersionMismatch,
+		/// Returned if a command is sent with a `seq` the signer already used.
+		StaleSequence,
+		/// Returned if a command is sent with a `seq` that skips some of the signer's.
+		SequenceGap,
+	}
+
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		/// Validate unsigned call to this module.
+		///
+		/// By default unsigned transactions are disallowed, but implementing the validator
+		/// here we make sure that some particular calls (the ones produced by offchain worker)
+		/// are being whitelisted and marked as valid.
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			// Only one response per command is accepted into the pool, whichever way it is
+			// submitted, and responses to commands that are already settled are stale.
+			let valid_tx = |command_id: &CommandId| {
+				match <CommandStatusOf<T>>::get(command_id) {
+					Some(status) if !status.is_final() => {},
+					Some(_) => return InvalidTransaction::Stale.into(),
+					None => return InvalidTransaction::Call.into(),
+				}
+				ValidTransaction::with_tag_prefix("ocw-edge:")
+					.priority(UNSIGNED_TXS_PRIORITY)
+					.and_provides((b"submit_response", command_id))
+					.longevity(3)
+					.propagate(true)
+					.build()
+			};
+
+			match call {
+				Call::submit_response_unsigned_with_signed_payload {
+					ref response_payload,
+					ref signature
+				} => {
+					if !SignedPayload::<T>::verify::<T::AuthorityId>(response_payload, signature.clone()) {
+						return InvalidTransaction::BadProof.into();
+					}
+					// A valid signature only says who signed, that has to be an authority.
+					let signer = response_payload.public.clone().into_account();
+					if !<OcwAuthorities<T>>::contains_key(&signer) {
+						return InvalidTransaction::BadSigner.into();
+					}
+					// Only one unsigned response is accepted every `UnsignedInterval` blocks.
+					let block_number = response_payload.block_number;
+					if block_number < <NextUnsignedAt<T>>::get() {
+						return InvalidTransaction::Stale.into();
+					}
+					if block_number > <system::Pallet<T>>::block_number() {
+						return InvalidTransaction::Future.into();
+					}
+					valid_tx(&response_payload.command_id)
+				},
+				_ => InvalidTransaction::Call.into(),
+			}
+		}
+	}
+}
+
+/// Identifier of a connection between Cyborg blockchain and an external edge server.
+#[derive(
+	Encode, Decode, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug, TypeInfo,
+	MaxEncodedLen,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ConnectionId(pub u32);
+
+impl From<u32> for ConnectionId {
+	fn from(id: u32) -> Self {
+		ConnectionId(id)
+	}
+}
+
+impl From<ConnectionId> for u32 {
+	fn from(id: ConnectionId) -> Self {
+		id.0
+	}
+}
+
+/// Identifier of a command queued for CyberHub.
+pub type CommandId = u64;
+
+/// Identifier of the group of commands queued by a single `broadcast_command`.
+pub type BroadcastId = u64;
+
+/// Identifier of a command template.
+pub type TemplateId = u64;
+
+/// A CyberHub response bounded by `MaxResponseLength`.
+pub type ResponseOf<T> = BoundedVec<u8, <T as Config>::MaxResponseLength>;
+
+/// A connection tag bounded by `MaxTagLength`.
+pub type TagOf<T> = BoundedVec<u8, <T as Config>::MaxTagLength>;
+
+/// The tags of a connection, bounded by `MaxTagsPerConnection`.
+pub type TagsOf<T> = BoundedVec<TagOf<T>, <T as Config>::MaxTagsPerConnection>;
+
+/// An edge server endpoint URL bounded by `MaxUrlLength`.
+pub type UrlOf<T> = BoundedVec<u8, <T as Config>::MaxUrlLength>;
+
+/// The endpoints of a connection, in the order they are tried, bounded by `MaxEndpoints`.
+pub type EndpointsOf<T> = BoundedVec<UrlOf<T>, <T as Config>::MaxEndpoints>;
+
+/// A connection metadata key bounded by `MaxMetadataKeyLength`.
+pub type MetadataKeyOf<T> = BoundedVec<u8, <T as Config>::MaxMetadataKeyLength>;
+
+/// A connection metadata value bounded by `MaxMetadataValueLength`.
+pub type MetadataValueOf<T> = BoundedVec<u8, <T as Config>::MaxMetadataValueLength>;
+
+/// What CyberHub reported for a command in a response envelope.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+enum CommandOutcome {
+	/// The command succeeded, with the result body and the edge server's signature over it.
+	Ok(Vec<u8>, Option<sr25519::Signature>),
+	/// The command failed, with the error body.
+	Error(Vec<u8>),
+	/// CyberHub took the command on without a result.
+	Acknowledged,
+}
+
+/// The outcome CyberHub reported for one command of a batch request.
+type BatchResult = (CommandId, CommandOutcome);
+
+/// A response ready to be submitted, along with the edge server's signature over it.
+type SignedResponseOf<T> = (ResponseOf<T>, Option<sr25519::Signature>);
+
+/// The responses fetched from CyberHub, along with the commands they answer.
+type FetchedResponses<T> = Result<Vec<(CommandId, SignedResponseOf<T>)>, &'static str>;
+
+/// Lets other pallets react to command responses, without this pallet knowing about them.
+pub trait OnCommandResponse<T: Config> {
+	/// Called once the response to `command_id` has been recorded.
+	fn on_command_response(command_id: CommandId, response: &[u8]);
+
+	/// The weight of [`Self::on_command_response`], charged on top of every response
+	/// submission.
+	fn weight() -> Weight {
+		Weight::zero()
+	}
+}
+
+impl<T: Config> OnCommandResponse<T> for () {
+	fn on_command_response(_command_id: CommandId, _response: &[u8]) {}
+}
+
+/// Encodes command payloads for their trip to the edge server, to encrypt them to its key for
+/// instance.
+///
+/// Only what the offchain worker sends is encoded, the payload stored on chain stays as it was
+/// submitted. CyberHub takes commands as text, codecs with a binary output should encode it, as
+/// hex for instance.
+pub trait PayloadCodec {
+	/// Encodes `payload` for the edge server holding `server_pubkey`, `None` if the server
+	/// registered no key.
+	fn encode_for_transport(payload: &[u8], server_pubkey: Option<&sr25519::Public>) -> Vec<u8>;
+}
+
+impl PayloadCodec for () {
+	fn encode_for_transport(payload: &[u8], _server_pubkey: Option<&sr25519::Public>) -> Vec<u8> {
+		payload.to_vec()
+	}
+}
+
+/// Names the algorithm of a `Config::ResponseHasher`, which is stored with every hash it
+/// computes.
+pub trait ResponseHashAlgorithm {
+	/// The algorithm the hasher implements.
+	fn algorithm() -> HashAlgorithm;
+}
+
+impl ResponseHashAlgorithm for BlakeTwo256 {
+	fn algorithm() -> HashAlgorithm {
+		HashAlgorithm::Blake2_256
+	}
+}
+
+impl ResponseHashAlgorithm for Keccak256 {
+	fn algorithm() -> HashAlgorithm {
+		HashAlgorithm::Keccak256
+	}
+}
+
+/// SHA-256 hasher, for a `Config::ResponseHasher` that matches a CyberHub hashing with it.
+#[derive(PartialEq, Eq, Clone, RuntimeDebug, TypeInfo, serde::Serialize, serde::Deserialize)]
+pub struct Sha256;
+
+impl sp_core::Hasher for Sha256 {
+	type Out = H256;
+	type StdHasher = <BlakeTwo256 as sp_core::Hasher>::StdHasher;
+	const LENGTH: usize = 32;
+
+	fn hash(s: &[u8]) -> Self::Out {
+		sp_io::hashing::sha2_256(s).into()
+	}
+}
+
+impl HashT for Sha256 {
+	type Output = H256;
+
+	fn ordered_trie_root(input: Vec<Vec<u8>>, version: StateVersion) -> Self::Output {
+		match version {
+			StateVersion::V0 => LayoutV0::<Self>::ordered_trie_root(input),
+			StateVersion::V1 => LayoutV1::<Self>::ordered_trie_root(input),
+		}
+	}
+
+	fn trie_root(input: Vec<(Vec<u8>, Vec<u8>)>, version: StateVersion) -> Self::Output {
+		match version {
+			StateVersion::V0 => LayoutV0::<Self>::trie_root(input),
+			StateVersion::V1 => LayoutV1::<Self>::trie_root(input),
+		}
+	}
+}
+
+impl ResponseHashAlgorithm for Sha256 {
+	fn algorithm() -> HashAlgorithm {
+		HashAlgorithm::Sha256
+	}
+}
+
+/// Ensures the origin is signed by the edge server of a connection, that is by the account of
+/// its `server_pubkey`.
+///
+/// Connections without a registered key have no server origin.
+pub struct EnsureConnectionOrigin<T>(PhantomData<T>);
+
+impl<T: Config> EnsureConnectionOrigin<T>
+where
+	T::AccountId: From<sr25519::Public>,
+{
+	/// The account the edge server of `connection` signs with, if it registered a key.
+	fn server_account(connection: &ConnectionId) -> Option<T::AccountId> {
+		<Connection<T>>::get(connection)?.server_pubkey.map(Into::into)
+	}
+}
+
+impl<T: Config> EnsureOriginWithArg<T::RuntimeOrigin, ConnectionId> for EnsureConnectionOrigin<T>
+where
+	T::AccountId: From<sr25519::Public>,
+{
+	type Success = T::AccountId;
+
+	fn try_origin(
+		o: T::RuntimeOrigin,
+		connection: &ConnectionId,
+	) -> Result<Self::Success, T::RuntimeOrigin> {
+		let server = Self::server_account(connection);
+		o.into().and_then(|o| match o {
+			system::RawOrigin::Signed(who) if server.as_ref() == Some(&who) => Ok(who),
+			o => Err(o.into()),
+		})
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn try_successful_origin(connection: &ConnectionId) -> Result<T::RuntimeOrigin, ()> {
+		let server = Self::server_account(connection).ok_or(())?;
+		Ok(system::RawOrigin::Signed(server).into())
+	}
+}
+
+/// Record of a connection between Cyborg blockchain and an external edge server.
+#[derive(
+	Encode, Decode, CloneNoBound, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo,
+	MaxEncodedLen,
+)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound())]
+pub struct ConnectionInfo<T: Config> {
+	/// Endpoint URLs of the edge server, the primary one first. The offchain worker tries them
+	/// in order until one answers.
+	pub endpoints: EndpointsOf<T>,
+	/// Human-readable name chosen by the owner, unique among their connections.
+	pub label: Option<BoundedVec<u8, T::MaxLabelLength>>,
+	/// Account that owns the connection.
+	pub owner: T::AccountId,
+	/// Block at which the connection was created.
+	pub created_at: BlockNumberFor<T>,
+	/// Block at which the edge server was last heard from, through a response or a heartbeat.
+	pub last_seen: BlockNumberFor<T>,
+	/// Whether the connection is currently served.
+	pub status: ConnectionStatus,
+	/// Accounts besides the owner that may send commands over the connection.
+	pub authorized_senders: BoundedVec<T::AccountId, T::MaxAuthorizedSenders>,
+	/// Amount reserved from the owner while the connection exists.
+	pub deposit: BalanceOf<T>,
+	/// Tags the owner groups the connection under.
+	pub tags: TagsOf<T>,
+	/// Key the edge server signs its responses with, responses are only accepted with a valid
+	/// signature once it is set.
+	pub server_pubkey: Option<sr25519::Public>,
+	/// Index in `endpoints` of the endpoint that last answered the offchain worker.
+	pub active_endpoint: u32,
+	/// Block of the latest lifecycle change of the connection, that is its creation, transfer
+	/// or latest change of status.
+	pub last_lifecycle_block: BlockNumberFor<T>,
+	/// Block at which the connection is removed, if it is only meant to last for a while.
+	pub expires_at: Option<BlockNumberFor<T>>,
+	/// How many connections had the id of this one before it, so that references to them can be
+	/// told apart from references to this one.
+	pub generation: u32,
+	/// How the offchain worker gets the results of the commands sent over the connection.
+	pub poll_mode: PollMode,
+	/// Goes up by one with every change the owner makes to the connection or its metadata.
+	///
+	/// Those calls take an `expected_version` and fail with `VersionMismatch` if the connection
+	/// is at another one, so that concurrent edits do not silently overwrite each other.
+	pub version: u32,
+}
+
+/// Connection details as returned by the runtime API.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ConnectionDetails<AccountId, BlockNumber> {
+	/// Endpoint URLs of the edge server, the primary one first.
+	pub endpoints: Vec<Vec<u8>>,
+	/// Human-readable name chosen by the owner.
+	pub label: Option<Vec<u8>>,
+	/// Account that owns the connection.
+	pub owner: AccountId,
+	/// Block at which the connection was created.
+	pub created_at: BlockNumber,
+	/// Block at which the edge server was last heard from.
+	pub last_seen: BlockNumber,
+	/// Whether the connection is currently served.
+	pub status: ConnectionStatus,
+	/// Tags the owner groups the connection under.
+	pub tags: Vec<Vec<u8>>,
+	/// Key the edge server signs its responses with, if any.
+	pub server_pubkey: Option<sr25519::Public>,
+	/// Index in `endpoints` of the endpoint that last answered the offchain worker.
+	pub active_endpoint: u32,
+	/// Block of the latest creation, transfer or status change of the connection.
+	pub last_lifecycle_block: BlockNumber,
+	/// Block at which the connection is removed, if any.
+	pub expires_at: Option<BlockNumber>,
+	/// How many connections had the id of this one before it.
+	pub generation: u32,
+	/// How the offchain worker gets the results of its commands.
+	pub poll_mode: PollMode,
+	/// Goes up by one with every change the owner makes to the connection.
+	pub version: u32,
+	/// Number of commands the edge server has yet to answer.
+	pub in_flight: u32,
+	/// How many of the latest commands over the connection succeeded.
+	pub health: ConnectionHealth,
+}
+
+/// Connection details of the runtime `T`.
+pub type ConnectionDetailsOf<T> =
+	ConnectionDetails<<T as frame_system::Config>::AccountId, BlockNumberFor<T>>;
+
+/// A page of the connections owned by an account, as returned by the runtime API.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ConnectionsPage<AccountId, BlockNumber> {
+	/// The connections of the page, by ascending id.
+	pub connections: Vec<(ConnectionId, ConnectionDetails<AccountId, BlockNumber>)>,
+	/// Where the next page starts after, if there is one.
+	pub next: Option<ConnectionId>,
+}
+
+/// A page of connections of the runtime `T`.
+pub type ConnectionsPageOf<T> =
+	ConnectionsPage<<T as frame_system::Config>::AccountId, BlockNumberFor<T>>;
+
+/// How a command sent with `send_raw_command` is delivered.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct SendOptions<BlockNumber> {
+	/// Commands with a higher priority are delivered first.
+	pub priority: u8,
+	/// Block after which the command is expired instead of delivered, if any.
+	pub valid_until: Option<BlockNumber>,
+	/// Reference of the client, under which resubmissions are not queued again.
+	pub client_ref: Option<[u8; 32]>,
+	/// Whether the edge server only acknowledges the command instead of answering it.
+	pub ack_only: bool,
+	/// Number of the command among those of the signer, for them to be queued in order.
+	pub seq: Option<u64>,
+}
+
+/// Send options of the runtime `T`.
+pub type SendOptionsOf<T> = SendOptions<BlockNumberFor<T>>;
+
+/// A reusable command registered with `register_template`.
+#[derive(
+	Encode, Decode, CloneNoBound, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo,
+	MaxEncodedLen,
+)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound())]
+pub struct CommandTemplate<T: Config> {
+	/// Account that registered the template and may remove it.
+	pub owner: T::AccountId,
+	/// The command, with `{n}` where the `n`th parameter goes.
+	pub payload: BoundedVec<u8, T::MaxCommandLength>,
+	/// Hash of the schema the responses to its commands follow, if the owner named one.
+	pub schema_hash: Option<H256>,
+}
+
+/// The commands queued by a single `broadcast_command`.
+#[derive(
+	Encode, Decode, CloneNoBound, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo,
+	MaxEncodedLen,
+)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound())]
+pub struct Broadcast<T: Config> {
+	/// The commands of the broadcast that are still stored, in the order they were queued.
+	pub commands: BoundedVec<CommandId, T::MaxConnectionsPerAccount>,
+	/// How many of them have not reached a final status yet.
+	pub pending: u32,
+}
+
+/// Where a command of a broadcast stands, as returned by the runtime API.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct BroadcastResult {
+	/// The command.
+	pub command_id: CommandId,
+	/// The connection it was queued on.
+	pub connection: ConnectionId,
+	/// Its lifecycle status.
+	pub status: CommandStatus,
+	/// The response CyberHub gave to it, if it is kept on chain.
+	pub response: Option<Vec<u8>>,
+	/// The hash of that response, if only the hash is kept on chain.
+	pub response_hash: Option<ResponseHash>,
+}
+
+/// A command the offchain worker dispatched to CyberHub, as recorded in the dispatch log.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct DispatchLogEntry<BlockNumber> {
+	/// Block in which the dispatch was recorded.
+	pub block: BlockNumber,
+	/// The command that was dispatched.
+	pub command_id: CommandId,
+	/// The connection it was dispatched over.
+	pub connection: ConnectionId,
+	/// Blake2-256 hash of the command bytes.
+	pub payload_hash: H256,
+}
+
+/// A dispatch log entry of the runtime `T`.
+pub type DispatchLogEntryOf<T> = DispatchLogEntry<BlockNumberFor<T>>;
+
+/// Totals over the whole pallet, as returned by the runtime API.
+#[derive(
+	Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct EdgeConnectStats {
+	/// Number of connections, whatever their status.
+	pub total_connections: u32,
+	/// Number of `Active` connections.
+	pub active_connections: u32,
+	/// Number of commands still `Queued` for delivery, over all connections.
+	pub total_queued_commands: u32,
+}
+
+impl<T: Config> From<ConnectionInfo<T>> for ConnectionDetailsOf<T> {
+	fn from(info: ConnectionInfo<T>) -> Self {
+		ConnectionDetails {
+			endpoints: info.endpoints.into_iter().map(BoundedVec::into_inner).collect(),
+			label: info.label.map(BoundedVec::into_inner),
+			owner: info.owner,
+			created_at: info.created_at,
+			last_seen: info.last_seen,
+			status: info.status,
+			tags: info.tags.into_iter().map(BoundedVec::into_inner).collect(),
+			server_pubkey: info.server_pubkey,
+			active_endpoint: info.active_endpoint,
+			last_lifecycle_block: info.last_lifecycle_block,
+			expires_at: info.expires_at,
+			generation: info.generation,
+			poll_mode: info.poll_mode,
+			version: info.version,
+			// Kept apart from the connection record, see `connection_details_of`.
+			in_flight: 0,
+			health: ConnectionHealth::Healthy,
+		}
+	}
+}
+
+/// Status of a connection between Cyborg blockchain and an edge server.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum ConnectionStatus {
+	/// Commands are accepted and delivered to the edge server.
+	Active,
+	/// Temporarily not accepting commands.
+	Paused,
+	/// The edge server is no longer reachable through this connection.
+	Disconnected,
+}
+
+/// How the offchain worker gets the results of the commands sent over a connection.
+#[derive(
+	Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum PollMode {
+	/// CyberHub answers the request a command is sent with, and is asked again every block
+	/// until it has the result.
+	#[default]
+	Push,
+	/// Commands are sent once, and their results are drained from the `/responses` endpoint of
+	/// the edge server every block.
+	LongPoll,
+}
+
+/// The algorithm a response hash was computed with.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum HashAlgorithm {
+	/// 256-bit BLAKE2b, as used throughout Substrate.
+	Blake2_256,
+	/// 256-bit Keccak, as used by Ethereum.
+	Keccak256,
+	/// SHA-256.
+	Sha256,
+}
+
+/// The hash of a response whose body is kept off chain, see `Config::HashResponses`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ResponseHash {
+	/// The hash of the response body.
+	pub hash: H256,
+	/// The algorithm `hash` was computed with.
+	pub algorithm: HashAlgorithm,
+}
+
+/// How a connection fared with its latest settled commands, see `RecentOutcomes`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum ConnectionHealth {
+	/// At least `DegradedBelow` of the commands completed, or none settled yet.
+	Healthy,
+	/// Fewer than `DegradedBelow` of the commands completed.
+	Degraded,
+	/// Fewer than `UnhealthyBelow` of the commands completed.
+	Unhealthy,
+}
+
+/// A command queued for delivery to an edge server.
+#[derive(
+	Encode, Decode, CloneNoBound, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo,
+	MaxEncodedLen,
+)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound())]
+pub struct Command<T: Config> {
+	/// Identifier of the command.
+	pub id: CommandId,
+	/// Position of the command among those sent over its connection, so that the edge server
+	/// can tell replays apart.
+	pub nonce: u64,
+	/// Raw command bytes sent to the edge server.
+	pub payload: BoundedVec<u8, T::MaxCommandLength>,
+	/// Delivery priority, higher priorities are sent to CyberHub first.
+	pub priority: u8,
+	/// Account that submitted the command.
+	pub submitter: T::AccountId,
+	/// Block at which the command was submitted.
+	pub submitted_at: BlockNumberFor<T>,
+	/// Block at which the command was dispatched to CyberHub, if it was.
+	pub dispatched_at: Option<BlockNumberFor<T>>,
+	/// Block at which the command reached its final status, if it did.
+	pub settled_at: Option<BlockNumberFor<T>>,
+	/// Last block at which the command may still be dispatched, if its submitter set one.
+	pub valid_until: Option<BlockNumberFor<T>>,
+	/// Whether the edge server only acknowledges the command instead of responding to it.
+	pub ack_only: bool,
+}
+
+impl<T: Config> Command<T> {
+	/// Whether the command can no longer be dispatched at block `now`.
+	pub fn has_expired(&self, now: BlockNumberFor<T>) -> bool {
+		self.valid_until.is_some_and(|valid_until| valid_until < now)
+	}
+}
+
+/// Lifecycle of a command, from being queued to its final outcome.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum CommandStatus {
+	/// Waiting in the connection's queue.
+	Queued,
+	/// Delivered to CyberHub by the offchain worker.
+	Dispatched,
+	/// Accepted by CyberHub, the result is not available yet.
+	AwaitingResponse,
+	/// A response has been stored on chain.
+	Completed,
+	/// CyberHub could not be reached or returned an invalid response.
+	Failed,
+	/// CyberHub did not answer in time.
+	TimedOut,
+	/// The command was not dispatched before its `valid_until` block.
+	Expired,
+	/// CyberHub acknowledged a command sent `ack_only`, no response is coming.
+	Acknowledged,
+	/// Flushed from the queue by its owner before it was dispatched.
+	Cancelled,
+}
+
+impl CommandStatus {
+	/// Whether the command has reached a final state.
+	pub fn is_final(&self) -> bool {
+		matches!(
+			self,
+			Self::Completed |
+				Self::Failed |
+				Self::TimedOut |
+				Self::Expired |
+				Self::Acknowledged |
+				Self::Cancelled
+		)
+	}
+
+	/// Whether a command in this state may move to `next`.
+	pub fn can_transition_to(&self, next: CommandStatus) -> bool {
+		use CommandStatus::*;
+		matches!(
+			(self, next),
+			(Queued, Dispatched) |
+				(Dispatched, AwaitingResponse) |
+				(Queued, Expired | Cancelled) |
+				(Queued | Dispatched, Acknowledged) |
+				(Queued | Dispatched | AwaitingResponse, Completed | Failed | TimedOut)
+		)
+	}
+}
+
+/// Payload used by this crate to hold response
+/// data required to submit a transaction.
+///
+/// The payload is signed by one of the offchain worker keys, so that the response can be
+/// submitted as an unsigned transaction whose origin is still verifiable.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
+pub struct ResponsePayload<Public, BlockNumber> {
+	/// Block at which the response was fetched.
+	pub block_number: BlockNumber,
+	/// Command the response answers.
+	pub command_id: CommandId,
+	/// Raw response bytes received from CyberHub.
+	pub response: Vec<u8>,
+	/// The edge server's signature over the response, if it sent one.
+	pub server_signature: Option<sr25519::Signature>,
+	/// Key that signed the payload.
+	pub public: Public,
+}
+
+impl<T: SigningTypes> SignedPayload<T> for ResponsePayload<T::Public, BlockNumberFor<T>> {
+	fn public(&self) -> T::Public {
+		self.public.clone()
+	}
+}
+
+/// How the offchain worker submits the responses it fetched, as configured for the runtime.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum SubmitStrategy {
+	/// Signed `receive_response` transactions from every local account, which pay fees.
+	Signed,
+	/// An unsigned transaction with a payload signed by any local account.
+	UnsignedForAny,
+	/// Unsigned transactions with payloads signed by every local account.
+	UnsignedForAll,
+}
+
+/// How the offchain worker submits the responses it fetched in a given run.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+enum TransactionType {
+	/// Signed `receive_response` transactions from every local account.
+	Signed,
+	/// An unsigned transaction with a payload signed by any local account.
+	UnsignedForAny,
+	/// Unsigned transactions with payloads signed by every local account.
+	UnsignedForAll,
+	/// Nothing is sent, another run did so recently or there is no local account to sign with.
+	None,
+}
+
+impl From<SubmitStrategy> for TransactionType {
+	fn from(strategy: SubmitStrategy) -> Self {
+		match strategy {
+			SubmitStrategy::Signed => TransactionType::Signed,
+			SubmitStrategy::UnsignedForAny => TransactionType::UnsignedForAny,
+			SubmitStrategy::UnsignedForAll => TransactionType::UnsignedForAll,
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Chooses which transaction type to send.
+	///
+	/// The type is the one `OcwSubmitStrategy` asks for. Nothing is sent within `GracePeriod`
+	/// blocks of the last run that did, nor without a local account to sign with.
+	///
+	/// Returns a type of transaction that should be produced in current run.
+	fn choose_transaction_type(block_number: BlockNumberFor<T>) -> TransactionType {
+		if !Signer::<T, T::AuthorityId>::all_accounts().can_sign() {
+			log::error!(
+				target: LOG_TARGET,
+				"run skipped block={:?} outcome=no_local_accounts",
+				block_number
+			);
+			return TransactionType::None
+		}
+
+		/// A friendlier name for the error that is going to be returned in case we are in the grace
+		/// period.
+		const RECENTLY_SENT: () = ();
+
+		// Start off by creating a reference to Local Storage value.
+		// Since the local storage is common for all offchain workers, it's a good practice
+		// to prepend your entry with the module name.
+		let val = StorageValueRef::persistent(b"edge-connect::last-send");
+		// The Local Storage is persisted and shared between runs of the offchain workers,
+		// and offchain workers may run concurrently. We can use the `mutate` function, to
+		// write a storage entry in an atomic fashion. Under the hood it uses `compare_and_set`
+		// low-level method of local storage API, which means that only one worker
+		// will be able to "acquire a lock" and send a transaction if multiple workers
+		// happen to be executed concurrently.
+		let res = val.mutate(|last_send: Result<Option<BlockNumberFor<T>>, StorageRetrievalError>| {
+			match last_send {
+				// If we already have a value in storage and the block number is recent enough
+				// we avoid sending another transaction at this time.
+				Ok(Some(block)) if block_number < block + T::GracePeriod::get() =>
+					Err(RECENTLY_SENT),
+				// In every other case we attempt to acquire the lock and send a transaction.
+				_ => Ok(block_number),
+			}
+		});
+
+		// The result of `mutate` call will give us a nested `Result` type.
+		// The first one matches the return of the closure passed to `mutate`, i.e.
+		// if we return `Err` from the closure, we get an `Err` here.
+		// In case we return `Ok`, here we will have another (inner) `Result` that indicates
+		// if the value has been set to the storage correctly - i.e. if it wasn't
+		// written to in the meantime.
+		match res {
+			// The value has been set correctly, which means we can safely send a transaction now.
+			Ok(_) => T::OcwSubmitStrategy::get().into(),
+			// We are in the grace period, we should not send a transaction this time.
+			Err(MutateStorageError::ValueFunctionFailed(RECENTLY_SENT)) => {
+				log::debug!(
+					target: LOG_TARGET,
+					"run skipped block={:?} outcome=grace_period",
+					block_number
+				);
+				TransactionType::None
+			},
+			// We wanted to send a transaction, but failed to write the block number (acquire a
+			// lock). This indicates that another offchain worker that was running concurrently
+			// most likely executed the same logic and succeeded at writing to storage.
+			// Thus we don't really want to send the transaction, knowing that the other run
+			// already did.
+			Err(MutateStorageError::ConcurrentModification(_)) => TransactionType::None,
+		}
+	}
+
+	/// Fetches responses with `fetch` and submits them the way `should_send` says.
+	fn submit_responses(
+		should_send: TransactionType,
+		block_number: BlockNumberFor<T>,
+		fetch: impl FnOnce() -> FetchedResponses<T>,
+	) -> Result<(), &'static str> {
+		match should_send {
+			TransactionType::Signed => Self::fetch_response_and_send_signed(fetch),
+			TransactionType::UnsignedForAny =>
+				Self::fetch_response_and_send_unsigned_for_any_account(block_number, fetch),
+			TransactionType::UnsignedForAll =>
+				Self::fetch_response_and_send_unsigned_for_all_accounts(block_number, fetch),
+			TransactionType::None => Ok(()),
+		}
+	}
+
+	/// A helper function to fetch the responses and send signed transactions.
+	fn fetch_response_and_send_signed(
+		fetch: impl FnOnce() -> FetchedResponses<T>,
+	) -> Result<(), &'static str> {
+		let signer = Signer::<T, T::AuthorityId>::all_accounts();
+		if !signer.can_sign() {
+			return Err(
+				"No local accounts available. Consider adding one via `author_insertKey` RPC.",
+			)
+		}
+		// Make an external HTTP request to fetch the current responses.
+		// Note this call will block until response is received.
+		let responses = fetch()?;
+		for (command_id, (response, server_signature)) in responses {
+			// Using `send_signed_transaction` associated type we create and submit a transaction
+			// representing the call, we've just created.
+			// Submit signed will return a vector of results for all accounts that were found in
+			// the local keystore with expected `KEY_TYPE`.
+			let results = signer.send_signed_transaction(|_account| Call::receive_response {
+				command_id,
+				response: response.clone(),
+				server_signature: server_signature.clone(),
+			});
+
+			for (acc, res) in &results {
+				match res {
+					Ok(()) => log::debug!(
+						target: LOG_TARGET,
+						"response submitted command={} account={:?} outcome=signed",
+						command_id,
+						acc.id
+					),
+					Err(e) => log::error!(
+						target: LOG_TARGET,
+						"response submission failed command={} account={:?} outcome=error \
+						 error={:?}",
+						command_id,
+						acc.id,
+						e
+					),
+				}
+			}
+			// Like the unsigned submissions, make sure CyberHub is not asked again while the
+			// response is on its way.
+			if results.iter().any(|(_, res)| res.is_ok()) {
+				Self::set_dispatched_watermark(command_id);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// A helper function to fetch the responses, sign payloads and send unsigned transactions
+	fn fetch_response_and_send_unsigned_for_any_account(
+		block_number: BlockNumberFor<T>,
+		fetch: impl FnOnce() -> FetchedResponses<T>,
+	) -> Result<(), &'static str> {
+		// Make sure we don't fetch the response if unsigned transaction is going to be rejected
+		// anyway.
+		let next_unsigned_at = <NextUnsignedAt<T>>::get();
+		if next_unsigned_at > block_number {
+			return Err("Too early to send unsigned transaction")
+		}
+
+		// Make an external HTTP request to fetch the current responses.
+		// Note this call will block until response is received.
+		let responses = fetch()?;
+		for (command_id, (response, server_signature)) in responses {
+			// -- Sign using any account
+			let (_, result) = Signer::<T, T::AuthorityId>::any_account()
+				.send_unsigned_transaction(
+					|account| {
+						ResponsePayload {
+							response: response.to_vec(),
+							server_signature: server_signature.clone(),
+							block_number,
+							command_id,
+							public: account.public.clone(),
+						}
+					},
+					|payload, signature| Call::submit_response_unsigned_with_signed_payload {
+						response_payload: payload,
+						signature,
+					},
+				)
+				.ok_or("No local accounts accounts available.")?;
+			result.map_err(|()| "Unable to submit transaction")?;
+			log::debug!(
+				target: LOG_TARGET,
+				"response submitted command={} outcome=unsigned_for_any",
+				command_id
+			);
+			Self::set_dispatched_watermark(command_id);
+		}
+
+		Ok(())
+	}
+
+	/// A helper function to fetch the responses, sign payloads and send unsigned transactions
+	fn fetch_response_and_send_unsigned_for_all_accounts(
+		block_number: BlockNumberFor<T>,
+		fetch: impl FnOnce() -> FetchedResponses<T>,
+	) -> Result<(), &'static str> {
+		// Make sure we don't fetch the response if unsigned transaction is going to be rejected
+		// anyway.
+		let next_unsigned_at = <NextUnsignedAt<T>>::get();
+		if next_unsigned_at > block_number {
+			return Err("Too early to send unsigned transaction")
+		}
+
+		// Make an external HTTP request to fetch the current responses.
+		// Note this call will block until response is received.
+		let responses = fetch()?;
+		for (command_id, (response, server_signature)) in responses {
+			// -- Sign using all accounts
+			let transaction_results = Signer::<T, T::AuthorityId>::all_accounts()
+				.send_unsigned_transaction(
+					|account| {
+						ResponsePayload {
+							response: response.to_vec(),
+							server_signature: server_signature.clone(),
+							block_number,
+							command_id,
+							public: account.public.clone(),
+						}
+					},
+					|payload, signature| Call::submit_response_unsigned_with_signed_payload {
+						response_payload: payload,
+						signature,
+					},
+				);
+			for (_account_id, result) in transaction_results.into_iter() {
+				if result.is_err() {
+					return Err("Unable to submit transaction")
+				}
+			}
+			log::debug!(
+				target: LOG_TARGET,
+				"response submitted command={} outcome=unsigned_for_all",
+				command_id
+			);
+			Self::set_dispatched_watermark(command_id);
+		}
+
+		Ok(())
+	}
+
+	/// Key of the offchain storage lock guarding deliveries to `connection`.
+	///
+	/// Offchain storage keys of this pallet are the SCALE encoding of an
+	/// `edge-connect::<purpose>::` prefix followed by the `ConnectionId` or `CommandId` they
+	/// concern, so that every connection and command gets its own entry per purpose.
+	fn lock_key(connection: ConnectionId) -> Vec<u8> {
+		(b"edge-connect::lock::", connection).encode()
+	}
+
+	/// Key under which the response body with the given `hash` is indexed off chain.
+	fn response_body_key(hash: H256) -> Vec<u8> {
+		(b"edge-connect::response::", hash).encode()
+	}
+
+	/// Key under which the `index`th dispatch log entry of `block` is indexed off chain.
+	fn dispatch_log_key(block: BlockNumberFor<T>, index: u32) -> Vec<u8> {
+		(b"edge-connect::dispatch-log::", block, index).encode()
+	}
+
+	/// Key of the retry bookkeeping of `command_id`.
+	fn retry_key(command_id: CommandId) -> Vec<u8> {
+		(b"edge-connect::retry::", command_id).encode()
+	}
+
+	/// Returns how many requests for `command_id` failed and the block it may be retried at.
+	fn retry_state(command_id: CommandId) -> Option<(u32, BlockNumberFor<T>)> {
+		let key = Self::retry_key(command_id);
+		sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &key)
+			.and_then(|raw| Decode::decode(&mut &raw[..]).ok())
+	}
+
+	/// Whether a request for `command_id` may be sent at `block_number`.
+	fn retry_due(command_id: CommandId, block_number: BlockNumberFor<T>) -> bool {
+		!matches!(Self::retry_state(command_id), Some((_, next)) if next > block_number)
+	}
+
+	/// Notes a failed request for `command_id` and holds off the next one for `2^attempts`
+	/// blocks, capped at `MAX_RETRY_BACKOFF`.
+	///
+	/// Returns `false` once `MaxCommandRetries` is exhausted, the command should be failed then.
+	fn schedule_retry(command_id: CommandId) -> bool {
+		let attempts = Self::retry_state(command_id).map_or(0, |(attempts, _)| attempts) + 1;
+		if attempts > T::MaxCommandRetries::get() {
+			log::warn!(
+				target: LOG_TARGET,
+				"retries exhausted command={} attempt={} outcome=failed",
+				command_id,
+				attempts
+			);
+			Self::clear_retry(command_id);
+			return false
+		}
+
+		let backoff = 2u32.saturating_pow(attempts).min(MAX_RETRY_BACKOFF);
+		let next_retry_block = <system::Pallet<T>>::block_number() + backoff.into();
+		log::debug!(
+			target: LOG_TARGET,
+			"retry scheduled command={} attempt={} outcome=backoff next_block={:?}",
+			command_id,
+			attempts,
+			next_retry_block
+		);
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			&Self::retry_key(command_id),
+			&(attempts, next_retry_block).encode(),
+		);
+		true
+	}
+
+	/// Forgets the failed requests of `command_id`.
+	fn clear_retry(command_id: CommandId) {
+		let key = Self::retry_key(command_id);
+		sp_io::offchain::local_storage_clear(StorageKind::PERSISTENT, &key);
+	}
+
+	/// Key of the watermark of the last command answered over `connection`.
+	fn dispatched_key(connection: ConnectionId) -> Vec<u8> {
+		(b"edge-connect::dispatched::", connection).encode()
+	}
+
+	/// Returns the id of the last command whose response was submitted for `connection`.
+	fn dispatched_watermark(connection: ConnectionId) -> Option<CommandId> {
+		let key = Self::dispatched_key(connection);
+		sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &key)
+			.and_then(|raw| CommandId::decode(&mut &raw[..]).ok())
+	}
+
+	/// Records that the response to `command_id` has been submitted, so later runs skip it.
+	fn set_dispatched_watermark(command_id: CommandId) {
+		let Some(connection) = <CommandConnection<T>>::get(command_id) else { return };
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			&Self::dispatched_key(connection),
+			&command_id.encode(),
+		);
+	}
+
+	/// Forgets the watermark of `connection`.
+	fn clear_dispatched_watermark(connection: ConnectionId) {
+		let key = Self::dispatched_key(connection);
+		sp_io::offchain::local_storage_clear(StorageKind::PERSISTENT, &key);
+	}
+
+	/// Returns the queued commands of `connection` that have not reached a final status yet.
+	///
+	/// Commands come in delivery order: highest priority first, in queue order within a
+	/// priority. The queue itself stays in insertion order.
+	fn pending_commands(connection: ConnectionId) -> Vec<Command<T>> {
+		let mut pending: Vec<_> = <CommandQueue<T>>::get(connection)
+			.into_iter()
+			.filter(|command| {
+				matches!(<CommandStatusOf<T>>::get(command.id), Some(status) if !status.is_final())
+			})
+			.collect();
+		// The sort is stable, which keeps commands of the same priority first in, first out.
+		pending.sort_by_key(|command| Reverse(command.priority));
+		pending
+	}
+
+	/// Sends `commands` to the first of `endpoints` that answers and returns the responses that
+	/// are ready.
+	///
+	/// A single command is sent on its own, several go out as one batch request. Responses to
+	/// commands that were cancelled in the meantime are dropped.
+	fn fetch_responses(
+		endpoints: &[&str],
+		commands: &[Command<T>],
+	) -> Result<Vec<(CommandId, SignedResponseOf<T>)>, &'static str> {
+		let mut responses = match commands {
+			[] => Vec::new(),
+			[command] => Self::fetch_response(endpoints, command)?
+				.map(|response| (command.id, response))
+				.into_iter()
+				.collect(),
+			commands => Self::fetch_batch_response(endpoints, commands)?,
+		};
+		responses.retain(|(command_id, _)| <CommandStatusOf<T>>::contains_key(command_id));
+		Ok(responses)
+	}
+
+	/// Sends `command` to the first of `endpoints` that answers and returns the bounded response
+	/// body, along with the edge server's signature over it.
+	///
+	/// Returns `None` when CyberHub accepted or acknowledged the command but has no result yet,
+	/// the latter settling commands sent `ack_only`. Failed requests
+	/// are retried with an exponential backoff, commands that CyberHub failed, answered with a
+	/// malformed envelope or that ran out of retries are moved to `Failed` via a signed
+	/// transaction.
+	fn fetch_response(
+		endpoints: &[&str],
+		command: &Command<T>,
+	) -> Result<Option<SignedResponseOf<T>>, &'static str> {
+		let request = Self::encode_request(command);
+		let (endpoint, body) = match Self::fetch_from_endpoints(endpoints, Some(&request)) {
+			Ok(answer) => answer,
+			Err(e) => {
+				if !Self::schedule_retry(command.id) {
+					Self::send_status_update(command.id, CommandStatus::Failed)?;
+				}
+				Self::note_unreachable(command.id, &e)?;
+				return Err(Self::http_error(e))
+			},
+		};
+		Self::note_endpoint(command.id, endpoint)?;
+		Self::note_dispatched(command.id)?;
+
+		let Some(body) = body else {
+			Self::note_acknowledged(command)?;
+			return Ok(None)
+		};
+		if body.is_empty() {
+			// CyberHub is still working on it, note that we are waiting for the result.
+			Self::note_awaiting_response(command.id)?;
+			return Ok(None)
+		}
+		match Self::decode_response(command.id, &body) {
+			Ok(CommandOutcome::Acknowledged) => Self::note_acknowledged(command).map(|()| None),
+			outcome => Self::settle(command.id, outcome).map(Some),
+		}
+	}
+
+	/// Sends `commands` in a single request to the first of `endpoints` that answers and returns
+	/// the responses that are ready.
+	///
+	/// Every command keeps its own status: a result missing from the batch means CyberHub is
+	/// still working on that command, an entry that cannot be used only fails its own command.
+	/// A request that does not reach CyberHub is retried for every command of the batch, and a
+	/// batch response that cannot be decoded fails all of them.
+	fn fetch_batch_response(
+		endpoints: &[&str],
+		commands: &[Command<T>],
+	) -> Result<Vec<(CommandId, SignedResponseOf<T>)>, &'static str> {
+		let request = Self::encode_batch_request(commands);
+		let (endpoint, body) = match Self::fetch_from_endpoints(endpoints, Some(&request)) {
+			Ok(answer) => answer,
+			Err(e) => {
+				for command in commands {
+					if !Self::schedule_retry(command.id) {
+						Self::send_status_update(command.id, CommandStatus::Failed)?;
+					}
+				}
+				if let Some(command) = commands.first() {
+					Self::note_unreachable(command.id, &e)?;
+				}
+				return Err(Self::http_error(e))
+			},
+		};
+		if let Some(command) = commands.first() {
+			Self::note_endpoint(command.id, endpoint)?;
+		}
+		for command in commands {
+			Self::note_dispatched(command.id)?;
+		}
+
+		// An empty body means CyberHub has no result for any command of the batch yet, no body
+		// at all that it acknowledged all of them.
+		let results = match body {
+			None => {
+				for command in commands {
+					Self::note_acknowledged(command)?;
+				}
+				return Ok(Vec::new())
+			},
+			Some(body) if body.is_empty() => Ok(Vec::new()),
+			Some(body) => Self::decode_batch_response(&body),
+		};
+		let results = match results {
+			Ok(results) => results,
+			Err(e) => {
+				for command in commands {
+					Self::send_status_update(command.id, CommandStatus::Failed)?;
+				}
+				return Err(e)
+			},
+		};
+
+		let mut responses = Vec::new();
+		for command in commands {
+			let Some((_, outcome)) = results.iter().find(|(id, _)| *id == command.id) else {
+				Self::note_awaiting_response(command.id)?;
+				continue
+			};
+			if *outcome == CommandOutcome::Acknowledged {
+				Self::note_acknowledged(command)?;
+				continue
+			}
+			match Self::settle(command.id, Ok(outcome.clone())) {
+				Ok(response) => responses.push((command.id, response)),
+				Err(e) => log::error!(
+					target: LOG_TARGET,
+					"command failed command={} outcome=error error={:?}",
+					command.id,
+					e
+				),
+			}
+		}
+		Ok(responses)
+	}
+
+	/// Sends `commands` to the first of `endpoints` that answers, for a long-polled edge server
+	/// to work on.
+	///
+	/// Whatever the edge server answers the request with is ignored, the results are drained
+	/// later on, unless it acknowledged the commands outright. A request that does not reach the
+	/// edge server is retried for every command, like a pushed one.
+	fn dispatch_commands(endpoints: &[&str], commands: &[Command<T>]) -> Result<(), &'static str> {
+		let request = match commands {
+			[command] => Self::encode_request(command),
+			commands => Self::encode_batch_request(commands),
+		};
+		let (endpoint, body) = match Self::fetch_from_endpoints(endpoints, Some(&request)) {
+			Ok(answer) => answer,
+			Err(e) => {
+				for command in commands {
+					if !Self::schedule_retry(command.id) {
+						Self::send_status_update(command.id, CommandStatus::Failed)?;
+					}
+				}
+				if let Some(command) = commands.first() {
+					Self::note_unreachable(command.id, &e)?;
+				}
+				return Err(Self::http_error(e))
+			},
+		};
+		if let Some(command) = commands.first() {
+			Self::note_endpoint(command.id, endpoint)?;
+		}
+		for command in commands {
+			Self::note_dispatched(command.id)?;
+			if body.is_none() {
+				Self::note_acknowledged(command)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// GETs the results a long-polled edge server has ready from the `/responses` endpoint of
+	/// the first of `endpoints` that answers, and returns the responses to submit.
+	///
+	/// The endpoint answers with a batch of response envelopes, or with an empty body when it
+	/// has nothing new. Results for commands that are not waiting on `connection` are dropped,
+	/// an entry that cannot be used only fails its own command.
+	fn drain_responses(endpoints: &[&str], connection: ConnectionId) -> FetchedResponses<T> {
+		// Commands sent in earlier runs are in the hands of the edge server by now.
+		for command in Self::pending_commands(connection) {
+			Self::note_awaiting_response(command.id)?;
+		}
+
+		let urls: Vec<_> = endpoints.iter().map(|url| Self::responses_url(url)).collect();
+		let urls: Vec<_> = urls.iter().map(String::as_str).collect();
+		let (_, body) = Self::fetch_from_endpoints(&urls, None).map_err(Self::http_error)?;
+		let Some(body) = body else { return Ok(Vec::new()) };
+		if body.is_empty() {
+			return Ok(Vec::new())
+		}
+
+		let mut responses = Vec::new();
+		for (command_id, outcome) in Self::decode_batch_response(&body)? {
+			let waiting = <CommandConnection<T>>::get(command_id) == Some(connection) &&
+				matches!(<CommandStatusOf<T>>::get(command_id), Some(s) if !s.is_final());
+			if !waiting {
+				log::debug!(
+					target: LOG_TARGET,
+					"response dropped connection={} command={} outcome=not_waiting",
+					connection.0,
+					command_id
+				);
+				continue
+			}
+			if outcome == CommandOutcome::Acknowledged {
+				if let Some(command) = Self::queued_command(command_id) {
+					Self::note_acknowledged(&command)?;
+				}
+				continue
+			}
+			match Self::settle(command_id, Ok(outcome)) {
+				Ok(response) => responses.push((command_id, response)),
+				Err(e) => log::error!(
+					target: LOG_TARGET,
+					"command failed command={} outcome=error error={:?}",
+					command_id,
+					e
+				),
+			}
+		}
+		Ok(responses)
+	}
+
+	/// Turns what CyberHub reported for `command_id` into the response to submit.
+	///
+	/// Anything else fails the command: errors reported by CyberHub are submitted along with the
+	/// command, responses that cannot be used only move it to `Failed`.
+	fn settle(
+		command_id: CommandId,
+		outcome: Result<CommandOutcome, &'static str>,
+	) -> Result<SignedResponseOf<T>, &'static str> {
+		let error = match outcome {
+			Ok(CommandOutcome::Ok(body, signature)) => match Self::bound_response(body) {
+				Ok(response) => return Ok((response, signature)),
+				Err(e) => e,
+			},
+			Ok(CommandOutcome::Error(body)) => {
+				Self::send_command_error(command_id, body)?;
+				return Err("CyberHub failed the command")
+			},
+			Ok(CommandOutcome::Acknowledged) => "CyberHub only acknowledged the command",
+			Err(e) => e,
+		};
+		Self::send_status_update(command_id, CommandStatus::Failed)?;
+		Err(error)
+	}
+
+	/// Notes that a request for `command_id` made it to CyberHub.
+	fn note_dispatched(command_id: CommandId) -> Result<(), &'static str> {
+		Self::clear_retry(command_id);
+		// The command made it to CyberHub, let observers know it actually left the node.
+		if <CommandStatusOf<T>>::get(command_id) == Some(CommandStatus::Queued) {
+			Self::send_status_update(command_id, CommandStatus::Dispatched)?;
+		}
+		Ok(())
+	}
+
+	/// Reports the connection `command_id` was sent over as unreachable if the request failed
+	/// with `e` before getting an answer.
+	///
+	/// Overlapping runs and the batches of a single run would all report the same outage, so
+	/// the worker remembers its own reports on top of the one recorded on chain.
+	fn note_unreachable(command_id: CommandId, e: &http::Error) -> Result<(), &'static str> {
+		if !matches!(e, http::Error::DeadlineReached | http::Error::IoError) {
+			return Ok(())
+		}
+		let Some(connection) = <CommandConnection<T>>::get(command_id) else { return Ok(()) };
+
+		let key = Self::unreachable_key(connection);
+		let reported = sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &key)
+			.and_then(|raw| BlockNumberFor::<T>::decode(&mut &raw[..]).ok());
+		let last = <LastUnreachableReport<T>>::get(connection).max(reported);
+		let block = <system::Pallet<T>>::block_number();
+		if !Self::unreachable_report_due(last, block) {
+			return Ok(())
+		}
+
+		sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, &key, &block.encode());
+		Self::send_unreachable_report(connection)
+	}
+
+	/// Key of the block at which the worker last reported `connection` unreachable.
+	fn unreachable_key(connection: ConnectionId) -> Vec<u8> {
+		(b"edge-connect::unreachable::", connection).encode()
+	}
+
+	/// Notes that the endpoint at `index` answered the request for `command_id`, recording a
+	/// failover on chain if another endpoint answered for its connection before.
+	///
+	/// The batches of a run would all report the same failover, so the worker remembers the
+	/// endpoint it reported until the chain catches up with it.
+	fn note_endpoint(command_id: CommandId, index: u32) -> Result<(), &'static str> {
+		let Some(connection) = <CommandConnection<T>>::get(command_id) else { return Ok(()) };
+		let Some(info) = <Connection<T>>::get(connection) else { return Ok(()) };
+
+		let key = Self::endpoint_key(connection);
+		if index == info.active_endpoint {
+			sp_io::offchain::local_storage_clear(StorageKind::PERSISTENT, &key);
+			return Ok(())
+		}
+		let reported = sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &key)
+			.and_then(|raw| u32::decode(&mut &raw[..]).ok());
+		if reported == Some(index) {
+			return Ok(())
+		}
+
+		sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, &key, &index.encode());
+		Self::send_endpoint_failover(connection, index)
+	}
+
+	/// Key of the endpoint the worker last reported as answering for `connection`.
+	fn endpoint_key(connection: ConnectionId) -> Vec<u8> {
+		(b"edge-connect::endpoint::", connection).encode()
+	}
+
+	/// Notes that CyberHub has no result for `command_id` yet.
+	///
+	/// Commands sent `ack_only` never await a response, they stay `Dispatched` until CyberHub
+	/// acknowledges them.
+	fn note_awaiting_response(command_id: CommandId) -> Result<(), &'static str> {
+		let ack_only = Self::queued_command(command_id).is_some_and(|command| command.ack_only);
+		if !ack_only && <CommandStatusOf<T>>::get(command_id) == Some(CommandStatus::Dispatched) {
+			Self::send_status_update(command_id, CommandStatus::AwaitingResponse)?;
+		}
+		Ok(())
+	}
+
+	/// Notes that CyberHub acknowledged `command`, which settles it if it was sent `ack_only`.
+	///
+	/// Other commands are still waiting on their result.
+	fn note_acknowledged(command: &Command<T>) -> Result<(), &'static str> {
+		if !command.ack_only {
+			return Self::note_awaiting_response(command.id)
+		}
+		if <CommandStatusOf<T>>::get(command.id).is_some_and(|status| status.is_final()) {
+			return Ok(())
+		}
+		Self::send_status_update(command.id, CommandStatus::Acknowledged)
+	}
+
+	/// Bounds a decoded response to `MaxResponseLength`.
+	///
+	/// Responses that do not fit are never truncated, the command fails instead.
+	fn bound_response(response: Vec<u8>) -> Result<ResponseOf<T>, &'static str> {
+		response.try_into().map_err(|_| "CyberHub response is too long")
+	}
+
+	/// Describes why a CyberHub request failed.
+	fn http_error(e: http::Error) -> &'static str {
+		match e {
+			http::Error::DeadlineReached => "CyberHub request timed out",
+			http::Error::IoError => "CyberHub request could not be sent",
+			http::Error::Unknown => "CyberHub returned an invalid response",
+		}
+	}
+
+	/// Builds the JSON request body sent to CyberHub for `command`.
+	///
+	/// The command id is included so that CyberHub can echo it back with the result.
+	fn encode_request(command: &Command<T>) -> Vec<u8> {
+		wire::to_json(&Self::command_request(command))
+	}
+
+	/// Builds the JSON array of requests sent to CyberHub for a batch of `commands`.
+	fn encode_batch_request(commands: &[Command<T>]) -> Vec<u8> {
+		wire::to_json(&commands.iter().map(Self::command_request).collect::<Vec<_>>())
 	}
 
-	// Errors inform users that something went wrong.
-	#[pallet::error]
-	pub enum Error<T> {
-		/// Returned if the connection already exists.
-		ConnectionAlreadyExists,
-		/// Returned if the connection does not exist.
-		ConnectionDoesNotExist,
-		/// Returned if the response is too large.
-		ResponseTooLarge,
-		/// Return error if the command is not valid.
-		InvalidCommand,
-		/// Returned if the command is too long.
-		CommandTooLong,
-		/// Returned if the command are too many.
-		TooManyCommands,
+	/// The request describing `command` to CyberHub, with the payload encoded by
+	/// `PayloadCodec`.
+	fn command_request(command: &Command<T>) -> wire::CommandRequest<'static> {
+		let server_pubkey = <CommandConnection<T>>::get(command.id)
+			.and_then(<Connection<T>>::get)
+			.and_then(|info| info.server_pubkey);
+		let payload =
+			T::PayloadCodec::encode_for_transport(&command.payload, server_pubkey.as_ref());
+		wire::CommandRequest {
+			command_id: command.id,
+			nonce: command.nonce,
+			command: String::from_utf8_lossy(&payload).into_owned().into(),
+			schema_hash: <CommandSchemas<T>>::get(command.id)
+				.map(|hash| wire::encode_hex(hash.as_bytes()).into()),
+			ack_only: command.ack_only,
+		}
+	}
+
+	/// Extracts the outcome from a CyberHub response envelope, making sure it answers
+	/// `command_id`.
+	fn decode_response(command_id: CommandId, body: &[u8]) -> Result<CommandOutcome, &'static str> {
+		let response: wire::CommandResponse =
+			wire::from_json(body).map_err(|_| "CyberHub response is not a valid envelope")?;
+		if response.command_id != command_id {
+			return Err("CyberHub response does not match the command")
+		}
+		Self::command_outcome(response)
+	}
+
+	/// Extracts the per-command outcomes from a CyberHub batch response body.
+	fn decode_batch_response(body: &[u8]) -> Result<Vec<BatchResult>, &'static str> {
+		let responses: Vec<wire::CommandResponse> =
+			wire::from_json(body).map_err(|_| "CyberHub response is not a valid batch")?;
+		responses
+			.into_iter()
+			.map(|response| Ok((response.command_id, Self::command_outcome(response)?)))
+			.collect()
+	}
+
+	/// What a response envelope says about its command.
+	fn command_outcome(response: wire::CommandResponse) -> Result<CommandOutcome, &'static str> {
+		let signature = match response.signature {
+			Some(hex) => Some(
+				wire::decode_hex(&hex)
+					.and_then(|raw| sr25519::Signature::try_from(&raw[..]).ok())
+					.ok_or("CyberHub response has a malformed signature")?,
+			),
+			None => None,
+		};
+		let body = response.body.into_owned().into_bytes();
+		Ok(match response.status {
+			wire::ResponseStatus::Ok => CommandOutcome::Ok(body, signature),
+			wire::ResponseStatus::Error => CommandOutcome::Error(body),
+			wire::ResponseStatus::Ack => CommandOutcome::Acknowledged,
+		})
+	}
+
+	/// Submits a signed transaction moving `command_id` to `status`.
+	fn send_status_update(command_id: CommandId, status: CommandStatus) -> Result<(), &'static str> {
+		let signer = Signer::<T, T::AuthorityId>::all_accounts();
+		if !signer.can_sign() {
+			return Err(
+				"No local accounts available. Consider adding one via `author_insertKey` RPC.",
+			)
+		}
+
+		let results = signer
+			.send_signed_transaction(|_account| Call::update_command_status { command_id, status });
+		for (acc, res) in &results {
+			match res {
+				Ok(()) => log::debug!(
+					target: LOG_TARGET,
+					"status update submitted command={} status={:?} account={:?} outcome=ok",
+					command_id,
+					status,
+					acc.id
+				),
+				Err(e) => log::error!(
+					target: LOG_TARGET,
+					"status update submission failed command={} status={:?} account={:?} \
+					 outcome=error error={:?}",
+					command_id,
+					status,
+					acc.id,
+					e
+				),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Submits a signed transaction failing `command_id` with the `error` CyberHub reported.
+	///
+	/// The error is only kept as a diagnostic, so it is cut down to `MaxResponseLength`
+	/// rather than being rejected when it does not fit.
+	fn send_command_error(command_id: CommandId, error: Vec<u8>) -> Result<(), &'static str> {
+		let signer = Signer::<T, T::AuthorityId>::all_accounts();
+		if !signer.can_sign() {
+			return Err(
+				"No local accounts available. Consider adding one via `author_insertKey` RPC.",
+			)
+		}
+
+		let error = BoundedVec::<u8, T::MaxResponseLength>::truncate_from(error);
+		let results = signer.send_signed_transaction(|_account| Call::report_command_error {
+			command_id,
+			error: error.clone(),
+		});
+		for (acc, res) in &results {
+			match res {
+				Ok(()) => log::debug!(
+					target: LOG_TARGET,
+					"error report submitted command={} account={:?} outcome=ok",
+					command_id,
+					acc.id
+				),
+				Err(e) => log::error!(
+					target: LOG_TARGET,
+					"error report submission failed command={} account={:?} \
+					 outcome=error error={:?}",
+					command_id,
+					acc.id,
+					e
+				),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Submits a signed transaction reporting `connection` as unreachable.
+	fn send_unreachable_report(connection: ConnectionId) -> Result<(), &'static str> {
+		let signer = Signer::<T, T::AuthorityId>::all_accounts();
+		if !signer.can_sign() {
+			return Err(
+				"No local accounts available. Consider adding one via `author_insertKey` RPC.",
+			)
+		}
+
+		let results =
+			signer.send_signed_transaction(|_account| Call::report_unreachable { connection });
+		for (acc, res) in &results {
+			match res {
+				Ok(()) => log::debug!(
+					target: LOG_TARGET,
+					"unreachable report submitted connection={} account={:?} outcome=ok",
+					connection.0,
+					acc.id
+				),
+				Err(e) => log::error!(
+					target: LOG_TARGET,
+					"unreachable report submission failed connection={} account={:?} \
+					 outcome=error error={:?}",
+					connection.0,
+					acc.id,
+					e
+				),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Submits a signed transaction recording that the endpoint at `index` answered for
+	/// `connection`.
+	fn send_endpoint_failover(connection: ConnectionId, index: u32) -> Result<(), &'static str> {
+		let signer = Signer::<T, T::AuthorityId>::all_accounts();
+		if !signer.can_sign() {
+			return Err(
+				"No local accounts available. Consider adding one via `author_insertKey` RPC.",
+			)
+		}
+
+		let results = signer.send_signed_transaction(|_account| Call::record_endpoint_failover {
+			connection,
+			index,
+		});
+		for (acc, res) in &results {
+			match res {
+				Ok(()) => log::debug!(
+					target: LOG_TARGET,
+					"failover submitted connection={} endpoint={} account={:?} outcome=ok",
+					connection.0,
+					index,
+					acc.id
+				),
+				Err(e) => log::error!(
+					target: LOG_TARGET,
+					"failover submission failed connection={} endpoint={} account={:?} \
+					 outcome=error error={:?}",
+					connection.0,
+					index,
+					acc.id,
+					e
+				),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Pings the health URL of the edge server at `url` and, if it answers, submits a signed
+	/// heartbeat for `connection`.
+	fn ping_and_send_heartbeat(url: &str, connection: ConnectionId) -> Result<(), &'static str> {
+		let signer = Signer::<T, T::AuthorityId>::all_accounts();
+		if !signer.can_sign() {
+			return Err(
+				"No local accounts available. Consider adding one via `author_insertKey` RPC.",
+			)
+		}
+
+		let health_url = Self::health_url(url);
+		Self::ping(&health_url).map_err(Self::http_error)?;
+
+		let results = signer.send_signed_transaction(|_account| Call::heartbeat { connection });
+		for (acc, res) in &results {
+			match res {
+				Ok(()) => log::debug!(
+					target: LOG_TARGET,
+					"heartbeat submitted connection={} account={:?} outcome=ok",
+					connection.0,
+					acc.id
+				),
+				Err(e) => log::error!(
+					target: LOG_TARGET,
+					"heartbeat submission failed connection={} account={:?} \
+					 outcome=error error={:?}",
+					connection.0,
+					acc.id,
+					e
+				),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// The health check URL of the edge server at `url`.
+	fn health_url(url: &str) -> String {
+		let mut health_url = String::from(url.trim_end_matches('/'));
+		health_url.push_str("/health");
+		health_url
+	}
+
+	/// The URL a long-polled edge server at `url` hands its results out at.
+	fn responses_url(url: &str) -> String {
+		let mut responses_url = String::from(url.trim_end_matches('/'));
+		responses_url.push_str("/responses");
+		responses_url
+	}
+
+	/// GETs `url` and succeeds if it answers with status 200.
+	fn ping(url: &str) -> Result<(), http::Error> {
+		let deadline = Self::request_deadline();
+		let pending = http::Request::get(url).deadline(deadline).send().map_err(|e| {
+			log::warn!(
+				target: LOG_TARGET,
+				"request not sent url={} outcome=io_error error={:?}",
+				url,
+				e
+			);
+			http::Error::IoError
+		})?;
+		let response = Self::wait_for(url, pending, deadline)?;
+		if response.code != 200 {
+			log::warn!(
+				target: LOG_TARGET,
+				"request failed url={} outcome=bad_status status={}",
+				url,
+				response.code
+			);
+			return Err(http::Error::Unknown)
+		}
+		Ok(())
+	}
+
+	/// The deadline for an HTTP request started now.
+	fn request_deadline() -> Timestamp {
+		sp_io::offchain::timestamp().add(Duration::from_millis(T::HttpRequestTimeout::get()))
+	}
+
+	/// Blocks until the request to `url` finishes, or abandons it once `deadline` passes.
+	fn wait_for(
+		url: &str,
+		pending: http::PendingRequest,
+		deadline: Timestamp,
+	) -> Result<http::Response, http::Error> {
+		// The request is already being processed by the host. The outer error means the
+		// deadline was reached, the inner one that the request itself failed.
+		match pending.try_wait(deadline) {
+			Ok(Ok(response)) => Ok(response),
+			Err(_) | Ok(Err(http::Error::DeadlineReached)) => {
+				log::warn!(
+					target: LOG_TARGET,
+					"request abandoned url={} outcome=deadline timeout_ms={}",
+					url,
+					T::HttpRequestTimeout::get()
+				);
+				Err(http::Error::DeadlineReached)
+			},
+			Ok(Err(e)) => {
+				log::warn!(
+					target: LOG_TARGET,
+					"request failed url={} outcome=transport_error error={:?}",
+					url,
+					e
+				);
+				Err(e)
+			},
+		}
+	}
+
+	/// POSTs `payload` to each of `endpoints` in turn until one answers, or GETs them without
+	/// one, and returns the index of that endpoint along with its raw response body, if any.
+	///
+	/// If none of them answers, the error of the last one is returned.
+	fn fetch_from_endpoints(
+		endpoints: &[&str],
+		payload: Option<&[u8]>,
+	) -> Result<(u32, Option<Vec<u8>>), http::Error> {
+		let mut error = http::Error::Unknown;
+		for (index, url) in endpoints.iter().enumerate() {
+			match Self::fetch_from_cyberhub(url, payload) {
+				Ok(body) => return Ok((index as u32, body)),
+				Err(e) => error = e,
+			}
+		}
+		Err(error)
+	}
+
+	/// POSTs `payload` to the CyberHub endpoint at `url`, or GETs it without one, and returns
+	/// the raw response body.
+	///
+	/// Returns `None` when CyberHub only acknowledged the request, with a `202 Accepted`.
+	// TODO: change http to websocket
+	fn fetch_from_cyberhub(
+		url: &str,
+		payload: Option<&[u8]>,
+	) -> Result<Option<Vec<u8>>, http::Error> {
+		// We want to keep the offchain worker execution time reasonable, so the external call
+		// has to complete within `HttpRequestTimeout`.
+		// You can also wait idefinitely for the response, however you may still get a timeout
+		// coming from the host machine.
+		let deadline = Self::request_deadline();
+		// Initiate an external HTTP POST request carrying the command as its body.
+		// This is using high-level wrappers from `sp_runtime`, for the low-level calls that
+		// you can find in `sp_io`. The API is trying to be similar to `reqwest`, but
+		// since we are running in a custom WASM execution environment we can't simply
+		// import the library here.
+		let request = match payload {
+			Some(payload) => http::Request::post(url, vec![payload]),
+			None => http::Request::get(url),
+		};
+		// We set the deadline for sending of the request, note that awaiting response can
+		// have a separate deadline.
+		let pending = request.deadline(deadline).send().map_err(|e| {
+			log::warn!(
+				target: LOG_TARGET,
+				"request not sent url={} outcome=io_error error={:?}",
+				url,
+				e
+			);
+			http::Error::IoError
+		})?;
+
+		let response = Self::wait_for(url, pending, deadline)?;
+		// Let's check the status code before we proceed to reading the response.
+		if response.code == 202 {
+			return Ok(None)
+		}
+		if response.code != 200 {
+			log::warn!(
+				target: LOG_TARGET,
+				"request failed url={} outcome=bad_status status={}",
+				url,
+				response.code
+			);
+			return Err(http::Error::Unknown)
+		}
+
+		// Next we want to fully read the response body and collect it to a vector of bytes.
+		// Reading stops early if the host fails to deliver the body, in which case the error
+		// is kept on the iterator.
+		let mut body = response.body();
+		let bytes = body.by_ref().collect::<Vec<u8>>();
+		if let Some(e) = body.error() {
+			log::warn!(
+				target: LOG_TARGET,
+				"response body not read url={} outcome=io_error error={:?}",
+				url,
+				e
+			);
+			return Err(http::Error::IoError)
+		}
+
+		Ok(Some(bytes))
+	}
+
+	/// The message an edge server signs to vouch for its `response` to `command_id`, the SCALE
+	/// encoding of `(command_id, response)`.
+	pub fn response_signing_payload(command_id: CommandId, response: &[u8]) -> Vec<u8> {
+		(command_id, response).encode()
+	}
+
+	/// Store the response to an in-flight command.
+	fn add_response(
+		maybe_who: Option<T::AccountId>,
+		command_id: CommandId,
+		response: BoundedVec<u8, T::MaxResponseLength>,
+		server_signature: Option<sr25519::Signature>,
+	) -> sp_runtime::DispatchResult {
+		// Edge servers that registered a key have to vouch for their responses.
+		let connection = <CommandConnection<T>>::get(command_id);
+		let server_pubkey =
+			connection.and_then(<Connection<T>>::get).and_then(|info| info.server_pubkey);
+		if let Some(server_pubkey) = server_pubkey {
+			let message = Self::response_signing_payload(command_id, &response);
+			ensure!(
+				server_signature.is_some_and(|signature| {
+					sp_io::crypto::sr25519_verify(&signature, &message, &server_pubkey)
+				}),
+				Error::<T>::InvalidResponseSignature
+			);
+		}
+
+		Self::accept_response(maybe_who, command_id, response)
+	}
+
+	/// Checks that `origin` is signed by one of the `OcwAuthorities` and returns its account.
+	fn ensure_ocw_authority(
+		origin: T::RuntimeOrigin,
+	) -> Result<T::AccountId, sp_runtime::DispatchError> {
+		let who = system::ensure_signed(origin)?;
+		ensure!(<OcwAuthorities<T>>::contains_key(&who), sp_runtime::DispatchError::BadOrigin);
+		Ok(who)
+	}
+
+	/// Stores a response that was vouched for, if its command still waits for one.
+	fn accept_response(
+		maybe_who: Option<T::AccountId>,
+		command_id: CommandId,
+		response: BoundedVec<u8, T::MaxResponseLength>,
+	) -> sp_runtime::DispatchResult {
+		// Only commands that were dispatched and have not reached a final status can receive a
+		// response.
+		let status = <CommandStatusOf<T>>::get(command_id).ok_or(Error::<T>::UnknownCommandId)?;
+		ensure!(!status.is_final(), Error::<T>::CommandAlreadyCompleted);
+		ensure!(
+			matches!(status, CommandStatus::Dispatched | CommandStatus::AwaitingResponse),
+			Error::<T>::CommandNotDispatched
+		);
+
+		let connection = <CommandConnection<T>>::get(command_id);
+		log::info!(target: LOG_TARGET, "response accepted command={} outcome=completed", command_id);
+		let hash = T::ResponseHasher::hash(&response);
+		if T::HashResponses::get() {
+			sp_io::offchain_index::set(&Self::response_body_key(hash), &response);
+			let algorithm = T::ResponseHasher::algorithm();
+			<ResponseHashes<T>>::insert(command_id, ResponseHash { hash, algorithm });
+		} else {
+			<Responses<T>>::insert(command_id, &response);
+		}
+		Self::set_command_status(command_id, CommandStatus::Completed);
+		if let Some(connection) = connection {
+			<Connection<T>>::mutate(connection, |info| {
+				if let Some(info) = info {
+					info.last_seen = <system::Pallet<T>>::block_number();
+				}
+			});
+		}
+
+		T::OnResponse::on_command_response(command_id, &response);
+
+		// Emit an event that new response has been received.
+		Self::deposit_event(Event::ResponseReceived {
+			command_id,
+			maybe_who,
+			response_hash: hash,
+		});
+
+		Ok(())
+	}
+
+	/// Charges `CommandFee` to `who`, paying it to `FeeDestination` or burning it.
+	fn charge_command_fee(who: &T::AccountId) -> sp_runtime::DispatchResult {
+		let amount = T::CommandFee::get();
+		if amount.is_zero() {
+			return Ok(())
+		}
+
+		match T::FeeDestination::get() {
+			Some(dest) =>
+				T::Currency::transfer(who, &dest, amount, ExistenceRequirement::KeepAlive),
+			None => T::Currency::withdraw(
+				who,
+				amount,
+				WithdrawReasons::FEE,
+				ExistenceRequirement::KeepAlive,
+			)
+			.map(drop),
+		}
+		.map_err(|_| Error::<T>::InsufficientBalance)?;
+
+		Self::deposit_event(Event::CommandFeePaid { who: who.clone(), amount });
+		Ok(())
+	}
+
+	/// Checks and queues a command sent by `who` with `send_command` or `send_raw_command`.
+	fn do_send_command(
+		who: T::AccountId,
+		connection: ConnectionId,
+		command: BoundedVec<u8, T::MaxCommandLength>,
+		options: SendOptionsOf<T>,
+	) -> DispatchResultWithPostInfo {
+		let SendOptions { priority, valid_until, client_ref, ack_only, seq } = options;
+		// A retry of a command that is still stored gets that command back, even once the
+		// queue is full or the connection is rate limited.
+		if let Some(client_ref) = client_ref {
+			if let Some(command_id) = <CommandByClientRef<T>>::get(connection, client_ref) {
+				Self::deposit_event(Event::CommandDeduplicated {
+					connection,
+					client_ref,
+					command_id,
+				});
+				return Ok(().into())
+			}
+		}
+
+		let now = <system::Pallet<T>>::block_number();
+		let rejected = T::WeightInfo::send_command_rejected();
+		ensure!(
+			valid_until.is_none_or(|valid_until| valid_until >= now),
+			Error::<T>::CommandAlreadyExpired.with_weight(rejected)
+		);
+
+		// Check that the connection exists, accepts commands and the signer may use it.
+		Self::can_send_command(&who, connection).map_err(|error| {
+			let early = [Error::<T>::ConnectionDoesNotExist, Error::<T>::NotAuthorizedToSend]
+				.into_iter()
+				.any(|early| error == early.into());
+			if early {
+				error.with_weight(rejected)
+			} else {
+				error.into()
+			}
+		})?;
+
+		// Make sure there is something to send.
+		ensure!(!command.is_empty(), Error::<T>::InvalidCommand);
+
+		// Numbered commands must come in order, without any left out.
+		if let Some(seq) = seq {
+			let next = <LastSeq<T>>::get(&who).map_or(Some(0), |last| last.checked_add(1));
+			ensure!(next.is_some_and(|next| seq >= next), Error::<T>::StaleSequence);
+			ensure!(next == Some(seq), Error::<T>::SequenceGap);
+			<LastSeq<T>>::insert(&who, seq);
+		}
+
+		Self::charge_command_fee(&who)?;
+		let command_id =
+			Self::do_queue_command(who, connection, command, priority, valid_until, ack_only)?;
+		if let Some(client_ref) = client_ref {
+			<CommandByClientRef<T>>::insert(connection, client_ref, command_id);
+			<CommandClientRef<T>>::insert(command_id, client_ref);
+		}
+
+		Ok(().into())
+	}
+
+	/// Pushes `command` from `who` onto the queue of `connection` and returns its id.
+	fn do_queue_command(
+		who: T::AccountId,
+		connection: ConnectionId,
+		command: BoundedVec<u8, T::MaxCommandLength>,
+		priority: u8,
+		valid_until: Option<BlockNumberFor<T>>,
+		ack_only: bool,
+	) -> Result<CommandId, sp_runtime::DispatchError> {
+		let command_id = <NextCommandId<T>>::get();
+		let nonce = <NextCommandNonce<T>>::get(connection);
+		let command = Command {
+			id: command_id,
+			nonce,
+			payload: command,
+			priority,
+			submitter: who,
+			submitted_at: <system::Pallet<T>>::block_number(),
+			dispatched_at: None,
+			settled_at: None,
+			valid_until,
+			ack_only,
+		};
+
+		// Push the command onto the connection's queue, if there's room
+		let depth = <CommandQueue<T>>::try_mutate(connection, |queue| {
+			queue.try_push(command).map_err(|_| Error::<T>::CommandQueueFull)?;
+			Ok::<_, Error<T>>(queue.len() as u32)
+		})?;
+		<CommandConnection<T>>::insert(command_id, connection);
+		<ConnectionCommands<T>>::insert(connection, command_id, ());
+		<CommandStatusOf<T>>::insert(command_id, CommandStatus::Queued);
+		Self::note_command_status(command_id, None, Some(CommandStatus::Queued));
+		<NextCommandId<T>>::put(command_id.wrapping_add(1));
+		<NextCommandNonce<T>>::insert(connection, nonce.wrapping_add(1));
+		<CommandsInBlock<T>>::mutate(connection, |count| *count = count.saturating_add(1));
+
+		// Emit an event.
+		Self::deposit_event(Event::CommandQueued { connection, command_id, nonce });
+		Self::note_queue_depth(connection, depth);
+
+		Ok(command_id)
+	}
+
+	/// Raises `QueueDepthHigh` once the queue of `connection`, now `depth` commands long,
+	/// reaches `QueueDepthAlertThreshold`, and `QueueDepthRecovered` once it falls back below.
+	///
+	/// A connection raises the alert at most once every `QueueDepthAlertInterval` blocks, so
+	/// that a queue hovering around the threshold does not flood clients with events.
+	fn note_queue_depth(connection: ConnectionId, depth: u32) {
+		let alert = <QueueDepthAlert<T>>::get(connection);
+		if depth >= T::QueueDepthAlertThreshold::get() {
+			let now = <system::Pallet<T>>::block_number();
+			let due = match alert {
+				Some((_, true)) => false,
+				Some((raised_at, false)) => now >= raised_at + T::QueueDepthAlertInterval::get(),
+				None => true,
+			};
+			if due {
+				<QueueDepthAlert<T>>::insert(connection, (now, true));
+				Self::deposit_event(Event::QueueDepthHigh { connection, depth });
+			}
+		} else if let Some((raised_at, true)) = alert {
+			<QueueDepthAlert<T>>::insert(connection, (raised_at, false));
+			Self::deposit_event(Event::QueueDepthRecovered { connection, depth });
+		}
+	}
+
+	/// Fills the `{n}` placeholders of a template `payload` with the matching `params`.
+	fn fill_template(
+		payload: &[u8],
+		params: &[BoundedVec<u8, T::MaxCommandLength>],
+	) -> Result<BoundedVec<u8, T::MaxCommandLength>, sp_runtime::DispatchError> {
+		let mut command = Vec::with_capacity(payload.len());
+		let mut rest = payload;
+		while let Some(open) = rest.iter().position(|b| *b == b'{') {
+			command.extend_from_slice(&rest[..open]);
+			rest = &rest[open..];
+			let digits = rest[1..].iter().take_while(|b| b.is_ascii_digit()).count();
+			if digits == 0 || rest.get(digits + 1) != Some(&b'}') {
+				// Not a placeholder, keep the brace.
+				command.push(b'{');
+				rest = &rest[1..];
+				continue
+			}
+			let param = rest[1..=digits]
+				.iter()
+				.try_fold(0usize, |index, digit| {
+					index.checked_mul(10)?.checked_add((digit - b'0') as usize)
+				})
+				.and_then(|index| params.get(index))
+				.ok_or(Error::<T>::MissingTemplateParam)?;
+			command.extend_from_slice(param);
+			rest = &rest[digits + 2..];
+		}
+		command.extend_from_slice(rest);
+		Ok(command.try_into().map_err(|_| Error::<T>::TemplateCommandTooLong)?)
+	}
+
+	/// Validates and stores a new connection owned by `who`, reserving `deposit` from them, and
+	/// returns its id. The connection is removed at `expires_at`, if given.
+	fn do_create_connection(
+		who: &T::AccountId,
+		url: Vec<u8>,
+		label: Option<Vec<u8>>,
+		deposit: BalanceOf<T>,
+		expires_at: Option<BlockNumberFor<T>>,
+		poll_mode: PollMode,
+	) -> Result<ConnectionId, sp_runtime::DispatchError> {
+		let endpoints: EndpointsOf<T> =
+			vec![Self::bound_url(url)?].try_into().map_err(|_| Error::<T>::TooManyEndpoints)?;
+		let label = Self::bound_label(label)?;
+
+		// Check that the owner does not already have a connection to this edge server.
+		Self::ensure_no_clash(who, &endpoints, &label, None)?;
+
+		// Allocate an identifier for the new connection, the lowest freed one if there is one.
+		// Fresh ids never wrap around, so once they run out only freed ones are handed out.
+		let mut free = <FreeConnectionIds<T>>::get();
+		let (connection, next) = if free.is_empty() {
+			let connection = <NextConnectionId<T>>::get();
+			(connection, Some(connection.0.checked_add(1).ok_or(Error::<T>::ConnectionIdExhausted)?))
+		} else {
+			(free.remove(0), None)
+		};
+
+		T::Currency::reserve(who, deposit).map_err(|_| Error::<T>::InsufficientBalance)?;
+
+		let now = <system::Pallet<T>>::block_number();
+		let info = ConnectionInfo {
+			endpoints,
+			label,
+			owner: who.clone(),
+			created_at: now,
+			last_seen: now,
+			status: ConnectionStatus::Active,
+			authorized_senders: Default::default(),
+			deposit,
+			tags: Default::default(),
+			server_pubkey: None,
+			active_endpoint: 0,
+			last_lifecycle_block: now,
+			expires_at,
+			generation: <ConnectionGeneration<T>>::get(connection),
+			poll_mode,
+			version: 0,
+		};
+
+		// Update storage.
+		<Connection<T>>::insert(connection, info);
+		<ConnectionsOf<T>>::try_mutate(who, |connections| {
+			connections.try_push(connection).map_err(|_| Error::<T>::TooManyConnections)
+		})?;
+		match next {
+			Some(next) => <NextConnectionId<T>>::put(ConnectionId(next)),
+			None => <FreeConnectionIds<T>>::put(free),
+		}
+		Self::note_connection_status(None, Some(ConnectionStatus::Active));
+		if let Some(at) = expires_at {
+			Self::schedule::<ConnectionExpiries<T>, _>(at, connection);
+		}
+
+		Ok(connection)
+	}
+
+	/// Removes `connection` along with its queued commands and returns the deposit to its owner.
+	fn do_remove_connection(connection: ConnectionId, info: ConnectionInfo<T>) {
+		<Connection<T>>::remove(connection);
+		<ConnectionsOf<T>>::mutate(&info.owner, |connections| {
+			connections.retain(|id| *id != connection)
+		});
+		for command in <CommandQueue<T>>::take(connection) {
+			Self::remove_command(command.id);
+		}
+		<LastUnreachableReport<T>>::remove(connection);
+		<QueueDepthAlert<T>>::remove(connection);
+		<Leases<T>>::remove(connection);
+		// Free the id up for a connection of the next generation, unless enough ids are free
+		// already. Nonces carry on over the generations of an id, so that CyberHub does not turn
+		// down the commands of the next connection as already seen.
+		let freed = <FreeConnectionIds<T>>::mutate(|free| {
+			free.binary_search(&connection)
+				.err()
+				.is_some_and(|index| free.try_insert(index, connection).is_ok())
+		});
+		if freed {
+			<ConnectionGeneration<T>>::mutate(connection, |generation| {
+				*generation = generation.wrapping_add(1)
+			});
+		} else {
+			<NextCommandNonce<T>>::remove(connection);
+		}
+		<InFlightCommands<T>>::remove(connection);
+		<RecentOutcomes<T>>::remove(connection);
+		let entries = <MetadataEntries<T>>::take(connection);
+		let _ = <ConnectionMetadata<T>>::clear_prefix(connection, entries, None);
+		T::Currency::unreserve(&info.owner, info.deposit);
+		Self::note_connection_status(Some(info.status), None);
+	}
+
+	/// Validates an endpoint URL: it must use the `http` or `https` scheme, fit in
+	/// `MaxUrlLength` bytes and be hosted on an authorized domain.
+	fn bound_url(url: Vec<u8>) -> Result<UrlOf<T>, sp_runtime::DispatchError> {
+		// Only plain web endpoints are supported.
+		ensure!(
+			url.starts_with(b"http://") || url.starts_with(b"https://"),
+			Error::<T>::InvalidUrl
+		);
+		let url: UrlOf<T> = url.try_into().map_err(|_| Error::<T>::UrlTooLong)?;
+		let host = Self::bound_domain(Self::url_host(&url).to_vec())
+			.map_err(|_| Error::<T>::DomainNotAuthorized)?;
+		ensure!(<AuthorizedDomains<T>>::contains_key(host), Error::<T>::DomainNotAuthorized);
+		Ok(url)
+	}
+
+	/// Returns the host of an `http` or `https` URL, without user info or port.
+	fn url_host(url: &[u8]) -> &[u8] {
+		let rest = url
+			.strip_prefix(b"https://")
+			.or_else(|| url.strip_prefix(b"http://"))
+			.unwrap_or(url);
+		let authority = rest.split(|b| matches!(b, b'/' | b'?' | b'#')).next().unwrap_or(rest);
+		let host = authority.rsplit(|b| *b == b'@').next().unwrap_or(authority);
+		if host.starts_with(b"[") {
+			// IPv6 literals keep their brackets, the port follows the closing one.
+			return host.split_inclusive(|b| *b == b']').next().unwrap_or(host)
+		}
+		host.split(|b| *b == b':').next().unwrap_or(host)
+	}
+
+	/// Converts a domain to its lowercased, bounded form.
+	fn bound_domain(
+		mut domain: Vec<u8>,
+	) -> Result<BoundedVec<u8, T::MaxUrlLength>, sp_runtime::DispatchError> {
+		ensure!(!domain.is_empty(), Error::<T>::InvalidDomain);
+		domain.make_ascii_lowercase();
+		Ok(domain.try_into().map_err(|_| Error::<T>::InvalidDomain)?)
+	}
+
+	/// Converts a command to its bounded form.
+	fn bound_command(
+		command: Vec<u8>,
+	) -> Result<BoundedVec<u8, T::MaxCommandLength>, sp_runtime::DispatchError> {
+		Ok(command.try_into().map_err(|_| Error::<T>::CommandTooLong)?)
+	}
+
+	/// Converts a connection label to its bounded form.
+	fn bound_label(
+		label: Option<Vec<u8>>,
+	) -> Result<Option<BoundedVec<u8, T::MaxLabelLength>>, sp_runtime::DispatchError> {
+		label
+			.map(|label| label.try_into().map_err(|_| Error::<T>::LabelTooLong.into()))
+			.transpose()
+	}
+
+	/// Converts connection tags to their bounded form, rejecting empty and repeated tags.
+	fn bound_tags(tags: Vec<Vec<u8>>) -> Result<TagsOf<T>, sp_runtime::DispatchError> {
+		let mut bounded = TagsOf::<T>::default();
+		for tag in tags {
+			ensure!(!tag.is_empty(), Error::<T>::InvalidTag);
+			let tag: TagOf<T> = tag.try_into().map_err(|_| Error::<T>::InvalidTag)?;
+			ensure!(!bounded.contains(&tag), Error::<T>::DuplicateTag);
+			bounded.try_push(tag).map_err(|_| Error::<T>::TooManyTags)?;
+		}
+		Ok(bounded)
+	}
+
+	/// Whether a connection last reported unreachable at `last` may be reported again at
+	/// `block`.
+	fn unreachable_report_due(
+		last: Option<BlockNumberFor<T>>,
+		block: BlockNumberFor<T>,
+	) -> bool {
+		let interval = T::UnreachableReportInterval::get();
+		!matches!(last, Some(last) if block < last.saturating_add(interval))
+	}
+
+	/// Converts a connection metadata key to its bounded form.
+	fn bound_metadata_key(key: Vec<u8>) -> Result<MetadataKeyOf<T>, sp_runtime::DispatchError> {
+		ensure!(!key.is_empty(), Error::<T>::InvalidMetadataKey);
+		Ok(key.try_into().map_err(|_| Error::<T>::InvalidMetadataKey)?)
+	}
+
+	/// Makes sure none of the connections of `owner`, other than `skip`, already uses one of
+	/// `endpoints` or `label`.
+	fn ensure_no_clash(
+		owner: &T::AccountId,
+		endpoints: &[UrlOf<T>],
+		label: &Option<BoundedVec<u8, T::MaxLabelLength>>,
+		skip: Option<ConnectionId>,
+	) -> sp_runtime::DispatchResult {
+		for id in <ConnectionsOf<T>>::get(owner) {
+			if Some(id) == skip {
+				continue
+			}
+			let Some(other) = <Connection<T>>::get(id) else { continue };
+			ensure!(
+				!other.endpoints.iter().any(|url| endpoints.contains(url)),
+				Error::<T>::ConnectionAlreadyExists
+			);
+			ensure!(label.is_none() || &other.label != label, Error::<T>::DuplicateLabel);
+		}
+		Ok(())
+	}
+
+	/// Applies `f` to the connection record, provided it exists, is owned by `who` and is at
+	/// `expected_version` if one is given, then bumps its version.
+	fn mutate_owned_connection(
+		who: &T::AccountId,
+		connection: ConnectionId,
+		expected_version: Option<u32>,
+		f: impl FnOnce(&mut ConnectionInfo<T>) -> sp_runtime::DispatchResult,
+	) -> sp_runtime::DispatchResult {
+		<Connection<T>>::try_mutate(connection, |maybe_info| {
+			let info = maybe_info.as_mut().ok_or(Error::<T>::ConnectionDoesNotExist)?;
+			ensure!(&info.owner == who, Error::<T>::NotConnectionOwner);
+			ensure!(
+				expected_version.unwrap_or(info.version) == info.version,
+				Error::<T>::VersionMismatch
+			);
+			let status = info.status;
+			f(info)?;
+			info.version = info.version.wrapping_add(1);
+			Self::note_connection_status(Some(status), Some(info.status));
+			if info.status != status || &info.owner != who {
+				info.last_lifecycle_block = <system::Pallet<T>>::block_number();
+			}
+			Ok(())
+		})
 	}
 
-	#[pallet::validate_unsigned]
-	impl<T: Config> ValidateUnsigned for Pallet<T> {
-		type Call = Call<T>;
+	/// Records the new status of a command and lets clients know about it.
+	fn set_command_status(command_id: CommandId, status: CommandStatus) {
+		let previous = <CommandStatusOf<T>>::mutate(command_id, |current| current.replace(status));
+		Self::note_command_status(command_id, previous, Some(status));
+		if status.is_final() {
+			let now = <system::Pallet<T>>::block_number();
+			Self::mutate_queued_command(command_id, |command| command.settled_at = Some(now));
+			Self::schedule_prune(command_id, now);
+			if let Some(broadcast_id) = <CommandBroadcast<T>>::get(command_id) {
+				Self::settle_broadcast_command(broadcast_id);
+			}
+		} else if status == CommandStatus::AwaitingResponse {
+			Self::schedule_timeout(command_id);
+		}
+		Self::deposit_event(Event::CommandStatusChanged { command_id, status });
+		match status {
+			CommandStatus::Dispatched =>
+				if let Some(connection) = <CommandConnection<T>>::get(command_id) {
+					Self::deposit_event(Event::CommandDispatched { command_id, connection });
+				},
+			CommandStatus::Failed => Self::deposit_event(Event::CommandFailed { command_id }),
+			CommandStatus::TimedOut => Self::deposit_event(Event::CommandTimedOut { command_id }),
+			CommandStatus::Expired => Self::deposit_event(Event::CommandExpired { command_id }),
+			CommandStatus::Acknowledged =>
+				Self::deposit_event(Event::CommandAcknowledged { command_id }),
+			_ => {},
+		}
+	}
 
-		/// Validate unsigned call to this module.
-		///
-		/// By default unsigned transactions are disallowed, but implementing the validator
-		/// here we make sure that some particular calls (the ones produced by offchain worker)
-		/// are being whitelisted and marked as valid.
-		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
-			let valid_tx = |provide| ValidTransaction::with_tag_prefix("ocw-edge:")
-				.priority(UNSIGNED_TXS_PRIORITY)
-				.and_provides([&provide])
-				.longevity(3)
-				.propagate(true)
-				.build();
+	/// Keeps the connection counters in step with a connection going from status `from` to
+	/// `to`, `None` standing for a connection that does not exist.
+	fn note_connection_status(from: Option<ConnectionStatus>, to: Option<ConnectionStatus>) {
+		match (from, to) {
+			(None, Some(_)) => <TotalConnectionCount<T>>::mutate(|count| count.saturating_inc()),
+			(Some(_), None) => <TotalConnectionCount<T>>::mutate(|count| count.saturating_dec()),
+			_ => {},
+		}
+		let active = Some(ConnectionStatus::Active);
+		if from == active && to != active {
+			<ActiveConnectionCount<T>>::mutate(|count| count.saturating_dec());
+		} else if from != active && to == active {
+			<ActiveConnectionCount<T>>::mutate(|count| count.saturating_inc());
+		}
+	}
 
-			match call {
-				Call::submit_response_unsigned { block_number: _block_number, response: _response } => valid_tx(b"submit_number_unsigned".to_vec()),
-				Call::submit_response_unsigned_with_signed_payload {
-					ref response_payload,
-					ref signature
-				} => {
-					if !SignedPayload::<T>::verify::<T::AuthorityId>(response_payload, signature.clone()) {
-						return InvalidTransaction::BadProof.into();
-					}
-					valid_tx(b"submit_number_unsigned_with_signed_payload".to_vec())
-				},
-				_ => InvalidTransaction::Call.into(),
+	/// Keeps `TotalQueuedCommands` and the `InFlightCommands` of its connection in step with
+	/// `command_id` going from status `from` to `to`, `None` standing for a command that is not
+	/// stored.
+	fn note_command_status(
+		command_id: CommandId,
+		from: Option<CommandStatus>,
+		to: Option<CommandStatus>,
+	) {
+		let queued = Some(CommandStatus::Queued);
+		if from == queued && to != queued {
+			<TotalQueuedCommands<T>>::mutate(|count| count.saturating_dec());
+		} else if from != queued && to == queued {
+			<TotalQueuedCommands<T>>::mutate(|count| count.saturating_inc());
+		}
+
+		// Commands the edge server ran, or failed to, count towards the health of their
+		// connection.
+		if from != to {
+			match to {
+				Some(CommandStatus::Completed) | Some(CommandStatus::Acknowledged) =>
+					Self::record_outcome(command_id, true),
+				Some(CommandStatus::Failed) | Some(CommandStatus::TimedOut) =>
+					Self::record_outcome(command_id, false),
+				_ => {},
 			}
 		}
-	}
-}
 
-/// Payload used by this crate to hold response
-/// data required to submit a transaction.
-#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
-pub struct ResponsePayload<Public, BlockNumber> {
-	block_number: BlockNumber,
-	response: String,
-	public: Public,
-}
+		let awaiting = Some(CommandStatus::AwaitingResponse);
+		if (from == awaiting) == (to == awaiting) {
+			return
+		}
+		let Some(connection) = <CommandConnection<T>>::get(command_id) else { return };
+		if from == awaiting {
+			<InFlightCommands<T>>::mutate(connection, |count| count.saturating_dec());
+		} else {
+			<InFlightCommands<T>>::mutate(connection, |count| count.saturating_inc());
+		}
+	}
 
-impl<T: SigningTypes> SignedPayload<T> for ResponsePayload<T::Public, BlockNumberFor<T>> {
-	fn public(&self) -> T::Public {
-		self.public.clone()
+	/// Appends the outcome of `command_id` to the `RecentOutcomes` of its connection, dropping
+	/// the oldest one once `HealthWindow` of them are kept.
+	fn record_outcome(command_id: CommandId, completed: bool) {
+		let Some(connection) = <CommandConnection<T>>::get(command_id) else { return };
+		<RecentOutcomes<T>>::mutate(connection, |outcomes| {
+			if outcomes.is_full() && !outcomes.is_empty() {
+				outcomes.remove(0);
+			}
+			// Only fails for an empty window, which keeps no outcomes.
+			let _ = outcomes.try_push(completed);
+		});
 	}
-}
-enum TransactionType {
-	Signed,
-	UnsignedForAny,
-	UnsignedForAll,
-	Raw,
-	None,
-}
 
-impl<T: Config> Pallet<T> {
-	/// Chooses which transaction type to send.
-	///
-	/// This function serves mostly to showcase `StorageValue` helper
-	/// and local storage usage.
+	/// Appends the dispatch of `command` in block `now` to the offchain-indexed dispatch log.
 	///
-	/// Returns a type of transaction that should be produced in current run.
-	fn choose_transaction_type(block_number: BlockNumberFor<T>) -> TransactionType {
-		/// A friendlier name for the error that is going to be returned in case we are in the grace
-		/// period.
-		const RECENTLY_SENT: () = ();
+	/// Entries are never written over by later blocks, the log only grows.
+	fn log_dispatch(now: BlockNumberFor<T>, command: &Command<T>) {
+		let Some(connection) = <CommandConnection<T>>::get(command.id) else { return };
+		let index = match <DispatchLogCursor<T>>::get() {
+			Some((block, count)) if block == now => count,
+			_ => 0,
+		};
+		let entry = DispatchLogEntry {
+			block: now,
+			command_id: command.id,
+			connection,
+			payload_hash: H256::from(sp_io::hashing::blake2_256(&command.payload)),
+		};
+		sp_io::offchain_index::set(&Self::dispatch_log_key(now, index), &entry.encode());
+		<DispatchLogCursor<T>>::put((now, index.saturating_add(1)));
+	}
 
-		// Start off by creating a reference to Local Storage value.
-		// Since the local storage is common for all offchain workers, it's a good practice
-		// to prepend your entry with the module name.
-		let val = StorageValueRef::persistent(b"example_ocw::last_send");
-		// The Local Storage is persisted and shared between runs of the offchain workers,
-		// and offchain workers may run concurrently. We can use the `mutate` function, to
-		// write a storage entry in an atomic fashion. Under the hood it uses `compare_and_set`
-		// low-level method of local storage API, which means that only one worker
-		// will be able to "acquire a lock" and send a transaction if multiple workers
-		// happen to be executed concurrently.
-		let res = val.mutate(|last_send: Result<Option<BlockNumberFor<T>>, StorageRetrievalError>| {
-			match last_send {
-				// If we already have a value in storage and the block number is recent enough
-				// we avoid sending another transaction at this time.
-				Ok(Some(block)) if block_number < block + T::GracePeriod::get() =>
-					Err(RECENTLY_SENT),
-				// In every other case we attempt to acquire the lock and send a transaction.
-				_ => Ok(block_number),
+	/// Counts one more command of `broadcast_id` as settled, completing the broadcast once none
+	/// is left.
+	fn settle_broadcast_command(broadcast_id: BroadcastId) {
+		<Broadcasts<T>>::mutate(broadcast_id, |broadcast| {
+			let Some(broadcast) = broadcast else { return };
+			broadcast.pending.saturating_dec();
+			if broadcast.pending == 0 {
+				Self::deposit_event(Event::BroadcastCompleted { broadcast_id });
 			}
 		});
+	}
 
-		// The result of `mutate` call will give us a nested `Result` type.
-		// The first one matches the return of the closure passed to `mutate`, i.e.
-		// if we return `Err` from the closure, we get an `Err` here.
-		// In case we return `Ok`, here we will have another (inner) `Result` that indicates
-		// if the value has been set to the storage correctly - i.e. if it wasn't
-		// written to in the meantime.
-		match res {
-			// The value has been set correctly, which means we can safely send a transaction now.
-			Ok(block_number) => {
-				// We will send different transactions based on a random number.
-				// Note that this logic doesn't really guarantee that the transactions will be sent
-				// in an alternating fashion (i.e. fairly distributed). Depending on the execution
-				// order and lock acquisition, we may end up for instance sending two `Signed`
-				// transactions in a row. If a strict order is desired, it's better to use
-				// the storage entry for that. (for instance store both block number and a flag
-				// indicating the type of next transaction to send).
-				let transaction_type = block_number % 4u32.into();
-				if transaction_type == Zero::zero() {
-					TransactionType::Signed
-				} else if transaction_type == (BlockNumberFor::<T>::from(1u8)) {
-					TransactionType::UnsignedForAny
-				} else if transaction_type == (BlockNumberFor::<T>::from(2u8)) {
-					TransactionType::UnsignedForAll
-				} else {
-					TransactionType::Raw
-				}
-			},
-			// We are in the grace period, we should not send a transaction this time.
-			Err(MutateStorageError::ValueFunctionFailed(RECENTLY_SENT)) => TransactionType::None,
-			// We wanted to send a transaction, but failed to write the block number (acquire a
-			// lock). This indicates that another offchain worker that was running concurrently
-			// most likely executed the same logic and succeeded at writing to storage.
-			// Thus we don't really want to send the transaction, knowing that the other run
-			// already did.
-			Err(MutateStorageError::ConcurrentModification(_)) => TransactionType::None,
+	/// Returns the queued command with id `command_id`, if there is one.
+	fn queued_command(command_id: CommandId) -> Option<Command<T>> {
+		let connection = <CommandConnection<T>>::get(command_id)?;
+		<CommandQueue<T>>::get(connection).into_iter().find(|command| command.id == command_id)
+	}
+
+	/// Applies `f` to the queued command with id `command_id`, if there is one.
+	fn mutate_queued_command(command_id: CommandId, f: impl FnOnce(&mut Command<T>)) {
+		let Some(connection) = <CommandConnection<T>>::get(command_id) else { return };
+		<CommandQueue<T>>::mutate(connection, |queue| {
+			if let Some(command) = queue.iter_mut().find(|command| command.id == command_id) {
+				f(command);
+			}
+		});
+	}
+
+	/// Forgets how many commands were queued on each connection in the previous block.
+	fn reset_commands_in_block() -> frame_support::weights::Weight {
+		// Only connections that got a command in the previous block have an entry, so this is
+		// bounded by the size of that block.
+		let removed = <CommandsInBlock<T>>::clear(u32::MAX, None);
+		T::DbWeight::get().reads_writes(removed.loops.into(), removed.unique.into())
+	}
+
+	/// Lists `command_id` in the `CommandDeadlines` of the block it times out in, or of the first
+	/// block after that with room left.
+	fn schedule_timeout(command_id: CommandId) {
+		let Some(dispatched_at) =
+			Self::queued_command(command_id).and_then(|command| command.dispatched_at)
+		else {
+			return
+		};
+
+		let at = dispatched_at
+			.saturating_add(T::CommandTimeout::get())
+			.saturating_add(1u32.into());
+		Self::schedule::<CommandDeadlines<T>, _>(at, command_id);
+	}
+
+	/// Lists `command_id`, which settled in block `settled_at`, in the `CommandPrunes` of the
+	/// block it is pruned in, or of the first block after that with room left.
+	fn schedule_prune(command_id: CommandId, settled_at: BlockNumberFor<T>) {
+		let at = settled_at
+			.saturating_add(T::CommandRetentionBlocks::get())
+			.saturating_add(1u32.into());
+		Self::schedule::<CommandPrunes<T>, _>(at, command_id);
+	}
+
+	/// Appends `id` to the entry of `Schedule` for block `at`, or for the first block after it
+	/// with room left. Blocks that already started are moved on to the next one.
+	fn schedule<Schedule, Id>(at: BlockNumberFor<T>, id: Id)
+	where
+		Schedule: frame_support::storage::TryAppendMap<
+			BlockNumberFor<T>,
+			BoundedVec<Id, T::MaxPrunePerBlock>,
+			Id,
+		>,
+		Id: parity_scale_codec::FullCodec + Clone,
+	{
+		// Nothing would ever fit.
+		if T::MaxPrunePerBlock::get() == 0 {
+			return
+		}
+
+		let next = <system::Pallet<T>>::block_number().saturating_add(1u32.into());
+		let mut at = at.max(next);
+		while Schedule::try_append(at, id.clone()).is_err() {
+			at.saturating_inc();
 		}
 	}
 
-	/// A helper function to fetch the response and send signed transaction.
-	fn fetch_response_and_send_signed() -> Result<(), &'static str> {
-		let signer = Signer::<T, T::AuthorityId>::all_accounts();
-		if !signer.can_sign() {
-			return Err(
-				"No local accounts available. Consider adding one via `author_insertKey` RPC.",
-			)
+	/// Moves the commands scheduled in the `CommandDeadlines` of `now` that have been awaiting
+	/// a response for longer than `CommandTimeout` blocks to `TimedOut`.
+	///
+	/// The returned weight accounts for each command read, there are at most `MaxPrunePerBlock`
+	/// of them.
+	fn time_out_commands(now: BlockNumberFor<T>) -> frame_support::weights::Weight {
+		let timeout = T::CommandTimeout::get();
+		let (mut reads, mut writes) = (1u64, 0u64);
+		let deadlines = <CommandDeadlines<T>>::take(now);
+		if !deadlines.is_empty() {
+			writes += 1;
 		}
-		// Make an external HTTP request to fetch the current response.
-		// Note this call will block until response is received.
-		let response = Self::fetch_response().map_err(|_| "Failed to fetch response")?;
-
-		// Clone the response for use in the closure.
-		let response_clone = response.clone();
-
-		// Using `send_signed_transaction` associated type we create and submit a transaction
-		// representing the call, we've just created.
-		// Submit signed will return a vector of results for all accounts that were found in the
-		// local keystore with expected `KEY_TYPE`.
-		let results = signer.send_signed_transaction(|_account| {
-			// Clone the response_clone before moving it into the closure
-			let response_in_closure = response_clone.clone();
-			Call::submit_response { response: response_in_closure }
-		});
+		for command_id in deadlines {
+			// The status, and the connection and queue of the command.
+			reads += 3;
+			if <CommandStatusOf<T>>::get(command_id) != Some(CommandStatus::AwaitingResponse) {
+				continue
+			}
+			// Commands dispatched again since they were scheduled have a later entry.
+			let dispatched_at = Self::queued_command(command_id).and_then(|c| c.dispatched_at);
+			if dispatched_at.is_none_or(|at| now.saturating_sub(at) <= timeout) {
+				continue
+			}
 
-		for (acc, res) in &results {
-			match res {
-				Ok(()) => log::info!("[{:?}] Submitted response: {}", acc.id, response),
-				Err(e) => log::error!("[{:?}] Failed to submit transaction: {:?}", acc.id, e),
+			Self::set_command_status(command_id, CommandStatus::TimedOut);
+			// The status, the in-flight count and recent outcomes of the connection and the
+			// broadcast of the command if it has one.
+			reads += 4;
+			writes += 4;
+		}
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+
+	/// Prunes the commands scheduled in the `CommandPrunes` of `now` that settled more than
+	/// `CommandRetentionBlocks` blocks ago, along with their status and response.
+	///
+	/// There are at most `MaxPrunePerBlock` of them, so that the weight of the hook stays
+	/// predictable. The returned weight accounts for each command and queue read.
+	fn prune_commands(now: BlockNumberFor<T>) -> frame_support::weights::Weight {
+		let retention = T::CommandRetentionBlocks::get();
+		let (mut reads, mut writes) = (1u64, 0u64);
+		let prunes = <CommandPrunes<T>>::take(now);
+		if !prunes.is_empty() {
+			writes += 1;
+		}
+
+		let mut pruned = 0u32;
+		let mut queues = Vec::new();
+		for command_id in prunes {
+			reads += 1;
+			let Some(connection) = <CommandConnection<T>>::get(command_id) else { continue };
+			let index = match queues.iter().position(|(queued_on, _, _)| *queued_on == connection) {
+				Some(index) => index,
+				None => {
+					reads += 1;
+					let queue = <CommandQueue<T>>::get(connection);
+					queues.push((connection, queue.len(), queue));
+					queues.len() - 1
+				},
+			};
+
+			// Commands retried since they were scheduled have settled again, or not at all.
+			let queue = &mut queues[index].2;
+			let Some(position) = queue.iter().position(|command| {
+				command.id == command_id &&
+					matches!(command.settled_at, Some(at) if now.saturating_sub(at) > retention)
+			}) else {
+				continue
+			};
+			queue.remove(position);
+			Self::remove_command(command_id);
+			reads += 3;
+			writes += 11;
+			pruned += 1;
+		}
+
+		for (connection, before, queue) in queues {
+			if queue.len() == before {
+				continue
 			}
+			let depth = queue.len() as u32;
+			<CommandQueue<T>>::insert(connection, queue);
+			Self::note_queue_depth(connection, depth);
+			reads += 1;
+			writes += 2;
 		}
+		if pruned > 0 {
+			log::info!(
+				target: LOG_TARGET,
+				"commands pruned block={:?} count={} outcome=pruned",
+				now,
+				pruned
+			);
+		}
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
 
-		Ok(())
+	/// Disconnects `Active` connections whose edge server has not been seen for more than
+	/// `StaleConnectionBlocks` blocks.
+	///
+	/// Connections are checked in turn from the `StaleCheckCursor`, at most `MaxPrunePerBlock`
+	/// of them per call.
+	fn disconnect_stale_connections(now: BlockNumberFor<T>) -> frame_support::weights::Weight {
+		let threshold = T::StaleConnectionBlocks::get();
+		let max = T::MaxPrunePerBlock::get() as usize;
+		let (mut reads, mut writes) = (1u64, 1u64);
+		let mut connections = match <StaleCheckCursor<T>>::get() {
+			Some(cursor) => <Connection<T>>::iter_from(<Connection<T>>::hashed_key_for(cursor)),
+			None => <Connection<T>>::iter(),
+		};
+
+		let mut cursor = None;
+		let mut stale = Vec::new();
+		for (connection, mut info) in connections.by_ref().take(max) {
+			reads += 1;
+			cursor = Some(connection);
+			if info.status == ConnectionStatus::Active &&
+				now.saturating_sub(info.last_seen) > threshold
+			{
+				info.status = ConnectionStatus::Disconnected;
+				info.last_lifecycle_block = now;
+				stale.push((connection, info));
+			}
+		}
+		// Once the last connection is checked, the next call starts over.
+		if connections.next().is_none() {
+			cursor = None;
+		}
+		<StaleCheckCursor<T>>::set(cursor);
+
+		for (connection, info) in stale {
+			let last_seen = info.last_seen;
+			<Connection<T>>::insert(connection, info);
+			Self::note_connection_status(
+				Some(ConnectionStatus::Active),
+				Some(ConnectionStatus::Disconnected),
+			);
+			reads += 1;
+			writes += 2;
+			Self::deposit_event(Event::ConnectionStaled { connection, last_seen });
+		}
+		T::DbWeight::get().reads_writes(reads, writes)
 	}
 
-	/// A helper function to fetch the response and send a raw unsigned transaction.
-	fn fetch_response_and_send_raw_unsigned(block_number: BlockNumberFor<T>) -> Result<(), &'static str> {
-		// Make sure we don't fetch the response if unsigned transaction is going to be rejected
-		// anyway.
-		let next_unsigned_at = <NextUnsignedAt<T>>::get();
-		if next_unsigned_at > block_number {
-			return Err("Too early to send unsigned transaction")
+	/// Removes the connections scheduled in the `ConnectionExpiries` of `now` whose `expires_at`
+	/// block has been reached, returning their deposits.
+	///
+	/// There are at most `MaxPrunePerBlock` of them. Ids freed and handed out again since they
+	/// were scheduled are only removed if the new connection expired too.
+	fn expire_connections(now: BlockNumberFor<T>) -> frame_support::weights::Weight {
+		let (mut reads, mut writes) = (1u64, 0u64);
+		let expiries = <ConnectionExpiries<T>>::take(now);
+		if !expiries.is_empty() {
+			writes += 1;
 		}
 
-		// Make an external HTTP request to fetch the current response.
-		// Note this call will block until response is received.
-		let response = Self::fetch_response().map_err(|_| "Failed to fetch response")?;
-
-		// Received response is wrapped into a call to `submit_response_unsigned` public function of this
-		// pallet. This means that the transaction, when executed, will simply call that function
-		// passing `response` as an argument.
-		let call = Call::submit_response_unsigned { block_number, response };
-
-		// Now let's create a transaction out of this call and submit it to the pool.
-		// Here we showcase two ways to send an unsigned transaction / unsigned payload (raw)
-		//
-		// By default unsigned transactions are disallowed, so we need to whitelist this case
-		// by writing `UnsignedValidator`. Note that it's EXTREMELY important to carefuly
-		// implement unsigned validation logic, as any mistakes can lead to opening DoS or spam
-		// attack vectors. See validation logic docs for more details.
-		//
-		SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into())
-			.map_err(|()| "Unable to submit unsigned transaction.")?;
+		let mut weight = Weight::zero();
+		for connection in expiries {
+			reads += 1;
+			let Some(info) = <Connection<T>>::get(connection) else { continue };
+			if info.expires_at.is_none_or(|at| at > now) {
+				continue
+			}
 
-		Ok(())
+			let queued = <CommandQueue<T>>::decode_len(connection).unwrap_or(0) as u32;
+			let who = info.owner.clone();
+			Self::do_remove_connection(connection, info);
+			weight.saturating_accrue(T::WeightInfo::force_remove_connection(queued));
+			Self::deposit_event(Event::ConnectionExpired { connection, who });
+		}
+		weight.saturating_add(T::DbWeight::get().reads_writes(reads, writes))
 	}
 
-	/// A helper function to fetch the response, sign payload and send an unsigned transaction
-	fn fetch_response_and_send_unsigned_for_any_account(
-		block_number: BlockNumberFor<T>,
-	) -> Result<(), &'static str> {
-		// Make sure we don't fetch the response if unsigned transaction is going to be rejected
-		// anyway.
-		let next_unsigned_at = <NextUnsignedAt<T>>::get();
-		if next_unsigned_at > block_number {
-			return Err("Too early to send unsigned transaction")
+	/// Removes the leases scheduled in the `LeaseExpiries` of `now` that ran out by then.
+	///
+	/// There are at most `MaxPrunePerBlock` of them. Leases released since are gone, and those
+	/// taken out again since run until a later block.
+	fn expire_leases(now: BlockNumberFor<T>) -> frame_support::weights::Weight {
+		let (mut reads, mut writes) = (1u64, 0u64);
+		let expiries = <LeaseExpiries<T>>::take(now);
+		if !expiries.is_empty() {
+			writes += 1;
 		}
 
-		// Make an external HTTP request to fetch the current response.
-		// Note this call will block until response is received.
-		let response = Self::fetch_response().map_err(|_| "Failed to fetch response")?;
-
-		// -- Sign using any account
-		let (_, result) = Signer::<T, T::AuthorityId>::any_account()
-			.send_unsigned_transaction(
-				|account| {
-					let response_in_closure = response.clone();
-					ResponsePayload { response: response_in_closure, block_number, public: account.public.clone() }
-				},
-				|payload, signature| Call::submit_response_unsigned_with_signed_payload {
-					response_payload: payload,
-					signature,
-				},
-			)
-			.ok_or("No local accounts accounts available.")?;
-		result.map_err(|()| "Unable to submit transaction")?;
+		for connection in expiries {
+			reads += 1;
+			let Some((lessee, until)) = <Leases<T>>::get(connection) else { continue };
+			if until > now {
+				continue
+			}
 
-		Ok(())
+			<Leases<T>>::remove(connection);
+			writes += 1;
+			Self::deposit_event(Event::LeaseReleased { connection, lessee });
+		}
+		T::DbWeight::get().reads_writes(reads, writes)
 	}
 
-	/// A helper function to fetch the response, sign payload and send an unsigned transaction
-	fn fetch_response_and_send_unsigned_for_all_accounts(
-		block_number: BlockNumberFor<T>,
-	) -> Result<(), &'static str> {
-		// Make sure we don't fetch the response if unsigned transaction is going to be rejected
-		// anyway.
-		let next_unsigned_at = <NextUnsignedAt<T>>::get();
-		if next_unsigned_at > block_number {
-			return Err("Too early to send unsigned transaction")
+	/// Removes everything stored about `command_id` besides its queue entry.
+	fn remove_command(command_id: CommandId) {
+		let status = <CommandStatusOf<T>>::take(command_id);
+		Self::note_command_status(command_id, status, None);
+		if let Some(broadcast_id) = <CommandBroadcast<T>>::take(command_id) {
+			// A command that goes before it settles, like a cancelled one, holds up nothing.
+			if status.is_some_and(|status| !status.is_final()) {
+				Self::settle_broadcast_command(broadcast_id);
+			}
+			<Broadcasts<T>>::mutate_exists(broadcast_id, |maybe_broadcast| {
+				let Some(broadcast) = maybe_broadcast else { return };
+				broadcast.commands.retain(|id| *id != command_id);
+				if broadcast.commands.is_empty() {
+					*maybe_broadcast = None;
+				}
+			});
+		}
+		if let Some(connection) = <CommandConnection<T>>::take(command_id) {
+			<ConnectionCommands<T>>::remove(connection, command_id);
+			if let Some(client_ref) = <CommandClientRef<T>>::take(command_id) {
+				<CommandByClientRef<T>>::remove(connection, client_ref);
+			}
 		}
+		// Bodies kept off chain may be shared by several commands, they stay.
+		<Responses<T>>::remove(command_id);
+		<ResponseHashes<T>>::remove(command_id);
+		<CommandErrors<T>>::remove(command_id);
+		<CommandSchemas<T>>::remove(command_id);
+	}
 
-		// Make an external HTTP request to fetch the current response.
-		// Note this call will block until response is received.
-		let response = Self::fetch_response().map_err(|_| "Failed to fetch response")?;
-
-		// -- Sign using all accounts
-		let transaction_results = Signer::<T, T::AuthorityId>::all_accounts()
-			.send_unsigned_transaction(
-				|account| {
-					let response_in_closure = response.clone();
-					ResponsePayload { response: response_in_closure, block_number, public: account.public.clone() }
-				},
-				|payload, signature| Call::submit_response_unsigned_with_signed_payload {
-					response_payload: payload,
-					signature,
-				},
-			);
-		for (_account_id, result) in transaction_results.into_iter() {
-			if result.is_err() {
-				return Err("Unable to submit transaction")
+	/// Returns the response body with the given `hash` from offchain-indexed storage.
+	///
+	/// This is only available where the offchain database is, so callers of the runtime API
+	/// need to register an `OffchainDbExt`.
+	pub fn response_body(hash: H256) -> Option<Vec<u8>> {
+		sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &Self::response_body_key(hash))
+	}
+
+	/// Counts what the running totals keep track of from scratch, going over every connection
+	/// and command.
+	#[cfg(any(feature = "try-runtime", test))]
+	pub(crate) fn count_stats() -> EdgeConnectStats {
+		let mut stats = EdgeConnectStats::default();
+		for info in <Connection<T>>::iter_values() {
+			stats.total_connections.saturating_inc();
+			if info.status == ConnectionStatus::Active {
+				stats.active_connections.saturating_inc();
 			}
 		}
+		stats.total_queued_commands = <CommandStatusOf<T>>::iter_values()
+			.filter(|status| *status == CommandStatus::Queued)
+			.count() as u32;
+		stats
+	}
 
-		Ok(())
+	/// Counts the commands `AwaitingResponse` on every connection from scratch.
+	#[cfg(any(feature = "try-runtime", test))]
+	pub(crate) fn count_in_flight() -> BTreeMap<ConnectionId, u32> {
+		let mut in_flight = BTreeMap::new();
+		for (command_id, status) in <CommandStatusOf<T>>::iter() {
+			if status != CommandStatus::AwaitingResponse {
+				continue
+			}
+			if let Some(connection) = <CommandConnection<T>>::get(command_id) {
+				in_flight.entry(connection).or_insert(0u32).saturating_inc();
+			}
+		}
+		in_flight
 	}
 
-	/// Fetches the current response from remote URL and returns it as a string.
-	// TODO: change http to websocket
-	fn fetch_response() -> Result<String, http::Error> {
-		// We want to keep the offchain worker execution time reasonable, so we set a hard-coded
-		// deadline to 3s to complete the external call.
-		// You can also wait idefinitely for the response, however you may still get a timeout
-		// coming from the host machine.
-		let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(3_000));
-		// Initiate an external HTTP GET request.
-		// This is using high-level wrappers from `sp_runtime`, for the low-level calls that
-		// you can find in `sp_io`. The API is trying to be similar to `reqwest`, but
-		// since we are running in a custom WASM execution environment we can't simply
-		// import the library here.
-		let request = http::Request::get("http://127.0.0.1:8080/block");
-		// We set the deadline for sending of the request, note that awaiting response can
-		// have a separate deadline. Next we send the request, before that it's also possible
-		// to alter request headers or stream body content in case of non-GET requests.
-		let pending = request.deadline(deadline).send().map_err(|_| http::Error::IoError)?;
-
-		// The request is already being processed by the host, we are free to do anything
-		// else in the worker (we can send multiple concurrent requests too).
-		// At some point however we probably want to check the response though,
-		// so we can block current thread and wait for it to finish.
-		// Note that since the request is being driven by the host, we don't have to wait
-		// for the request to have it complete, we will just not read the response.
-		let response = pending.try_wait(deadline).map_err(|_| http::Error::DeadlineReached)??;
-		// Let's check the status code before we proceed to reading the response.
-		if response.code != 200 {
-			log::warn!("Unexpected status code: {}", response.code);
-			return Err(http::Error::Unknown)
+	/// Returns the dispatch log entries of the blocks from `from` up to and including `to`, in
+	/// the order the dispatches were recorded.
+	///
+	/// The log is written to offchain-indexed storage as dispatches land on chain. It is not
+	/// part of consensus and only exists on nodes that imported those blocks with
+	/// `--enable-offchain-indexing true`. Callers of the runtime API need to register an
+	/// `OffchainDbExt`. At most `MAX_DISPATCH_LOG_BLOCKS` blocks are read, starting at `from`.
+	pub fn dispatch_log(
+		from: BlockNumberFor<T>,
+		to: BlockNumberFor<T>,
+	) -> Vec<DispatchLogEntryOf<T>> {
+		let mut entries = Vec::new();
+		for offset in 0..MAX_DISPATCH_LOG_BLOCKS {
+			let Some(block) = from.checked_add(&offset.into()) else { break };
+			if block > to {
+				break
+			}
+			for index in 0.. {
+				let key = Self::dispatch_log_key(block, index);
+				let Some(entry) = sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &key)
+					.and_then(|raw| DispatchLogEntry::decode(&mut &raw[..]).ok())
+				else {
+					break
+				};
+				entries.push(entry);
+			}
 		}
+		entries
+	}
 
-		// Next we want to fully read the response body and collect it to a vector of bytes.
-		// Note that the return object allows you to read the body in chunks as well
-		// with a way to control the deadline.
-		let body = response.body().collect::<Vec<u8>>();
-
-		// Create a str slice from the body.
-		let body_str = sp_std::str::from_utf8(&body).map_err(|_| {
-			log::warn!("No UTF8 body");
-			http::Error::Unknown
-		})?;
+	/// Checks that connections, their owners' indexes and their commands agree.
+	#[cfg(any(feature = "try-runtime", test))]
+	pub(crate) fn do_try_state() -> Result<(), sp_runtime::TryRuntimeError> {
+		let next = <NextConnectionId<T>>::get();
+		for (connection, info) in <Connection<T>>::iter() {
+			ensure!(connection.0 < next.0, "Connection id is not below NextConnectionId");
+			ensure!(
+				<ConnectionsOf<T>>::get(&info.owner).contains(&connection),
+				"Connection is missing from its owner's index"
+			);
+		}
 
-		log::info!("fetch_response: {}", body_str);
+		let free = <FreeConnectionIds<T>>::get();
+		ensure!(free.windows(2).all(|ids| ids[0] < ids[1]), "Free connection ids are not sorted");
+		for connection in free {
+			ensure!(connection.0 < next.0, "Free connection id is not below NextConnectionId");
+			ensure!(!<Connection<T>>::contains_key(connection), "Free connection id is in use");
+		}
 
-		let response = body_str.to_string();
+		for (owner, connections) in <ConnectionsOf<T>>::iter() {
+			for connection in connections {
+				let info =
+					<Connection<T>>::get(connection).ok_or("Indexed connection does not exist")?;
+				ensure!(info.owner == owner, "Indexed connection belongs to another account");
+			}
+		}
 
-		match response.len() {
-			0 => Err(http::Error::Unknown),
-			_ => Ok(response),
+		for (connection, queue) in <CommandQueue<T>>::iter() {
+			for command in queue {
+				ensure!(
+					<CommandConnection<T>>::get(command.id) == Some(connection),
+					"Queued command is not mapped to its connection"
+				);
+			}
+		}
+		for (command_id, connection) in <CommandConnection<T>>::iter() {
+			ensure!(
+				<Connection<T>>::contains_key(connection),
+				"Command references a connection that does not exist"
+			);
+			ensure!(
+				<ConnectionCommands<T>>::contains_key(connection, command_id),
+				"Command is missing from its connection's history"
+			);
+		}
+		for (connection, command_id, ()) in <ConnectionCommands<T>>::iter() {
+			ensure!(
+				<CommandConnection<T>>::get(command_id) == Some(connection),
+				"Command history lists a command of another connection"
+			);
 		}
+
+		let counted = Self::count_stats();
+		ensure!(
+			<TotalConnectionCount<T>>::get() == counted.total_connections,
+			"TotalConnectionCount does not match the connections"
+		);
+		ensure!(
+			<ActiveConnectionCount<T>>::get() == counted.active_connections,
+			"ActiveConnectionCount does not match the active connections"
+		);
+		ensure!(
+			<TotalQueuedCommands<T>>::get() == counted.total_queued_commands,
+			"TotalQueuedCommands does not match the queued commands"
+		);
+		let in_flight: BTreeMap<_, _> =
+			<InFlightCommands<T>>::iter().filter(|(_, count)| *count > 0).collect();
+		ensure!(
+			in_flight == Self::count_in_flight(),
+			"InFlightCommands does not match the commands awaiting a response"
+		);
+
+		Ok(())
+	}
+
+	/// Returns the record of `connection`, if it exists.
+	pub fn connection_info(connection: ConnectionId) -> Option<ConnectionInfo<T>> {
+		<Connection<T>>::get(connection)
+	}
+
+	/// Returns whether `connection` exists and is owned by `who`.
+	pub fn owns(who: &T::AccountId, connection: ConnectionId) -> bool {
+		<Connection<T>>::get(connection).is_some_and(|info| info.owner == *who)
 	}
 
+	/// Returns up to `limit` of the connections owned by `who` with an id above `start_after`,
+	/// as served by the runtime API.
 	///
+	/// Pages hold at least one and at most `MAX_CONNECTIONS_PAGE` connections. Connections are
+	/// listed by id, so that removing one between two calls does not shift the following pages.
+	pub fn connection_details_of(
+		who: T::AccountId,
+		start_after: Option<ConnectionId>,
+		limit: u32,
+	) -> ConnectionsPageOf<T> {
+		let limit = limit.clamp(1, MAX_CONNECTIONS_PAGE) as usize;
+		let mut ids = <ConnectionsOf<T>>::get(who).into_inner();
+		ids.sort();
+		// `None` sorts before every id, so the first page starts at the lowest one.
+		ids.retain(|id| Some(*id) > start_after);
 
-	/// Add new response to the list.
-	fn add_response(maybe_who: Option<T::AccountId>, response: String) {
-		log::info!("Adding response to the list: {}", response);
+		let page = &ids[..ids.len().min(limit)];
+		let connections = page
+			.iter()
+			.filter_map(|&connection| {
+				<Connection<T>>::get(connection).map(|info| {
+					let in_flight = <InFlightCommands<T>>::get(connection);
+					let health = Self::connection_health(connection);
+					(connection, ConnectionDetails { in_flight, health, ..info.into() })
+				})
+			})
+			.collect();
+		let next = if ids.len() > page.len() { page.last().copied() } else { None };
+		ConnectionsPage { connections, next }
+	}
 
-		// Convert the string to a byte vector.
-		let response_bytes = response.into_bytes();
+	/// Returns up to `limit` of the commands stored for `connection` with an id above
+	/// `start_after`, along with their status, by ascending id.
+	///
+	/// Pages hold at least one and at most `MAX_COMMANDS_PAGE` commands. Commands are listed
+	/// until they are pruned, so for `CommandRetentionBlocks` after they settled.
+	pub fn commands_of(
+		connection: ConnectionId,
+		start_after: Option<CommandId>,
+		limit: u32,
+	) -> Vec<(CommandId, CommandStatus)> {
+		let limit = limit.clamp(1, MAX_COMMANDS_PAGE) as usize;
+		let mut ids: Vec<_> = <ConnectionCommands<T>>::iter_key_prefix(connection)
+			.filter(|id| Some(*id) > start_after)
+			.collect();
+		ids.sort();
+		ids.into_iter()
+			.filter_map(|command_id| Some((command_id, <CommandStatusOf<T>>::get(command_id)?)))
+			.take(limit)
+			.collect()
+	}
 
-		// Ensure the length doesn't exceed the maximum length.
-		let bounded_response = match BoundedVec::try_from(response_bytes) {
-			Ok(bounded) => bounded,
-			Err(_) => {
-				log::warn!("Response is too long. It has been ignored.");
-				return;
-			},
+	/// Judges the health of `connection` on the share of its `RecentOutcomes` that completed.
+	pub fn connection_health(connection: ConnectionId) -> ConnectionHealth {
+		let outcomes = <RecentOutcomes<T>>::get(connection);
+		let completed = outcomes.iter().filter(|completed| **completed).count() as u32;
+		let settled = outcomes.len() as u32;
+		let below = |threshold: Percent| {
+			completed.saturating_mul(100) < settled.saturating_mul(threshold.deconstruct().into())
 		};
+		if below(T::UnhealthyBelow::get()) {
+			ConnectionHealth::Unhealthy
+		} else if below(T::DegradedBelow::get()) {
+			ConnectionHealth::Degraded
+		} else {
+			ConnectionHealth::Healthy
+		}
+	}
+
+	/// Checks that `who` may send a command over `connection` and that it has room for one in
+	/// this block.
+	///
+	/// These are the checks `send_command` makes before charging the command fee, the runtime
+	/// API uses this to tell clients whether a command would be accepted.
+	pub fn can_send_command(
+		who: &T::AccountId,
+		connection: ConnectionId,
+	) -> sp_runtime::DispatchResult {
+		let info = <Connection<T>>::get(connection).ok_or(Error::<T>::ConnectionDoesNotExist)?;
+		ensure!(
+			&info.owner == who || info.authorized_senders.contains(who),
+			Error::<T>::NotAuthorizedToSend
+		);
+		ensure!(
+			&info.owner == who ||
+				Self::active_lease(connection).is_none_or(|(lessee, _)| &lessee == who),
+			Error::<T>::ConnectionLeased
+		);
+		ensure!(info.status == ConnectionStatus::Active, Error::<T>::ConnectionNotActive);
+		let queued = <CommandQueue<T>>::decode_len(connection).unwrap_or_default();
+		ensure!(queued < T::MaxQueuedCommands::get() as usize, Error::<T>::CommandQueueFull);
+		ensure!(
+			<CommandsInBlock<T>>::get(connection) < T::CommandsPerConnectionPerBlock::get(),
+			Error::<T>::RateLimited
+		);
+		Ok(())
+	}
 
-		// Get the current list of responses.
-		let mut responses = Responses::<T>::get();
+	/// Returns the lessee of `connection` and the block its lease runs until, if the lease has
+	/// not run out yet.
+	pub fn active_lease(connection: ConnectionId) -> Option<(T::AccountId, BlockNumberFor<T>)> {
+		let now = <system::Pallet<T>>::block_number();
+		<Leases<T>>::get(connection).filter(|(_, until)| now < *until)
+	}
 
-		// Attempt to append the new response to the list.
-		match responses.try_push((maybe_who.clone(), bounded_response.clone())) {
-			Ok(_) => {
-				// Update the storage.
-				Responses::<T>::put(responses);
+	/// Returns the status and response of every command of `broadcast_id` that is still
+	/// stored, in the order they were queued.
+	pub fn broadcast_results(broadcast_id: BroadcastId) -> Vec<BroadcastResult> {
+		let Some(broadcast) = <Broadcasts<T>>::get(broadcast_id) else { return Vec::new() };
+		broadcast
+			.commands
+			.into_iter()
+			.filter_map(|command_id| {
+				Some(BroadcastResult {
+					command_id,
+					connection: <CommandConnection<T>>::get(command_id)?,
+					status: <CommandStatusOf<T>>::get(command_id)?,
+					response: <Responses<T>>::get(command_id).map(BoundedVec::into_inner),
+					response_hash: <ResponseHashes<T>>::get(command_id),
+				})
+			})
+			.collect()
+	}
 
-				// Emit an event that new response has been received.
-				Self::deposit_event(Event::NewResponse { maybe_who, response: String::from_utf8(bounded_response.into()).unwrap() });
-			},
-			Err(_) => {
-				log::warn!("Unable to add response. Maximum number of responses reached.");
-			},
+	/// Returns the running totals over the connections and commands, without scanning them.
+	pub fn stats() -> EdgeConnectStats {
+		EdgeConnectStats {
+			total_connections: <TotalConnectionCount<T>>::get(),
+			active_connections: <ActiveConnectionCount<T>>::get(),
+			total_queued_commands: <TotalQueuedCommands<T>>::get(),
 		}
+	}
 
+	/// Returns the ids of the connections tagged with `tag`.
+	///
+	/// This scans every connection, so it is meant for the runtime API rather than for
+	/// dispatchables.
+	pub fn connections_by_tag(tag: Vec<u8>) -> Vec<ConnectionId> {
+		<Connection<T>>::iter()
+			.filter(|(_, info)| info.tags.iter().any(|t| t[..] == tag[..]))
+			.map(|(connection, _)| connection)
+			.collect()
 	}
 }
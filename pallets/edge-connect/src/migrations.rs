@@ -0,0 +1,418 @@
+//! Storage migrations for pallet-edge-connect.
+
+use super::*;
+use parity_scale_codec::DecodeAll;
+use frame_support::{
+	pallet_prelude::*,
+	storage::unhashed,
+	storage_alias,
+	traits::{OnRuntimeUpgrade, StorageVersion},
+};
+use sp_std::marker::PhantomData;
+
+#[cfg(feature = "try-runtime")]
+use sp_runtime::TryRuntimeError;
+
+/// Migration to storage version 1, where connections are keyed by id.
+pub mod v1 {
+	use super::*;
+
+	/// The single connection stored before version 1, as set by the old `create_connection`.
+	#[storage_alias]
+	pub(crate) type Connection<T: Config> = StorageValue<Pallet<T>, u32, OptionQuery>;
+
+	/// Rewrites the legacy `u32` connection as a `ConnectionInfo` owned by `Owner`.
+	///
+	/// The old value carries no endpoint, so the connection is created `Disconnected` without
+	/// endpoints and with no deposit. The owner can remove it, or point a new connection at the
+	/// edge server.
+	///
+	/// A legacy value that does not decode is logged and dropped instead of migrated, so that
+	/// dirty state cannot fail the upgrade.
+	pub struct MigrateToV1<T, Owner>(PhantomData<(T, Owner)>);
+
+	impl<T: Config, Owner: Get<T::AccountId>> OnRuntimeUpgrade for MigrateToV1<T, Owner> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 0 {
+				log::info!("Skipping v1 migration, storage is already at {:?}", on_chain);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let (mut reads, mut writes) = (2u64, 1u64);
+			if let Some(legacy) = take_legacy::<T>(&mut writes) {
+				let owner = Owner::get();
+				let connection = NextConnectionId::<T>::get();
+				let info = ConnectionInfo::<T> {
+					endpoints: Default::default(),
+					label: None,
+					owner: owner.clone(),
+					created_at: Zero::zero(),
+					last_seen: Zero::zero(),
+					status: ConnectionStatus::Disconnected,
+					authorized_senders: Default::default(),
+					deposit: Zero::zero(),
+					tags: Default::default(),
+					server_pubkey: None,
+					active_endpoint: 0,
+					last_lifecycle_block: Zero::zero(),
+					expires_at: None,
+					generation: 0,
+					poll_mode: PollMode::Push,
+					version: 0,
+				};
+
+				crate::Connection::<T>::insert(connection, info);
+				ConnectionsOf::<T>::mutate(&owner, |connections| {
+					if connections.try_push(connection).is_err() {
+						log::warn!("Connection {:?} is missing from its owner's index", connection);
+					}
+				});
+				NextConnectionId::<T>::put(ConnectionId(connection.0.wrapping_add(1)));
+				log::info!("Migrated legacy connection {} to {:?}", legacy, connection);
+				reads += 2;
+				writes += 3;
+			}
+
+			StorageVersion::new(1).put::<Pallet<T>>();
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			let raw = unhashed::get_raw(&Connection::<T>::hashed_key());
+			let skipped = raw.as_ref().is_some_and(|raw| decode_legacy(raw).is_none()) as u32;
+			let had_legacy = raw.is_some() && skipped == 0;
+			Ok((had_legacy, skipped, NextConnectionId::<T>::get()).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			let (had_legacy, skipped, connection): (bool, u32, ConnectionId) =
+				Decode::decode(&mut &state[..]).map_err(|_| "Invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 1, "Storage version not bumped");
+			ensure!(!Connection::<T>::exists(), "Legacy connection still stored");
+			if skipped > 0 {
+				log::warn!("Skipped {} undecodable legacy connection entries", skipped);
+				ensure!(
+					!crate::Connection::<T>::contains_key(connection),
+					"Undecodable legacy connection was migrated"
+				);
+			}
+			if had_legacy {
+				let info = crate::Connection::<T>::get(connection)
+					.ok_or("Legacy connection not migrated")?;
+				ensure!(info.owner == Owner::get(), "Migrated connection has the wrong owner");
+				ensure!(
+					info.status == ConnectionStatus::Disconnected,
+					"Migrated connection is not disconnected"
+				);
+				ensure!(
+					ConnectionsOf::<T>::get(&info.owner).contains(&connection),
+					"Migrated connection is not indexed"
+				);
+			}
+			Ok(())
+		}
+	}
+
+	/// Removes the legacy connection value and returns it, or `None` if there is none or it does
+	/// not decode. `writes` is bumped if there was a value to remove.
+	fn take_legacy<T: Config>(writes: &mut u64) -> Option<u32> {
+		let key = Connection::<T>::hashed_key();
+		let raw = unhashed::get_raw(&key)?;
+		unhashed::kill(&key);
+		*writes += 1;
+		let legacy = decode_legacy(&raw);
+		if legacy.is_none() {
+			log::warn!("Dropping undecodable legacy connection {:?}", raw);
+		}
+		legacy
+	}
+
+	/// Decodes a legacy connection value, which must be a `u32` and nothing more.
+	fn decode_legacy(raw: &[u8]) -> Option<u32> {
+		u32::decode_all(&mut &raw[..]).ok()
+	}
+}
+
+/// Migration to storage version 2, which keeps running totals of connections and commands.
+pub mod v2 {
+	use super::*;
+
+	/// Counts the existing connections and queued commands into their running totals.
+	///
+	/// This goes over every connection and command status once, from then on the totals are
+	/// kept up to date as connections and commands change.
+	pub struct MigrateToV2<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 1 {
+				log::info!("Skipping v2 migration, storage is at {:?}", on_chain);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let (mut reads, mut stats) = (1u64, EdgeConnectStats::default());
+			for info in Connection::<T>::iter_values() {
+				stats.total_connections.saturating_inc();
+				if info.status == ConnectionStatus::Active {
+					stats.active_connections.saturating_inc();
+				}
+				reads += 1;
+			}
+			for status in CommandStatusOf::<T>::iter_values() {
+				if status == CommandStatus::Queued {
+					stats.total_queued_commands.saturating_inc();
+				}
+				reads += 1;
+			}
+			TotalConnectionCount::<T>::put(stats.total_connections);
+			ActiveConnectionCount::<T>::put(stats.active_connections);
+			TotalQueuedCommands::<T>::put(stats.total_queued_commands);
+			log::info!("Counted {:?}", stats);
+
+			StorageVersion::new(2).put::<Pallet<T>>();
+			T::DbWeight::get().reads_writes(reads, 4)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			ensure!(Pallet::<T>::on_chain_storage_version() == 2, "Storage version not bumped");
+			ensure!(Pallet::<T>::stats() == Pallet::<T>::count_stats(), "Totals were not counted");
+			Ok(())
+		}
+	}
+}
+
+/// Migration to storage version 3, which counts the commands in flight on each connection.
+pub mod v3 {
+	use super::*;
+
+	/// Counts the commands `AwaitingResponse` into the `InFlightCommands` of their connection.
+	///
+	/// Commands that were dispatched before the upgrade would otherwise not hold back the
+	/// offchain worker, nor be taken off the count once answered.
+	pub struct MigrateToV3<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV3<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 2 {
+				log::info!("Skipping v3 migration, storage is at {:?}", on_chain);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let (mut reads, mut writes) = (1u64, 1u64);
+			for (command_id, status) in CommandStatusOf::<T>::iter() {
+				reads += 1;
+				if status != CommandStatus::AwaitingResponse {
+					continue
+				}
+				if let Some(connection) = CommandConnection::<T>::get(command_id) {
+					InFlightCommands::<T>::mutate(connection, |count| count.saturating_inc());
+					reads += 2;
+					writes += 1;
+				}
+			}
+
+			StorageVersion::new(3).put::<Pallet<T>>();
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			ensure!(Pallet::<T>::on_chain_storage_version() == 3, "Storage version not bumped");
+			let in_flight: BTreeMap<_, _> = InFlightCommands::<T>::iter().collect();
+			ensure!(in_flight == Pallet::<T>::count_in_flight(), "Commands in flight not counted");
+			Ok(())
+		}
+	}
+}
+
+/// Migration to storage version 4, which indexes the commands of each connection.
+pub mod v4 {
+	use super::*;
+
+	/// Lists every stored command in the `ConnectionCommands` of its connection.
+	///
+	/// Commands queued before the upgrade would otherwise be missing from the history of their
+	/// connection until they are pruned.
+	pub struct MigrateToV4<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV4<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 3 {
+				log::info!("Skipping v4 migration, storage is at {:?}", on_chain);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let (mut reads, mut writes) = (1u64, 1u64);
+			for (command_id, connection) in CommandConnection::<T>::iter() {
+				ConnectionCommands::<T>::insert(connection, command_id, ());
+				reads += 1;
+				writes += 1;
+			}
+
+			StorageVersion::new(4).put::<Pallet<T>>();
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			ensure!(Pallet::<T>::on_chain_storage_version() == 4, "Storage version not bumped");
+			ensure!(
+				CommandConnection::<T>::iter().all(|(command_id, connection)| {
+					ConnectionCommands::<T>::contains_key(connection, command_id)
+				}),
+				"Commands not indexed"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Migration to storage version 5, which stores the algorithm of every response hash.
+pub mod v5 {
+	use super::*;
+
+	/// Tags the existing `ResponseHashes` as `Blake2_256`, the only algorithm they were computed
+	/// with before `Config::ResponseHasher`.
+	pub struct MigrateToV5<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV5<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 4 {
+				log::info!("Skipping v5 migration, storage is at {:?}", on_chain);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0u64;
+			ResponseHashes::<T>::translate_values::<H256, _>(|hash| {
+				translated += 1;
+				Some(ResponseHash { hash, algorithm: HashAlgorithm::Blake2_256 })
+			});
+
+			StorageVersion::new(5).put::<Pallet<T>>();
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			Ok((ResponseHashes::<T>::iter_keys().count() as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			let hashes: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "Invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 5, "Storage version not bumped");
+			ensure!(
+				ResponseHashes::<T>::iter_values().count() as u32 == hashes,
+				"Response hashes not translated"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Migration to storage version 6, which schedules the timeouts and pruning of commands, and
+/// the expiry of connections and leases, by block.
+pub mod v6 {
+	use super::*;
+
+	/// Lists every command awaiting a response in `CommandDeadlines`, every settled command in
+	/// `CommandPrunes`, every connection with an `expires_at` block in `ConnectionExpiries` and
+	/// every lease in `LeaseExpiries`.
+	///
+	/// These used to be found by scanning every queue, connection and lease, those from before
+	/// the upgrade would otherwise never time out, be pruned or expire.
+	pub struct MigrateToV6<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV6<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 5 {
+				log::info!("Skipping v6 migration, storage is at {:?}", on_chain);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let (mut reads, mut writes) = (1u64, 1u64);
+			for (_, queue) in CommandQueue::<T>::iter() {
+				reads += 1;
+				for command in queue {
+					reads += 1;
+					if let Some(settled_at) = command.settled_at {
+						Pallet::<T>::schedule_prune(command.id, settled_at);
+						reads += 1;
+						writes += 1;
+					} else if CommandStatusOf::<T>::get(command.id) ==
+						Some(CommandStatus::AwaitingResponse)
+					{
+						// The command is read again, along with its connection.
+						Pallet::<T>::schedule_timeout(command.id);
+						reads += 3;
+						writes += 1;
+					}
+				}
+			}
+
+			for (connection, info) in Connection::<T>::iter() {
+				reads += 1;
+				if let Some(at) = info.expires_at {
+					Pallet::<T>::schedule::<ConnectionExpiries<T>, _>(at, connection);
+					reads += 1;
+					writes += 1;
+				}
+			}
+
+			for (connection, (_, until)) in Leases::<T>::iter() {
+				Pallet::<T>::schedule::<LeaseExpiries<T>, _>(until, connection);
+				reads += 2;
+				writes += 1;
+			}
+
+			StorageVersion::new(6).put::<Pallet<T>>();
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			ensure!(Pallet::<T>::on_chain_storage_version() == 6, "Storage version not bumped");
+			let scheduled: Vec<CommandId> =
+				CommandDeadlines::<T>::iter_values().flatten().collect();
+			ensure!(
+				CommandStatusOf::<T>::iter().all(|(command_id, status)| {
+					status != CommandStatus::AwaitingResponse || scheduled.contains(&command_id)
+				}),
+				"Timeouts not scheduled"
+			);
+			let prunes: Vec<CommandId> = CommandPrunes::<T>::iter_values().flatten().collect();
+			ensure!(
+				CommandQueue::<T>::iter_values().flatten().all(|command| {
+					command.settled_at.is_none() || prunes.contains(&command.id)
+				}),
+				"Prunes not scheduled"
+			);
+			let expiries: Vec<ConnectionId> =
+				ConnectionExpiries::<T>::iter_values().flatten().collect();
+			ensure!(
+				Connection::<T>::iter().all(|(connection, info)| {
+					info.expires_at.is_none() || expiries.contains(&connection)
+				}),
+				"Expiries not scheduled"
+			);
+			let leases: Vec<ConnectionId> = LeaseExpiries::<T>::iter_values().flatten().collect();
+			ensure!(
+				Leases::<T>::iter_keys().all(|connection| leases.contains(&connection)),
+				"Lease expiries not scheduled"
+			);
+			Ok(())
+		}
+	}
+}
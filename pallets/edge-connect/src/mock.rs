@@ -0,0 +1,487 @@
+use crate as pallet_edge_connect;
+use crate::{HashAlgorithm, ResponseHashAlgorithm, Sha256};
+use frame_support::{
+	parameter_types,
+	traits::{ConstU128, ConstU16, ConstU32, ConstU64},
+};
+use frame_system::EnsureRoot;
+use parity_scale_codec::Decode;
+use sp_core::{
+	hexdisplay::HexDisplay,
+	offchain::{
+		self, testing, HttpError, HttpRequestId, HttpRequestStatus, OffchainDbExt,
+		OffchainWorkerExt, OpaqueNetworkState, Timestamp, TransactionPoolExt,
+	},
+	sr25519::{self, Signature},
+	Hasher, H256,
+};
+use sp_keystore::{testing::MemoryKeystore, Keystore, KeystoreExt};
+use sp_runtime::{
+	testing::TestXt,
+	traits::{BlakeTwo256, Extrinsic as ExtrinsicT, Hash, IdentifyAccount, IdentityLookup, Verify},
+	BuildStorage, Percent, StateVersion,
+};
+use std::{
+	collections::{BTreeMap, VecDeque},
+	sync::{Arc, Mutex},
+};
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		Balances: pallet_balances,
+		EdgeConnect: pallet_edge_connect,
+	}
+);
+
+pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
+pub type Extrinsic = TestXt<RuntimeCall, ()>;
+
+pub const ALICE: AccountId = sr25519::Public([1u8; 32]);
+pub const BOB: AccountId = sr25519::Public([2u8; 32]);
+/// Offchain worker authority set at genesis.
+pub const OCW: AccountId = sr25519::Public([5u8; 32]);
+
+/// Balance every test account is endowed with at genesis.
+pub const ENDOWMENT: u128 = 1_000;
+/// Deposit reserved for each connection.
+pub const DEPOSIT: u128 = 10;
+
+/// Domains authorized at genesis.
+pub const DOMAINS: &[&[u8]] = &[
+	b"edge.example",
+	b"edge-a.example",
+	b"edge-b.example",
+	b"edge-c.example",
+	b"one-too-many.example",
+];
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = frame_support::weights::constants::RocksDbWeight;
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Nonce = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Block = Block;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u128>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u128;
+	type RuntimeEvent = RuntimeEvent;
+	type DustRemoval = ();
+	type ExistentialDeposit = ConstU128<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type FreezeIdentifier = ();
+	type MaxFreezes = ();
+	type RuntimeHoldReason = ();
+	type MaxHolds = ();
+}
+
+impl frame_system::offchain::SigningTypes for Test {
+	type Public = <Signature as Verify>::Signer;
+	type Signature = Signature;
+}
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Test
+where
+	RuntimeCall: From<LocalCall>,
+{
+	type OverarchingCall = RuntimeCall;
+	type Extrinsic = Extrinsic;
+}
+
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Test
+where
+	RuntimeCall: From<LocalCall>,
+{
+	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+		call: RuntimeCall,
+		_public: <Signature as Verify>::Signer,
+		_account: AccountId,
+		nonce: u64,
+	) -> Option<(RuntimeCall, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
+		Some((call, (nonce, ())))
+	}
+}
+
+parameter_types! {
+	pub const UnsignedPriority: u64 = 1 << 20;
+	pub static ConnectionCreationCooldown: u64 = 0;
+	pub static ConnectionDeposit: u128 = DEPOSIT;
+	pub static CommandFee: u128 = 0;
+	pub static FeeDestination: Option<AccountId> = None;
+	pub static HashResponses: bool = false;
+	pub static MaxCommandsPerBatch: u32 = 1;
+	pub static HttpRequestTimeout: u64 = 3_000;
+	pub static OcwSubmitStrategy: pallet_edge_connect::SubmitStrategy =
+		pallet_edge_connect::SubmitStrategy::Signed;
+	pub static MaxInFlightPerConnection: u32 = 2;
+	pub const DegradedBelow: Percent = Percent::from_percent(75);
+	pub const UnhealthyBelow: Percent = Percent::from_percent(50);
+	pub static ReceivedResponses: Vec<(pallet_edge_connect::CommandId, Vec<u8>)> = vec![];
+	pub static SealPayloads: bool = false;
+	pub static Sha256Responses: bool = false;
+	pub static OcwRunOnNonAuthorities: bool = false;
+	pub static QueueDepthAlertThreshold: u32 = 3;
+}
+
+/// Keeps the responses it is notified of in `ReceivedResponses`.
+pub struct RecordResponses;
+
+impl pallet_edge_connect::OnCommandResponse<Test> for RecordResponses {
+	fn on_command_response(command_id: pallet_edge_connect::CommandId, response: &[u8]) {
+		ReceivedResponses::mutate(|responses| responses.push((command_id, response.to_vec())));
+	}
+}
+
+/// Stands in for an encrypting codec while `SealPayloads` is set: payloads are reversed and
+/// prefixed with the hex encoded key they are sealed to.
+pub struct SealingCodec;
+
+impl pallet_edge_connect::PayloadCodec for SealingCodec {
+	fn encode_for_transport(payload: &[u8], server_pubkey: Option<&sr25519::Public>) -> Vec<u8> {
+		if !SealPayloads::get() {
+			return payload.to_vec();
+		}
+		let key = server_pubkey.map_or("none".into(), |key| HexDisplay::from(&key.0).to_string());
+		let mut sealed = format!("{}:", key).into_bytes();
+		sealed.extend(payload.iter().rev());
+		sealed
+	}
+}
+
+/// Hashes responses with `Sha256` while `Sha256Responses` is set, with `BlakeTwo256` otherwise.
+#[derive(PartialEq, Eq, Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ResponseHasher;
+
+impl Hasher for ResponseHasher {
+	type Out = H256;
+	type StdHasher = <BlakeTwo256 as Hasher>::StdHasher;
+	const LENGTH: usize = 32;
+
+	fn hash(s: &[u8]) -> Self::Out {
+		if Sha256Responses::get() {
+			<Sha256 as Hasher>::hash(s)
+		} else {
+			<BlakeTwo256 as Hasher>::hash(s)
+		}
+	}
+}
+
+impl Hash for ResponseHasher {
+	type Output = H256;
+
+	fn ordered_trie_root(input: Vec<Vec<u8>>, version: StateVersion) -> Self::Output {
+		if Sha256Responses::get() {
+			Sha256::ordered_trie_root(input, version)
+		} else {
+			BlakeTwo256::ordered_trie_root(input, version)
+		}
+	}
+
+	fn trie_root(input: Vec<(Vec<u8>, Vec<u8>)>, version: StateVersion) -> Self::Output {
+		if Sha256Responses::get() {
+			Sha256::trie_root(input, version)
+		} else {
+			BlakeTwo256::trie_root(input, version)
+		}
+	}
+}
+
+impl ResponseHashAlgorithm for ResponseHasher {
+	fn algorithm() -> HashAlgorithm {
+		if Sha256Responses::get() {
+			Sha256::algorithm()
+		} else {
+			BlakeTwo256::algorithm()
+		}
+	}
+}
+
+impl pallet_edge_connect::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeCall = RuntimeCall;
+	type AuthorityId = pallet_edge_connect::crypto::TestAuthId;
+	type GracePeriod = ConstU64<5>;
+	type UnsignedInterval = ConstU64<128>;
+	type UnsignedPriority = UnsignedPriority;
+	type MaxQueuedCommands = ConstU32<2>;
+	type MaxCommandLength = ConstU32<64>;
+	type MaxTemplateParams = ConstU32<2>;
+	type MaxResponseLength = ConstU32<64>;
+	type HashResponses = HashResponses;
+	type ResponseHasher = ResponseHasher;
+	type MaxUrlLength = ConstU32<64>;
+	type MaxEndpoints = ConstU32<2>;
+	type MaxConnectionsPerAccount = ConstU32<3>;
+	type MaxBulkRemove = ConstU32<3>;
+	type MaxFreeConnectionIds = ConstU32<2>;
+	type MaxLabelLength = ConstU32<16>;
+	type MaxAuthorizedSenders = ConstU32<2>;
+	type MaxTagLength = ConstU32<8>;
+	type MaxTagsPerConnection = ConstU32<2>;
+	type MaxMetadataKeyLength = ConstU32<8>;
+	type MaxMetadataValueLength = ConstU32<16>;
+	type MaxMetadataEntries = ConstU32<2>;
+	type MaxCommandRetries = ConstU32<2>;
+	type MaxCommandsPerBatch = MaxCommandsPerBatch;
+	type HttpRequestTimeout = HttpRequestTimeout;
+	type OcwSubmitStrategy = OcwSubmitStrategy;
+	type OcwRunOnNonAuthorities = OcwRunOnNonAuthorities;
+	type CommandTimeout = ConstU64<10>;
+	type CommandRetentionBlocks = ConstU64<5>;
+	type MaxPrunePerBlock = ConstU32<1>;
+	type MaxFlushPerCall = ConstU32<1>;
+	type CommandsPerConnectionPerBlock = ConstU32<2>;
+	type MaxInFlightPerConnection = MaxInFlightPerConnection;
+	type HealthWindow = ConstU32<4>;
+	type DegradedBelow = DegradedBelow;
+	type UnhealthyBelow = UnhealthyBelow;
+	type StaleConnectionBlocks = ConstU64<20>;
+	type UnreachableReportInterval = ConstU64<5>;
+	type QueueDepthAlertThreshold = QueueDepthAlertThreshold;
+	type QueueDepthAlertInterval = ConstU64<5>;
+	type Currency = Balances;
+	type ConnectionCreationCooldown = ConnectionCreationCooldown;
+	type ConnectionDeposit = ConnectionDeposit;
+	type CommandFee = CommandFee;
+	type FeeDestination = FeeDestination;
+	type GovernanceOrigin = EnsureRoot<AccountId>;
+	type ServerOrigin = pallet_edge_connect::EnsureConnectionOrigin<Test>;
+	type OnResponse = RecordResponses;
+	type PayloadCodec = SealingCodec;
+	type WeightInfo = ();
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	new_test_ext_with_connections(vec![])
+}
+
+// Build genesis storage with the given connections seeded.
+pub fn new_test_ext_with_connections(
+	connections: Vec<(AccountId, Vec<u8>)>,
+) -> sp_io::TestExternalities {
+	let storage = RuntimeGenesisConfig {
+		system: Default::default(),
+		balances: pallet_balances::GenesisConfig {
+			balances: (1..=4).map(|i| (sr25519::Public([i; 32]), ENDOWMENT)).collect(),
+		},
+		edge_connect: pallet_edge_connect::GenesisConfig {
+			authorized_domains: DOMAINS.iter().map(|domain| domain.to_vec()).collect(),
+			ocw_authorities: vec![OCW],
+			connections,
+		},
+	}
+	.build_storage()
+	.unwrap();
+
+	// Benchmarks sign responses with keys generated in the keystore.
+	let mut ext = sp_io::TestExternalities::new(storage);
+	ext.register_extension(KeystoreExt::new(MemoryKeystore::new()));
+	ext
+}
+
+// Build genesis storage for running the offchain worker against `CyberHub`, with one offchain
+// worker authority key in the keystore.
+pub fn new_offchain_test_ext() -> (sp_io::TestExternalities, CyberHub) {
+	let hub = CyberHub::default();
+	let keystore = MemoryKeystore::new();
+	let key = keystore.sr25519_generate_new(crate::KEY_TYPE, None).unwrap();
+
+	let mut ext = new_test_ext();
+	ext.execute_with(|| pallet_edge_connect::OcwAuthorities::<Test>::insert(key, ()));
+	ext.register_extension(OffchainWorkerExt::new(hub.clone()));
+	ext.register_extension(OffchainDbExt::new(hub.offchain.clone()));
+	ext.register_extension(TransactionPoolExt::new(hub.clone()));
+	ext.register_extension(KeystoreExt::new(keystore));
+	(ext, hub)
+}
+
+/// Stands in for CyberHub and the transaction pool in offchain worker tests.
+///
+/// Requests are expected in the order they are registered with `expect`, and answered with the
+/// status code and body given there. Transactions the worker submits are kept until `submitted`
+/// takes them.
+#[derive(Clone, Default)]
+pub struct CyberHub {
+	offchain: testing::TestOffchainExt,
+	state: Arc<Mutex<CyberHubState>>,
+}
+
+#[derive(Default)]
+struct CyberHubState {
+	/// Status codes of the expected requests that were not made yet, in order.
+	statuses: VecDeque<u16>,
+	/// Status codes of the requests made so far.
+	answers: BTreeMap<HttpRequestId, u16>,
+	/// Transactions submitted by the worker, encoded.
+	transactions: Vec<Vec<u8>>,
+	/// Whether the node pretends not to be a validator.
+	non_validator: bool,
+}
+
+impl CyberHub {
+	/// Expects a `method` request to `url` carrying `body`, answered with `status` and
+	/// `response`.
+	pub fn expect(&self, method: &str, url: &str, body: Vec<u8>, status: u16, response: &[u8]) {
+		self.offchain.0.write().expect_request(testing::PendingRequest {
+			method: method.into(),
+			uri: url.into(),
+			body,
+			response: Some(response.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+		self.state.lock().unwrap().statuses.push_back(status);
+	}
+
+	/// Expects `command` to be POSTed on its own to `url`, answered with `status` and
+	/// `response`.
+	pub fn expect_command(
+		&self,
+		url: &str,
+		command: &pallet_edge_connect::Command<Test>,
+		status: u16,
+		response: &[u8],
+	) {
+		self.expect("POST", url, EdgeConnect::encode_request(command), status, response);
+	}
+
+	/// Panics unless every expected request was made.
+	pub fn assert_requests_made(&self) {
+		let missing = self.state.lock().unwrap().statuses.len();
+		assert_eq!(missing, 0, "{} expected requests were not made", missing);
+	}
+
+	/// Makes the node a validator or not.
+	pub fn set_validator(&self, is_validator: bool) {
+		self.state.lock().unwrap().non_validator = !is_validator;
+	}
+
+	/// Takes the transactions the worker submitted so far.
+	pub fn submitted(&self) -> Vec<Extrinsic> {
+		let transactions = sp_std::mem::take(&mut self.state.lock().unwrap().transactions);
+		transactions.iter().map(|tx| Extrinsic::decode(&mut &tx[..]).unwrap()).collect()
+	}
+}
+
+impl offchain::Externalities for CyberHub {
+	fn is_validator(&self) -> bool {
+		!self.state.lock().unwrap().non_validator && self.offchain.is_validator()
+	}
+
+	fn network_state(&self) -> Result<OpaqueNetworkState, ()> {
+		self.offchain.network_state()
+	}
+
+	fn timestamp(&mut self) -> Timestamp {
+		self.offchain.timestamp()
+	}
+
+	fn sleep_until(&mut self, deadline: Timestamp) {
+		self.offchain.sleep_until(deadline)
+	}
+
+	fn random_seed(&mut self) -> [u8; 32] {
+		self.offchain.random_seed()
+	}
+
+	fn http_request_start(
+		&mut self,
+		method: &str,
+		uri: &str,
+		meta: &[u8],
+	) -> Result<HttpRequestId, ()> {
+		let id = self.offchain.http_request_start(method, uri, meta)?;
+		let mut state = self.state.lock().unwrap();
+		// Unexpected requests are turned down by the wrapped extension.
+		let status = state.statuses.pop_front().unwrap_or(200);
+		state.answers.insert(id, status);
+		Ok(id)
+	}
+
+	fn http_request_add_header(
+		&mut self,
+		request_id: HttpRequestId,
+		name: &str,
+		value: &str,
+	) -> Result<(), ()> {
+		self.offchain.http_request_add_header(request_id, name, value)
+	}
+
+	fn http_request_write_body(
+		&mut self,
+		request_id: HttpRequestId,
+		chunk: &[u8],
+		deadline: Option<Timestamp>,
+	) -> Result<(), HttpError> {
+		self.offchain.http_request_write_body(request_id, chunk, deadline)
+	}
+
+	fn http_response_wait(
+		&mut self,
+		ids: &[HttpRequestId],
+		deadline: Option<Timestamp>,
+	) -> Vec<HttpRequestStatus> {
+		let answers = &self.state.lock().unwrap().answers;
+		self.offchain
+			.http_response_wait(ids, deadline)
+			.into_iter()
+			.zip(ids)
+			.map(|(status, id)| match (status, answers.get(id)) {
+				(HttpRequestStatus::Finished(_), Some(code)) => HttpRequestStatus::Finished(*code),
+				(status, _) => status,
+			})
+			.collect()
+	}
+
+	fn http_response_headers(&mut self, request_id: HttpRequestId) -> Vec<(Vec<u8>, Vec<u8>)> {
+		self.offchain.http_response_headers(request_id)
+	}
+
+	fn http_response_read_body(
+		&mut self,
+		request_id: HttpRequestId,
+		buffer: &mut [u8],
+		deadline: Option<Timestamp>,
+	) -> Result<usize, HttpError> {
+		self.offchain.http_response_read_body(request_id, buffer, deadline)
+	}
+
+	fn set_authorized_nodes(&mut self, nodes: Vec<sp_core::OpaquePeerId>, authorized_only: bool) {
+		self.offchain.set_authorized_nodes(nodes, authorized_only)
+	}
+}
+
+impl offchain::TransactionPool for CyberHub {
+	fn submit_transaction(&mut self, extrinsic: Vec<u8>) -> Result<(), ()> {
+		self.state.lock().unwrap().transactions.push(extrinsic);
+		Ok(())
+	}
+}
@@ -1,27 +1,7545 @@
-use crate::{mock::*, Error, Event};
-use frame_support::{assert_noop, assert_ok};
+use crate::{
+	mock::*, wire, Call, CommandOutcome, CommandStatus, ConnectionDetails, ConnectionHealth,
+	ConnectionId, ConnectionMetadata, ConnectionStatus, ConnectionsPage, EdgeConnectStats, Error,
+	Event, HashAlgorithm, LastConnectionCreatedAt, MetadataEntries, NextConnectionId, PollMode,
+	QueueDepthAlert, ResponseHash, ResponsePayload, SendOptions, SubmitStrategy, TransactionType,
+	WeightInfo,
+};
+use frame_support::{
+	assert_noop, assert_ok,
+	dispatch::WithPostDispatchInfo,
+	traits::{Get, Hooks, ReservableCurrency},
+	weights::Weight,
+	BoundedVec,
+};
+use parity_scale_codec::{Decode, Encode};
+use sp_core::{
+	hexdisplay::HexDisplay,
+	offchain::{
+		testing, OffchainDbExt, OffchainWorkerExt, StorageKind, Timestamp, TransactionPoolExt,
+	},
+	sr25519, Pair, H256,
+};
+use sp_io::hashing::{blake2_256, sha2_256};
+use sp_keystore::{testing::MemoryKeystore, Keystore, KeystoreExt};
+use sp_runtime::{
+	offchain::{
+		http,
+		storage_lock::{StorageLock, Time},
+	},
+	traits::{Dispatchable, ValidateUnsigned},
+	transaction_validity::{InvalidTransaction, TransactionSource},
+	DispatchError,
+};
+
+fn url(raw: &[u8]) -> Vec<u8> {
+	raw.to_vec()
+}
+
+fn command(raw: &[u8]) -> Vec<u8> {
+	raw.to_vec()
+}
+
+fn rejected() -> Weight {
+	<() as WeightInfo>::send_command_rejected()
+}
+
+/// Moves `command_id` to `Dispatched`, as the offchain worker does once CyberHub took it.
+fn dispatch(command_id: u64) {
+	assert_ok!(EdgeConnect::update_command_status(
+		RuntimeOrigin::signed(OCW),
+		command_id,
+		CommandStatus::Dispatched
+	));
+}
+
+#[test]
+fn genesis_has_no_connections() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(EdgeConnect::next_connection_id(), ConnectionId(0));
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)), None);
+	});
+}
+
+#[test]
+fn genesis_seeds_connections() {
+	new_test_ext_with_connections(vec![
+		(ALICE, url(b"http://edge-a.example")),
+		(BOB, url(b"https://edge-b.example")),
+	])
+	.execute_with(|| {
+		let info = EdgeConnect::connection_info(ConnectionId(0)).unwrap();
+		assert_eq!(info.owner, ALICE);
+		assert_eq!(info.endpoints.to_vec(), vec![url(b"http://edge-a.example")]);
+		assert_eq!(info.created_at, 0);
+		assert_eq!(info.status, ConnectionStatus::Active);
+		assert_eq!(info.deposit, 0);
+		assert_eq!(Balances::reserved_balance(ALICE), 0);
+		assert_eq!(EdgeConnect::connections_of(ALICE).to_vec(), vec![ConnectionId(0)]);
+
+		let info = EdgeConnect::connection_info(ConnectionId(1)).unwrap();
+		assert_eq!(info.owner, BOB);
+		assert_eq!(EdgeConnect::connections_of(BOB).to_vec(), vec![ConnectionId(1)]);
+
+		// New connections get ids past the seeded ones.
+		assert_eq!(EdgeConnect::next_connection_id(), ConnectionId(2));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-c.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(2)).unwrap().owner, ALICE);
+	});
+}
 
 #[test]
-fn it_works_for_default_value() {
+fn create_connection_works() {
 	new_test_ext().execute_with(|| {
 		// Go past genesis block so events get deposited
 		System::set_block_number(1);
-		// Dispatch a signed extrinsic.
-		assert_ok!(TemplateModule::do_something(RuntimeOrigin::signed(1), 42));
-		// Read pallet storage and assert an expected result.
-		assert_eq!(TemplateModule::something(), Some(42));
-		// Assert that the correct event was deposited
-		System::assert_last_event(Event::SomethingStored { something: 42, who: 1 }.into());
+
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		let info = EdgeConnect::connection_info(ConnectionId(0)).unwrap();
+		assert_eq!(info.endpoints.to_vec(), vec![url(b"http://edge.example")]);
+		assert_eq!(info.active_endpoint, 0);
+		assert_eq!(info.owner, ALICE);
+		assert_eq!(info.created_at, 1);
+		assert_eq!(info.last_seen, 1);
+		assert_eq!(info.status, ConnectionStatus::Active);
+		assert_eq!(info.label, None);
+		assert_eq!(EdgeConnect::connections_of(ALICE).to_vec(), vec![ConnectionId(0)]);
+		assert_eq!(EdgeConnect::next_connection_id(), ConnectionId(1));
+		System::assert_last_event(
+			Event::ConnectionCreated { connection: ConnectionId(0), who: ALICE }.into(),
+		);
+	});
+}
+
+#[test]
+fn create_connection_rejects_duplicate() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"http://edge.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::ConnectionAlreadyExists
+		);
+
+		// The same endpoint may still be used by another account.
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(BOB),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+	});
+}
+
+#[test]
+fn create_connection_requires_signed_origin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::none(),
+				url(b"http://edge.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn create_connection_rejects_long_url() {
+	new_test_ext().execute_with(|| {
+		let max = <<Test as crate::Config>::MaxUrlLength as Get<u32>>::get() as usize;
+		let mut long = b"https://".to_vec();
+		long.resize(max + 1, b'a');
+
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				long,
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::UrlTooLong
+		);
+	});
+}
+
+#[test]
+fn create_connection_rejects_unsupported_scheme() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"ftp://edge.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::InvalidUrl
+		);
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"edge.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::InvalidUrl
+		);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"https://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+	});
+}
+
+#[test]
+fn create_connection_checks_labels() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			Some(b"edge".to_vec()),
+			None,
+			PollMode::Push
+		));
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0))
+				.unwrap()
+				.label
+				.map(|label| label.to_vec()),
+			Some(b"edge".to_vec())
+		);
+
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"http://edge-b.example"),
+				Some(b"edge".to_vec()),
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::DuplicateLabel
+		);
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"http://edge-b.example"),
+				Some(b"a label that is far too long".to_vec()),
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::LabelTooLong
+		);
+
+		// Labels only need to be unique per owner.
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(BOB),
+			url(b"http://edge-a.example"),
+			Some(b"edge".to_vec()),
+			None,
+			PollMode::Push
+		));
+	});
+}
+
+#[test]
+fn rename_connection_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			Some(b"edge".to_vec()),
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-b.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_noop!(
+			EdgeConnect::rename_connection(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(1),
+				Some(b"edge".to_vec()),
+				None
+			),
+			Error::<Test>::DuplicateLabel
+		);
+		assert_noop!(
+			EdgeConnect::rename_connection(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(1),
+				Some(b"backup".to_vec()),
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+
+		// Keeping the current label is fine.
+		assert_ok!(EdgeConnect::rename_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			Some(b"edge".to_vec()),
+			None
+		));
+		assert_ok!(EdgeConnect::rename_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(1),
+			Some(b"backup".to_vec()),
+			None
+		));
+
+		let label = EdgeConnect::connection_info(ConnectionId(1)).unwrap().label;
+		assert_eq!(label.clone().map(|label| label.to_vec()), Some(b"backup".to_vec()));
+		System::assert_last_event(
+			Event::ConnectionRenamed { connection: ConnectionId(1), label }.into(),
+		);
+	});
+}
+
+#[test]
+fn set_server_pubkey_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let pubkey = sr25519::Pair::from_seed(&[7; 32]).public();
+
+		assert_noop!(
+			EdgeConnect::set_server_pubkey(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				Some(pubkey),
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+
+		assert_ok!(EdgeConnect::set_server_pubkey(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			Some(pubkey),
+			None
+		));
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0)).unwrap().server_pubkey,
+			Some(pubkey)
+		);
+		System::assert_last_event(
+			Event::ServerPubkeySet { connection: ConnectionId(0), pubkey: Some(pubkey) }.into(),
+		);
+
+		// Unsigned responses are accepted again once the key is cleared.
+		assert_ok!(EdgeConnect::set_server_pubkey(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None,
+			None
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+	});
+}
+
+#[test]
+fn add_endpoint_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-c.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_noop!(
+			EdgeConnect::add_endpoint(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				url(b"http://edge-b.example"),
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+		assert_noop!(
+			EdgeConnect::add_endpoint(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				url(b"http://unknown.example"),
+				None
+			),
+			Error::<Test>::DomainNotAuthorized
+		);
+		assert_noop!(
+			EdgeConnect::add_endpoint(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				url(b"http://edge-a.example"),
+				None
+			),
+			Error::<Test>::DuplicateEndpoint
+		);
+		// The owner already reaches this edge server through another connection.
+		assert_noop!(
+			EdgeConnect::add_endpoint(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				url(b"http://edge-c.example"),
+				None
+			),
+			Error::<Test>::ConnectionAlreadyExists
+		);
+
+		assert_ok!(EdgeConnect::add_endpoint(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			url(b"http://edge-b.example"),
+			None
+		));
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0)).unwrap().endpoints.to_vec(),
+			vec![url(b"http://edge-a.example"), url(b"http://edge-b.example")]
+		);
+		System::assert_last_event(
+			Event::EndpointAdded {
+				connection: ConnectionId(0),
+				url: url(b"http://edge-b.example").try_into().unwrap(),
+			}
+			.into(),
+		);
+
+		assert_noop!(
+			EdgeConnect::add_endpoint(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				url(b"http://edge.example"),
+				None
+			),
+			Error::<Test>::TooManyEndpoints
+		);
+	});
+}
+
+#[test]
+fn remove_endpoint_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_noop!(
+			EdgeConnect::remove_endpoint(RuntimeOrigin::signed(ALICE), ConnectionId(0), 0, None),
+			Error::<Test>::LastEndpoint
+		);
+		assert_ok!(EdgeConnect::add_endpoint(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			url(b"http://edge-b.example"),
+			None
+		));
+		assert_ok!(EdgeConnect::record_endpoint_failover(
+			RuntimeOrigin::signed(OCW),
+			ConnectionId(0),
+			1
+		));
+
+		assert_noop!(
+			EdgeConnect::remove_endpoint(RuntimeOrigin::signed(BOB), ConnectionId(0), 0, None),
+			Error::<Test>::NotConnectionOwner
+		);
+		assert_noop!(
+			EdgeConnect::remove_endpoint(RuntimeOrigin::signed(ALICE), ConnectionId(0), 2, None),
+			Error::<Test>::UnknownEndpoint
+		);
+
+		// The endpoint that answers keeps doing so after the primary one is removed.
+		assert_ok!(EdgeConnect::remove_endpoint(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			0,
+			None
+		));
+		let info = EdgeConnect::connection_info(ConnectionId(0)).unwrap();
+		assert_eq!(info.endpoints.to_vec(), vec![url(b"http://edge-b.example")]);
+		assert_eq!(info.active_endpoint, 0);
+		System::assert_last_event(
+			Event::EndpointRemoved {
+				connection: ConnectionId(0),
+				url: url(b"http://edge-a.example").try_into().unwrap(),
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn update_endpoint_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-c.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::add_endpoint(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			url(b"http://edge-b.example"),
+			None
+		));
+		assert_ok!(EdgeConnect::record_endpoint_failover(
+			RuntimeOrigin::signed(OCW),
+			ConnectionId(0),
+			1
+		));
+
+		assert_noop!(
+			EdgeConnect::update_endpoint(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				url(b"http://edge.example"),
+				None,
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+		assert_noop!(
+			EdgeConnect::update_endpoint(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				url(b"ftp://edge.example"),
+				None,
+				None
+			),
+			Error::<Test>::InvalidUrl
+		);
+		assert_noop!(
+			EdgeConnect::update_endpoint(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				url(b"http://unknown.example"),
+				None,
+				None
+			),
+			Error::<Test>::DomainNotAuthorized
+		);
+		assert_noop!(
+			EdgeConnect::update_endpoint(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				url(b"http://edge-b.example"),
+				None,
+				None
+			),
+			Error::<Test>::DuplicateEndpoint
+		);
+		assert_noop!(
+			EdgeConnect::update_endpoint(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				url(b"http://edge-c.example"),
+				None,
+				None
+			),
+			Error::<Test>::ConnectionAlreadyExists
+		);
+
+		// The offchain worker goes back to the primary endpoint, now on its new host.
+		assert_ok!(EdgeConnect::update_endpoint(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			url(b"http://edge.example"),
+			None,
+			None
+		));
+		let info = EdgeConnect::connection_info(ConnectionId(0)).unwrap();
+		assert_eq!(
+			info.endpoints.to_vec(),
+			vec![url(b"http://edge.example"), url(b"http://edge-b.example")]
+		);
+		assert_eq!(info.active_endpoint, 0);
+		System::assert_last_event(
+			Event::EndpointUpdated {
+				connection: ConnectionId(0),
+				old_url: url(b"http://edge-a.example").try_into().unwrap(),
+				new_url: url(b"http://edge.example").try_into().unwrap(),
+			}
+			.into(),
+		);
+
+		assert_ok!(EdgeConnect::set_connection_status(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(1),
+			ConnectionStatus::Disconnected,
+			None
+		));
+		assert_noop!(
+			EdgeConnect::update_endpoint(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(1),
+				url(b"http://edge-a.example"),
+				None,
+				None
+			),
+			Error::<Test>::ConnectionNotActive
+		);
+	});
+}
+
+#[test]
+fn record_endpoint_failover_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			EdgeConnect::record_endpoint_failover(RuntimeOrigin::signed(OCW), ConnectionId(0), 1),
+			Error::<Test>::ConnectionDoesNotExist
+		);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::add_endpoint(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			url(b"http://edge-b.example"),
+			None
+		));
+
+		assert_noop!(
+			EdgeConnect::record_endpoint_failover(RuntimeOrigin::signed(OCW), ConnectionId(0), 2),
+			Error::<Test>::UnknownEndpoint
+		);
+		assert_noop!(
+			EdgeConnect::record_endpoint_failover(RuntimeOrigin::signed(OCW), ConnectionId(0), 0),
+			Error::<Test>::EndpointAlreadyActive
+		);
+		// Only offchain worker authorities may tell which endpoint answered.
+		assert_noop!(
+			EdgeConnect::record_endpoint_failover(RuntimeOrigin::signed(BOB), ConnectionId(0), 1),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(EdgeConnect::record_endpoint_failover(
+			RuntimeOrigin::signed(OCW),
+			ConnectionId(0),
+			1
+		));
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)).unwrap().active_endpoint, 1);
+		System::assert_last_event(
+			Event::EndpointFailover { connection: ConnectionId(0), from_index: 0, to_index: 1 }
+				.into(),
+		);
+
+		// Going back to the primary endpoint is a failover too.
+		assert_ok!(EdgeConnect::record_endpoint_failover(
+			RuntimeOrigin::signed(OCW),
+			ConnectionId(0),
+			0
+		));
+		System::assert_last_event(
+			Event::EndpointFailover { connection: ConnectionId(0), from_index: 1, to_index: 0 }
+				.into(),
+		);
+	});
+}
+
+#[test]
+fn set_connection_tags_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_noop!(
+			EdgeConnect::set_connection_tags(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				vec![b"gpu".to_vec()],
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+		assert_noop!(
+			EdgeConnect::set_connection_tags(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				vec![b"gpu".to_vec(), b"storage".to_vec(), b"ai".to_vec()],
+				None
+			),
+			Error::<Test>::TooManyTags
+		);
+		assert_noop!(
+			EdgeConnect::set_connection_tags(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				vec![b"inference".to_vec()],
+				None
+			),
+			Error::<Test>::InvalidTag
+		);
+		assert_noop!(
+			EdgeConnect::set_connection_tags(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				vec![Vec::new()],
+				None
+			),
+			Error::<Test>::InvalidTag
+		);
+		assert_noop!(
+			EdgeConnect::set_connection_tags(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				vec![b"gpu".to_vec(), b"gpu".to_vec()],
+				None
+			),
+			Error::<Test>::DuplicateTag
+		);
+
+		assert_ok!(EdgeConnect::set_connection_tags(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			vec![b"gpu".to_vec(), b"storage".to_vec()],
+			None
+		));
+		let tags = EdgeConnect::connection_info(ConnectionId(0)).unwrap().tags;
+		assert_eq!(
+			tags.iter().map(|tag| tag.to_vec()).collect::<Vec<_>>(),
+			vec![b"gpu".to_vec(), b"storage".to_vec()]
+		);
+		System::assert_last_event(
+			Event::ConnectionTagsSet { connection: ConnectionId(0), tags }.into(),
+		);
+
+		// An empty list clears the tags.
+		assert_ok!(EdgeConnect::set_connection_tags(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			Vec::new(),
+			None
+		));
+		assert!(EdgeConnect::connection_info(ConnectionId(0)).unwrap().tags.is_empty());
+	});
+}
+
+#[test]
+fn connections_by_tag_finds_tagged_connections() {
+	new_test_ext().execute_with(|| {
+		for (owner, endpoint, tags) in [
+			(ALICE, &b"http://edge-a.example"[..], vec![b"gpu".to_vec()]),
+			(ALICE, b"http://edge-b.example", vec![b"storage".to_vec()]),
+			(BOB, b"http://edge-c.example", vec![b"storage".to_vec(), b"gpu".to_vec()]),
+		] {
+			let connection = EdgeConnect::next_connection_id();
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(owner),
+				url(endpoint),
+				None,
+				None,
+				PollMode::Push
+			));
+			assert_ok!(EdgeConnect::set_connection_tags(
+				RuntimeOrigin::signed(owner),
+				connection,
+				tags,
+				None
+			));
+		}
+
+		let mut gpu = EdgeConnect::connections_by_tag(b"gpu".to_vec());
+		gpu.sort();
+		assert_eq!(gpu, vec![ConnectionId(0), ConnectionId(2)]);
+		assert_eq!(EdgeConnect::connections_by_tag(b"storage".to_vec()).len(), 2);
+		assert!(EdgeConnect::connections_by_tag(b"gp".to_vec()).is_empty());
+	});
+}
+
+#[test]
+fn set_metadata_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_noop!(
+			EdgeConnect::set_metadata(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				b"region".to_vec(),
+				b"eu-west".to_vec(),
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+		assert_noop!(
+			EdgeConnect::set_metadata(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				Vec::new(),
+				b"eu-west".to_vec(),
+				None
+			),
+			Error::<Test>::InvalidMetadataKey
+		);
+		assert_noop!(
+			EdgeConnect::set_metadata(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				b"datacenter".to_vec(),
+				b"eu-west".to_vec(),
+				None
+			),
+			Error::<Test>::InvalidMetadataKey
+		);
+		assert_noop!(
+			EdgeConnect::set_metadata(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				b"region".to_vec(),
+				b"europe-west-amsterdam".to_vec(),
+				None
+			),
+			Error::<Test>::MetadataValueTooLong
+		);
+
+		assert_ok!(EdgeConnect::set_metadata(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			b"region".to_vec(),
+			b"eu-west".to_vec(),
+			None
+		));
+		let key = BoundedVec::truncate_from(b"region".to_vec());
+		let value = EdgeConnect::connection_metadata(ConnectionId(0), &key);
+		assert_eq!(value.clone().map(|value| value.to_vec()), Some(b"eu-west".to_vec()));
+		System::assert_last_event(
+			Event::MetadataUpdated { connection: ConnectionId(0), key: key.clone(), value }.into(),
+		);
+
+		// Overwriting a key doesn't take up another entry.
+		assert_ok!(EdgeConnect::set_metadata(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			b"region".to_vec(),
+			b"us-east".to_vec(),
+			None
+		));
+		assert_ok!(EdgeConnect::set_metadata(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			b"rack".to_vec(),
+			b"7".to_vec(),
+			None
+		));
+		assert_eq!(
+			EdgeConnect::connection_metadata(ConnectionId(0), &key).map(|value| value.to_vec()),
+			Some(b"us-east".to_vec())
+		);
+		assert_eq!(MetadataEntries::<Test>::get(ConnectionId(0)), 2);
+
+		assert_noop!(
+			EdgeConnect::set_metadata(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				b"gpu".to_vec(),
+				b"a100".to_vec(),
+				None
+			),
+			Error::<Test>::TooManyMetadataEntries
+		);
+	});
+}
+
+#[test]
+fn connection_version_guards_concurrent_edits() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let version = || EdgeConnect::connection_info(ConnectionId(0)).unwrap().version;
+		assert_eq!(version(), 0);
+
+		// Two edits made against version 0, only the first one goes through.
+		assert_ok!(EdgeConnect::rename_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			Some(b"edge".to_vec()),
+			Some(0)
+		));
+		assert_eq!(version(), 1);
+		assert_noop!(
+			EdgeConnect::set_connection_tags(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				vec![b"gpu".to_vec()],
+				Some(0)
+			),
+			Error::<Test>::VersionMismatch
+		);
+
+		// Metadata edits count too, and skipping the check always works.
+		assert_ok!(EdgeConnect::set_metadata(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			b"region".to_vec(),
+			b"eu-west".to_vec(),
+			Some(1)
+		));
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None
+		));
+		assert_eq!(version(), 3);
+		assert_noop!(
+			EdgeConnect::clear_metadata(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				b"region".to_vec(),
+				Some(1)
+			),
+			Error::<Test>::VersionMismatch
+		);
+
+		// Failed edits leave the version alone.
+		assert_noop!(
+			EdgeConnect::pause_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0), Some(3)),
+			Error::<Test>::ConnectionAlreadyPaused
+		);
+		assert_eq!(EdgeConnect::connection_details_of(ALICE, None, 1).connections[0].1.version, 3);
+	});
+}
+
+#[test]
+fn clear_metadata_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::set_metadata(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			b"region".to_vec(),
+			b"eu-west".to_vec(),
+			None
+		));
+
+		assert_noop!(
+			EdgeConnect::clear_metadata(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				b"region".to_vec(),
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+		assert_noop!(
+			EdgeConnect::clear_metadata(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				b"rack".to_vec(),
+				None
+			),
+			Error::<Test>::UnknownMetadataKey
+		);
+
+		assert_ok!(EdgeConnect::clear_metadata(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			b"region".to_vec(),
+			None
+		));
+		let key = BoundedVec::truncate_from(b"region".to_vec());
+		assert_eq!(EdgeConnect::connection_metadata(ConnectionId(0), &key), None);
+		assert_eq!(MetadataEntries::<Test>::get(ConnectionId(0)), 0);
+		System::assert_last_event(
+			Event::MetadataUpdated { connection: ConnectionId(0), key, value: None }.into(),
+		);
 	});
 }
 
 #[test]
-fn correct_error_for_none_value() {
+fn removing_a_connection_clears_its_metadata() {
 	new_test_ext().execute_with(|| {
-		// Ensure the expected error is thrown when no value is present.
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::set_metadata(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			b"region".to_vec(),
+			b"eu-west".to_vec(),
+			None
+		));
+
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+
+		assert_eq!(ConnectionMetadata::<Test>::iter_prefix(ConnectionId(0)).count(), 0);
+		assert!(!MetadataEntries::<Test>::contains_key(ConnectionId(0)));
+	});
+}
+
+#[test]
+fn create_connection_respects_max_connections_per_account() {
+	new_test_ext().execute_with(|| {
+		let max = <<Test as crate::Config>::MaxConnectionsPerAccount as Get<u32>>::get();
+		for i in 0..max {
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				format!("http://edge.example/{}", i).into_bytes(),
+				None,
+				None,
+				PollMode::Push
+			));
+		}
+
 		assert_noop!(
-			TemplateModule::cause_error(RuntimeOrigin::signed(1)),
-			Error::<Test>::NoneValue
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"http://one-too-many.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::TooManyConnections
 		);
+		// Other accounts are not affected.
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(BOB),
+			url(b"http://one-too-many.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		// Removing a connection frees a slot.
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://one-too-many.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_eq!(EdgeConnect::connections_of(ALICE).len() as u32, max);
+	});
+}
+
+#[test]
+fn remove_connection_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)), None);
+		assert!(!EdgeConnect::connections_of(ALICE).contains(&ConnectionId(0)));
+		System::assert_last_event(
+			Event::ConnectionRemoved { connection: ConnectionId(0), who: ALICE }.into(),
+		);
+	});
+}
+
+#[test]
+fn remove_connection_drops_queued_commands() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"pong")
+		));
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"ok"),
+			None
+		));
+
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+
+		assert!(EdgeConnect::command_queue(ConnectionId(0)).is_empty());
+		for command_id in 0..2 {
+			assert_eq!(EdgeConnect::command_status(command_id), None);
+			assert_eq!(EdgeConnect::command_connection(command_id), None);
+			assert_eq!(EdgeConnect::responses(command_id), None);
+		}
+	});
+}
+
+#[test]
+fn remove_connection_fails_for_unknown_connection() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)),
+			Error::<Test>::ConnectionDoesNotExist
+		);
+	});
+}
+
+#[test]
+fn remove_connection_requires_owner() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_noop!(
+			EdgeConnect::remove_connection(RuntimeOrigin::signed(BOB), ConnectionId(0)),
+			Error::<Test>::NotConnectionOwner
+		);
+		assert_noop!(
+			EdgeConnect::remove_connection(RuntimeOrigin::none(), ConnectionId(0)),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn remove_connections_skips_connections_of_others() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		for (owner, endpoint) in [
+			(ALICE, &b"http://edge-a.example"[..]),
+			(BOB, b"http://edge-b.example"),
+			(ALICE, b"http://edge-c.example"),
+		] {
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(owner),
+				url(endpoint),
+				None,
+				None,
+				PollMode::Push
+			));
+		}
+		assert_eq!(Balances::reserved_balance(ALICE), 2 * DEPOSIT);
+
+		let connections = vec![ConnectionId(0), ConnectionId(1), ConnectionId(2)];
+		assert_ok!(EdgeConnect::remove_connections(
+			RuntimeOrigin::signed(ALICE),
+			connections.try_into().unwrap()
+		));
+		for connection in [ConnectionId(0), ConnectionId(2)] {
+			System::assert_has_event(Event::ConnectionRemoved { connection, who: ALICE }.into());
+		}
+		System::assert_last_event(
+			Event::ConnectionsRemoved { who: ALICE, count: 2, skipped: vec![ConnectionId(1)] }
+				.into(),
+		);
+		assert!(EdgeConnect::connections_of(ALICE).is_empty());
+		assert_eq!(Balances::reserved_balance(ALICE), 0);
+		assert!(EdgeConnect::connection_info(ConnectionId(1)).is_some());
+
+		// Nothing is left to remove the second time around.
+		assert_ok!(EdgeConnect::remove_connections(
+			RuntimeOrigin::signed(ALICE),
+			vec![ConnectionId(0)].try_into().unwrap()
+		));
+		System::assert_last_event(
+			Event::ConnectionsRemoved { who: ALICE, count: 0, skipped: vec![ConnectionId(0)] }
+				.into(),
+		);
+		assert_ok!(EdgeConnect::do_try_state());
+	});
+}
+
+#[test]
+fn force_remove_connection_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_eq!(Balances::reserved_balance(ALICE), DEPOSIT);
+
+		assert_ok!(EdgeConnect::force_remove_connection(RuntimeOrigin::root(), ConnectionId(0)));
+		System::assert_last_event(
+			Event::ConnectionForceRemoved { connection: ConnectionId(0), owner: ALICE }.into(),
+		);
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)), None);
+		assert!(EdgeConnect::connections_of(ALICE).is_empty());
+		assert!(EdgeConnect::command_queue(ConnectionId(0)).is_empty());
+		assert_eq!(EdgeConnect::command_status(0), None);
+		assert_eq!(Balances::reserved_balance(ALICE), 0);
+		assert_ok!(EdgeConnect::do_try_state());
+
+		assert_noop!(
+			EdgeConnect::force_remove_connection(RuntimeOrigin::root(), ConnectionId(0)),
+			Error::<Test>::ConnectionDoesNotExist
+		);
+	});
+}
+
+#[test]
+fn force_remove_connection_requires_governance() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		// Not even the owner may use it.
+		for origin in [RuntimeOrigin::signed(ALICE), RuntimeOrigin::signed(BOB)] {
+			assert_noop!(
+				EdgeConnect::force_remove_connection(origin, ConnectionId(0)),
+				DispatchError::BadOrigin
+			);
+		}
+	});
+}
+
+#[test]
+fn owns_follows_the_connection_owner() {
+	new_test_ext().execute_with(|| {
+		assert!(!EdgeConnect::owns(&ALICE, ConnectionId(0)));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert!(EdgeConnect::owns(&ALICE, ConnectionId(0)));
+		assert!(!EdgeConnect::owns(&BOB, ConnectionId(0)));
+
+		assert_ok!(EdgeConnect::transfer_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		assert!(!EdgeConnect::owns(&ALICE, ConnectionId(0)));
+		assert!(EdgeConnect::owns(&BOB, ConnectionId(0)));
+
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(BOB), ConnectionId(0)));
+		assert!(!EdgeConnect::owns(&BOB, ConnectionId(0)));
+	});
+}
+
+#[test]
+fn connection_deposit_is_reserved_and_returned() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)).unwrap().deposit, DEPOSIT);
+		assert_eq!(Balances::reserved_balance(ALICE), DEPOSIT);
+		assert_eq!(Balances::free_balance(ALICE), ENDOWMENT - DEPOSIT);
+
+		// The amount reserved at creation is returned, whatever the deposit is now.
+		ConnectionDeposit::set(DEPOSIT * 5);
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert_eq!(Balances::reserved_balance(ALICE), 0);
+		assert_eq!(Balances::free_balance(ALICE), ENDOWMENT);
+	});
+}
+
+#[test]
+fn connection_ids_do_not_wrap_around() {
+	new_test_ext().execute_with(|| {
+		NextConnectionId::<Test>::put(ConnectionId(u32::MAX - 1));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_eq!(
+			EdgeConnect::connections_of(ALICE).into_inner(),
+			vec![ConnectionId(u32::MAX - 1)]
+		);
+
+		// Once the ids run out, creating a connection fails instead of wrapping around.
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"http://edge-b.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::ConnectionIdExhausted
+		);
+		assert_eq!(Balances::reserved_balance(ALICE), DEPOSIT);
+
+		// Ids freed up by removed connections are still handed out.
+		assert_ok!(EdgeConnect::remove_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(u32::MAX - 1)
+		));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-b.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_eq!(
+			EdgeConnect::connections_of(ALICE).into_inner(),
+			vec![ConnectionId(u32::MAX - 1)]
+		);
+		assert_eq!(EdgeConnect::next_connection_id(), ConnectionId(u32::MAX));
+	});
+}
+
+#[test]
+fn removed_connection_ids_are_recycled() {
+	new_test_ext().execute_with(|| {
+		for edge in [b"http://edge-a.example", b"http://edge-b.example", b"http://edge-c.example"] {
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(edge),
+				None,
+				None,
+				PollMode::Push
+			));
+			LastConnectionCreatedAt::<Test>::remove(ALICE);
+		}
+		let generation =
+			|connection| EdgeConnect::connection_info(ConnectionId(connection)).unwrap().generation;
+		assert_eq!(generation(2), 0);
+
+		// Freed ids are kept lowest first, up to `MaxFreeConnectionIds` of them.
+		for connection in [2, 0, 1] {
+			assert_ok!(EdgeConnect::remove_connection(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(connection)
+			));
+		}
+		assert_eq!(
+			EdgeConnect::free_connection_ids().into_inner(),
+			vec![ConnectionId(0), ConnectionId(2)]
+		);
+		assert_eq!(EdgeConnect::connection_generation(ConnectionId(1)), 0);
+
+		// The lowest freed id is handed out first, to a connection of a newer generation.
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(BOB),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_eq!(EdgeConnect::connections_of(BOB).into_inner(), vec![ConnectionId(0)]);
+		assert_eq!(generation(0), 1);
+		assert_eq!(EdgeConnect::free_connection_ids().into_inner(), vec![ConnectionId(2)]);
+		assert_eq!(EdgeConnect::next_connection_id(), ConnectionId(3));
+
+		// The new connection starts out clean.
+		let info = EdgeConnect::connection_info(ConnectionId(0)).unwrap();
+		assert_eq!(info.owner, BOB);
+		assert!(info.authorized_senders.is_empty());
+		assert!(EdgeConnect::command_queue(ConnectionId(0)).is_empty());
+		assert_eq!(
+			EdgeConnect::connection_details_of(BOB, None, 10).connections[0].1.generation,
+			1
+		);
+
+		// Once the free ids are used up, fresh ones are handed out again.
+		LastConnectionCreatedAt::<Test>::remove(BOB);
+		for edge in [b"http://edge-b.example", b"http://edge-c.example"] {
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(BOB),
+				url(edge),
+				None,
+				None,
+				PollMode::Push
+			));
+			LastConnectionCreatedAt::<Test>::remove(BOB);
+		}
+		assert_eq!(
+			EdgeConnect::connections_of(BOB).into_inner(),
+			vec![ConnectionId(0), ConnectionId(2), ConnectionId(3)]
+		);
+		assert_eq!(generation(2), 1);
+		assert_eq!(generation(3), 0);
+		assert!(EdgeConnect::free_connection_ids().is_empty());
+	});
+}
+
+#[test]
+fn create_connection_requires_the_deposit() {
+	new_test_ext().execute_with(|| {
+		let pauper = sr25519::Public([9u8; 32]);
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(pauper),
+				url(b"http://edge.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::InsufficientBalance
+		);
+
+		ConnectionDeposit::set(ENDOWMENT);
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"http://edge.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::InsufficientBalance
+		);
+	});
+}
+
+#[test]
+fn create_connection_respects_the_creation_cooldown() {
+	new_test_ext().execute_with(|| {
+		ConnectionCreationCooldown::set(3);
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_eq!(LastConnectionCreatedAt::<Test>::get(ALICE), Some(1));
+
+		System::set_block_number(3);
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"http://edge-b.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::CreationOnCooldown
+		);
+		// The cooldown is per account.
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(BOB),
+			url(b"http://edge-c.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		System::set_block_number(4);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-b.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_eq!(LastConnectionCreatedAt::<Test>::get(ALICE), Some(4));
+	});
+}
+
+#[test]
+fn stats_follow_connections_and_commands() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let stats = |total_connections, active_connections, total_queued_commands| {
+			EdgeConnectStats { total_connections, active_connections, total_queued_commands }
+		};
+		assert_eq!(EdgeConnect::stats(), stats(0, 0, 0));
+
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(BOB),
+			url(b"http://edge-b.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		for _ in 0..2 {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			));
+		}
+		assert_eq!(EdgeConnect::stats(), stats(2, 2, 2));
+
+		// Only commands that still wait for delivery count as queued.
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Dispatched
+		));
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None
+		));
+		assert_eq!(EdgeConnect::stats(), stats(2, 1, 1));
+
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 1));
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(BOB), ConnectionId(1)));
+		assert_eq!(EdgeConnect::stats(), stats(1, 0, 0));
+
+		assert_ok!(EdgeConnect::resume_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None
+		));
+		assert_eq!(EdgeConnect::stats(), stats(1, 1, 0));
+		assert_ok!(EdgeConnect::do_try_state());
+	});
+}
+
+#[test]
+fn in_flight_commands_are_counted_per_connection() {
+	new_test_ext_with_connections(vec![
+		(ALICE, url(b"http://edge-a.example")),
+		(ALICE, url(b"http://edge-b.example")),
+	])
+	.execute_with(|| {
+		System::set_block_number(1);
+		for connection in [0, 0, 1] {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(connection),
+				command(b"ping")
+			));
+		}
+		for command_id in [0, 1, 2] {
+			for status in [CommandStatus::Dispatched, CommandStatus::AwaitingResponse] {
+				assert_ok!(EdgeConnect::update_command_status(
+					RuntimeOrigin::signed(OCW),
+					command_id,
+					status
+				));
+			}
+		}
+		assert_eq!(EdgeConnect::in_flight_commands(ConnectionId(0)), 2);
+		assert_eq!(EdgeConnect::in_flight_commands(ConnectionId(1)), 1);
+		let in_flight = |connection: usize| {
+			EdgeConnect::connection_details_of(ALICE, None, 10).connections[connection]
+				.1
+				.in_flight
+		};
+		assert_eq!(in_flight(0), 2);
+
+		// Commands stop being in flight once they are answered or given up on.
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		assert_eq!(in_flight(0), 1);
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			1,
+			CommandStatus::TimedOut
+		));
+		assert_eq!(in_flight(0), 0);
+		assert_eq!(in_flight(1), 1);
+
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(1)));
+		assert!(!crate::InFlightCommands::<Test>::contains_key(ConnectionId(1)));
+		assert_ok!(EdgeConnect::do_try_state());
+	});
+}
+
+#[test]
+fn connection_health_follows_recent_outcomes() {
+	new_test_ext_with_connections(vec![(ALICE, url(b"http://edge.example"))]).execute_with(|| {
+		System::set_block_number(1);
+		for _ in 0..2 {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			));
+		}
+		let health = || EdgeConnect::connection_details_of(ALICE, None, 10).connections[0].1.health;
+		assert_eq!(health(), ConnectionHealth::Healthy);
+
+		// Half of the commands completed, under the `DegradedBelow` of 75%.
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			1,
+			CommandStatus::Failed
+		));
+		assert_eq!(EdgeConnect::recent_outcomes(ConnectionId(0)).to_vec(), vec![true, false]);
+		assert_eq!(health(), ConnectionHealth::Degraded);
+
+		// Only the latest `HealthWindow` outcomes count.
+		for completed in [false, false, true] {
+			EdgeConnect::record_outcome(0, completed);
+		}
+		assert_eq!(
+			EdgeConnect::recent_outcomes(ConnectionId(0)).to_vec(),
+			vec![false, false, false, true]
+		);
+		assert_eq!(health(), ConnectionHealth::Unhealthy);
+		for _ in 0..3 {
+			EdgeConnect::record_outcome(0, true);
+		}
+		assert_eq!(health(), ConnectionHealth::Healthy);
+
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert!(!crate::RecentOutcomes::<Test>::contains_key(ConnectionId(0)));
+	});
+}
+
+#[test]
+fn connection_deposit_moves_with_transfer() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_ok!(EdgeConnect::transfer_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		assert_eq!(Balances::reserved_balance(ALICE), 0);
+		assert_eq!(Balances::free_balance(ALICE), ENDOWMENT - DEPOSIT);
+		assert_eq!(Balances::reserved_balance(BOB), DEPOSIT);
+
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(BOB), ConnectionId(0)));
+		assert_eq!(Balances::reserved_balance(BOB), 0);
+		assert_eq!(Balances::free_balance(BOB), ENDOWMENT + DEPOSIT);
+	});
+}
+
+#[test]
+fn set_connection_status_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_ok!(EdgeConnect::set_connection_status(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			ConnectionStatus::Paused,
+			None
+		));
+
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0)).unwrap().status,
+			ConnectionStatus::Paused
+		);
+		System::assert_last_event(
+			Event::ConnectionStatusChanged {
+				connection: ConnectionId(0),
+				who: ALICE,
+				status: ConnectionStatus::Paused,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn set_connection_status_requires_owner() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EdgeConnect::set_connection_status(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				ConnectionStatus::Paused,
+				None
+			),
+			Error::<Test>::ConnectionDoesNotExist
+		);
+
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_noop!(
+			EdgeConnect::set_connection_status(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				ConnectionStatus::Paused,
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+	});
+}
+
+#[test]
+fn pause_and_resume_connection_work() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None
+		));
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0)).unwrap().status,
+			ConnectionStatus::Paused
+		);
+		System::assert_last_event(
+			Event::ConnectionStatusChanged {
+				connection: ConnectionId(0),
+				who: ALICE,
+				status: ConnectionStatus::Paused,
+			}
+			.into(),
+		);
+
+		assert_ok!(EdgeConnect::resume_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None
+		));
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0)).unwrap().status,
+			ConnectionStatus::Active
+		);
+		System::assert_last_event(
+			Event::ConnectionStatusChanged {
+				connection: ConnectionId(0),
+				who: ALICE,
+				status: ConnectionStatus::Active,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn lifecycle_changes_are_stamped_on_the_connection() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let stamped =
+			|| EdgeConnect::connection_info(ConnectionId(0)).unwrap().last_lifecycle_block;
+		assert_eq!(stamped(), 1);
+
+		// Changes that leave status and owner alone are not lifecycle changes.
+		System::set_block_number(2);
+		assert_ok!(EdgeConnect::rename_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			Some(b"edge".to_vec()),
+			None
+		));
+		assert_eq!(stamped(), 1);
+
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None
+		));
+		assert_eq!(stamped(), 2);
+
+		System::set_block_number(3);
+		assert_ok!(EdgeConnect::transfer_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		assert_eq!(stamped(), 3);
+
+		// Connections that go stale are stamped too.
+		System::set_block_number(4);
+		assert_ok!(EdgeConnect::resume_connection(
+			RuntimeOrigin::signed(BOB),
+			ConnectionId(0),
+			None
+		));
+		System::assert_last_event(
+			Event::ConnectionStatusChanged {
+				connection: ConnectionId(0),
+				who: BOB,
+				status: ConnectionStatus::Active,
+			}
+			.into(),
+		);
+		// `StaleConnectionBlocks` is twenty.
+		EdgeConnect::on_initialize(25);
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0)).unwrap().status,
+			ConnectionStatus::Disconnected
+		);
+		assert_eq!(stamped(), 25);
+		assert_eq!(
+			EdgeConnect::connection_details_of(BOB, None, 10).connections[0]
+				.1
+				.last_lifecycle_block,
+			25
+		);
+	});
+}
+
+#[test]
+fn pause_and_resume_connection_reject_wrong_state() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_noop!(
+			EdgeConnect::resume_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0), None),
+			Error::<Test>::ConnectionNotPaused
+		);
+		assert_noop!(
+			EdgeConnect::pause_connection(RuntimeOrigin::signed(BOB), ConnectionId(0), None),
+			Error::<Test>::NotConnectionOwner
+		);
+
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None
+		));
+		assert_noop!(
+			EdgeConnect::pause_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0), None),
+			Error::<Test>::ConnectionAlreadyPaused
+		);
+		assert_noop!(
+			EdgeConnect::resume_connection(RuntimeOrigin::signed(BOB), ConnectionId(0), None),
+			Error::<Test>::NotConnectionOwner
+		);
+
+		assert_ok!(EdgeConnect::set_connection_status(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			ConnectionStatus::Disconnected,
+			None
+		));
+		assert_noop!(
+			EdgeConnect::pause_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0), None),
+			Error::<Test>::ConnectionNotActive
+		);
+	});
+}
+
+#[test]
+fn transfer_connection_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_ok!(EdgeConnect::transfer_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)).unwrap().owner, BOB);
+		assert!(!EdgeConnect::connections_of(ALICE).contains(&ConnectionId(0)));
+		assert_eq!(EdgeConnect::connections_of(BOB).to_vec(), vec![ConnectionId(0)]);
+		System::assert_last_event(
+			Event::ConnectionTransferred { connection: ConnectionId(0), from: ALICE, to: BOB }
+				.into(),
+		);
+
+		// The previous owner no longer controls the connection.
+		assert_noop!(
+			EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)),
+			Error::<Test>::NotConnectionOwner
+		);
+	});
+}
+
+#[test]
+fn transfer_connection_ends_the_lease() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::grant_command_access(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		assert_ok!(EdgeConnect::lease_connection(RuntimeOrigin::signed(BOB), ConnectionId(0), 5));
+		let carol = sr25519::Public([3u8; 32]);
+
+		assert_ok!(EdgeConnect::transfer_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			carol,
+			None
+		));
+		System::assert_has_event(
+			Event::LeaseReleased { connection: ConnectionId(0), lessee: BOB }.into(),
+		);
+		assert_eq!(EdgeConnect::lease(ConnectionId(0)), None);
+
+		// The new owner may lease the connection out again.
+		assert_ok!(EdgeConnect::grant_command_access(
+			RuntimeOrigin::signed(carol),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		assert_ok!(EdgeConnect::lease_connection(RuntimeOrigin::signed(BOB), ConnectionId(0), 7));
+		EdgeConnect::on_initialize(5);
+		assert_eq!(EdgeConnect::lease(ConnectionId(0)), Some((BOB, 7)));
+	});
+}
+
+#[test]
+fn transfer_connection_moves_what_is_left_of_the_deposit() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		// Part of the deposit was slashed in the meantime.
+		let _ = Balances::slash_reserved(&ALICE, 4);
+
+		assert_ok!(EdgeConnect::transfer_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)).unwrap().deposit, DEPOSIT - 4);
+		assert_eq!(Balances::reserved_balance(BOB), DEPOSIT - 4);
+
+		// Only what was moved is given back.
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(BOB), ConnectionId(0)));
+		assert_eq!(Balances::reserved_balance(BOB), 0);
+		assert_eq!(Balances::free_balance(BOB), ENDOWMENT + DEPOSIT - 4);
+	});
+}
+
+#[test]
+fn transfer_connection_rejects_invalid_transfers() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EdgeConnect::transfer_connection(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				BOB,
+				None
+			),
+			Error::<Test>::ConnectionDoesNotExist
+		);
+
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_noop!(
+			EdgeConnect::transfer_connection(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				ALICE,
+				None
+			),
+			Error::<Test>::CannotTransferToSelf
+		);
+		assert_noop!(
+			EdgeConnect::transfer_connection(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				BOB,
+				None
+			),
+			Error::<Test>::CannotTransferToSelf
+		);
+		assert_noop!(
+			EdgeConnect::transfer_connection(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				ALICE,
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+
+		// Bob already has a connection to the same edge server.
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(BOB),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_noop!(
+			EdgeConnect::transfer_connection(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				BOB,
+				None
+			),
+			Error::<Test>::ConnectionAlreadyExists
+		);
+	});
+}
+
+#[test]
+fn send_command_queues_command() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::grant_command_access(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+
+		assert_ok!(EdgeConnect::send_raw_command(
+			RuntimeOrigin::signed(BOB),
+			ConnectionId(0),
+			command(b"ping"),
+			SendOptions { priority: 3, ..Default::default() }
+		));
+
+		let queue = EdgeConnect::command_queue(ConnectionId(0));
+		assert_eq!(queue.len(), 1);
+		assert_eq!(queue[0].id, 0);
+		assert_eq!(queue[0].payload.to_vec(), b"ping".to_vec());
+		assert_eq!(queue[0].priority, 3);
+		assert_eq!(queue[0].submitter, BOB);
+		assert_eq!(queue[0].submitted_at, 1);
+		assert_eq!(EdgeConnect::next_command_id(), 1);
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Queued));
+		System::assert_last_event(
+			Event::CommandQueued { connection: ConnectionId(0), command_id: 0, nonce: 0 }.into(),
+		);
+	});
+}
+
+#[test]
+fn commands_get_increasing_nonces_per_connection() {
+	new_test_ext_with_connections(vec![
+		(ALICE, url(b"http://edge-a.example")),
+		(ALICE, url(b"http://edge-b.example")),
+	])
+	.execute_with(|| {
+		System::set_block_number(1);
+		let send = |connection| {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(connection),
+				command(b"ping")
+			));
+		};
+		send(0);
+		send(1);
+		// Nonces are not reused, even once the command that had one is gone.
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 0));
+		send(0);
+
+		let nonces: Vec<_> = EdgeConnect::command_queue(ConnectionId(0))
+			.iter()
+			.map(|command| (command.id, command.nonce))
+			.collect();
+		assert_eq!(nonces, vec![(2, 1)]);
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(1))[0].nonce, 0);
+		assert_eq!(EdgeConnect::next_command_nonce(ConnectionId(0)), 2);
+		System::assert_last_event(
+			Event::CommandQueued { connection: ConnectionId(0), command_id: 2, nonce: 1 }.into(),
+		);
+
+		// The next connection given the id carries on from there.
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert_eq!(EdgeConnect::next_command_nonce(ConnectionId(0)), 2);
+	});
+}
+
+#[test]
+fn send_command_deduplicates_client_refs() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let send = |client_ref| {
+			assert_ok!(EdgeConnect::send_raw_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping"),
+				SendOptions { client_ref, ..Default::default() }
+			));
+		};
+		send(Some([1; 32]));
+		assert_eq!(EdgeConnect::command_by_client_ref(ConnectionId(0), [1; 32]), Some(0));
+
+		// The retry is pointed at the command already queued.
+		send(Some([1; 32]));
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(0)).len(), 1);
+		assert_eq!(EdgeConnect::next_command_id(), 1);
+		System::assert_last_event(
+			Event::CommandDeduplicated {
+				connection: ConnectionId(0),
+				client_ref: [1; 32],
+				command_id: 0,
+			}
+			.into(),
+		);
+
+		// Even once the queue is full and the connection is rate limited.
+		send(None);
+		send(Some([1; 32]));
+		System::assert_last_event(
+			Event::CommandDeduplicated {
+				connection: ConnectionId(0),
+				client_ref: [1; 32],
+				command_id: 0,
+			}
+			.into(),
+		);
+		assert_noop!(
+			EdgeConnect::send_raw_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping"),
+				SendOptions { client_ref: Some([2; 32]), ..Default::default() }
+			),
+			Error::<Test>::CommandQueueFull
+		);
+
+		// Refs are forgotten along with their command, once it is pruned.
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		EdgeConnect::on_initialize(7);
+		assert_eq!(EdgeConnect::command_by_client_ref(ConnectionId(0), [1; 32]), None);
+		send(Some([1; 32]));
+		assert_eq!(EdgeConnect::command_by_client_ref(ConnectionId(0), [1; 32]), Some(2));
+		System::assert_last_event(
+			Event::CommandQueued { connection: ConnectionId(0), command_id: 2, nonce: 2 }.into(),
+		);
+	});
+}
+
+#[test]
+fn send_raw_command_takes_its_options_at_once() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let options = SendOptions {
+			priority: 7,
+			valid_until: Some(5),
+			client_ref: Some([1; 32]),
+			ack_only: true,
+			seq: None,
+		};
+		assert_ok!(EdgeConnect::send_raw_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping"),
+			options.clone()
+		));
+
+		let queued = &EdgeConnect::command_queue(ConnectionId(0))[0];
+		assert_eq!((queued.priority, queued.valid_until, queued.ack_only), (7, Some(5), true));
+		assert_eq!(EdgeConnect::command_by_client_ref(ConnectionId(0), [1; 32]), Some(0));
+
+		// Resubmissions with the same `client_ref` are deduplicated.
+		assert_ok!(EdgeConnect::send_raw_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping"),
+			options
+		));
+		assert_eq!(EdgeConnect::next_command_id(), 1);
+
+		// The default options are those `send_command` queues with.
+		assert_ok!(EdgeConnect::send_raw_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"pong"),
+			SendOptions::default()
+		));
+		let queued = &EdgeConnect::command_queue(ConnectionId(0))[1];
+		assert_eq!((queued.priority, queued.valid_until, queued.ack_only), (0, None, false));
+		assert_noop!(
+			EdgeConnect::send_raw_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"late"),
+				SendOptions { valid_until: Some(0), ..Default::default() }
+			),
+			Error::<Test>::CommandAlreadyExpired.with_weight(rejected())
+		);
+	});
+}
+
+#[test]
+fn numbered_commands_are_queued_in_order() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let send = |who, seq| {
+			EdgeConnect::send_raw_command(
+				RuntimeOrigin::signed(who),
+				ConnectionId(0),
+				command(b"ping"),
+				SendOptions { seq, ..Default::default() },
+			)
+		};
+
+		// Numbering starts at 0.
+		assert_noop!(send(ALICE, Some(1)), Error::<Test>::SequenceGap);
+		assert_ok!(send(ALICE, Some(0)));
+		assert_eq!(EdgeConnect::last_seq(ALICE), Some(0));
+
+		assert_noop!(send(ALICE, Some(0)), Error::<Test>::StaleSequence);
+		assert_noop!(send(ALICE, Some(2)), Error::<Test>::SequenceGap);
+		// Unnumbered commands leave the sequence alone.
+		assert_ok!(send(ALICE, None));
+		// A cancelled command keeps its number taken.
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 0));
+		System::set_block_number(2);
+		EdgeConnect::on_initialize(2);
+		assert_noop!(send(ALICE, Some(0)), Error::<Test>::StaleSequence);
+		assert_ok!(send(ALICE, Some(1)));
+		assert_eq!(EdgeConnect::last_seq(ALICE), Some(1));
+		assert_eq!(EdgeConnect::next_command_id(), 3);
+
+		// Every account numbers its own commands.
+		assert_ok!(EdgeConnect::grant_command_access(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 1));
+		assert_noop!(send(BOB, Some(1)), Error::<Test>::SequenceGap);
+		assert_ok!(send(BOB, Some(0)));
+		assert_eq!(EdgeConnect::last_seq(BOB), Some(0));
+	});
+}
+
+#[test]
+fn commands_are_sent_from_templates() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::register_template(
+			RuntimeOrigin::signed(BOB),
+			command(br#"{"run":"{0}","args":["{1}","{0}"]}"#).try_into().unwrap(),
+			None
+		));
+		System::assert_last_event(Event::TemplateRegistered { template_id: 0, who: BOB }.into());
+		let params = |params: &[&[u8]]| {
+			params
+				.iter()
+				.map(|param| command(param).try_into().unwrap())
+				.collect::<Vec<_>>()
+				.try_into()
+				.unwrap()
+		};
+
+		// Anyone may send commands from a template, over the connections they may use.
+		assert_ok!(EdgeConnect::send_command_from_template(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			0,
+			params(&[b"ping", b"-c1"])
+		));
+		assert_eq!(
+			EdgeConnect::command_queue(ConnectionId(0))[0].payload.to_vec(),
+			br#"{"run":"ping","args":["-c1","ping"]}"#.to_vec()
+		);
+		assert_noop!(
+			EdgeConnect::send_command_from_template(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				0,
+				params(&[b"ping", b"-c1"])
+			),
+			Error::<Test>::NotAuthorizedToSend.with_weight(rejected())
+		);
+		assert_noop!(
+			EdgeConnect::send_command_from_template(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				0,
+				params(&[b"ping"])
+			),
+			Error::<Test>::MissingTemplateParam
+		);
+		assert_noop!(
+			EdgeConnect::send_command_from_template(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				0,
+				params(&[&[b'x'; 32], b"-c1"])
+			),
+			Error::<Test>::TemplateCommandTooLong
+		);
+		assert_noop!(
+			EdgeConnect::send_command_from_template(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				1,
+				params(&[])
+			),
+			Error::<Test>::UnknownTemplate
+		);
+
+		// Only the owner of a template may remove it.
+		assert_noop!(
+			EdgeConnect::remove_template(RuntimeOrigin::signed(ALICE), 0),
+			Error::<Test>::NotTemplateOwner
+		);
+		assert_ok!(EdgeConnect::remove_template(RuntimeOrigin::signed(BOB), 0));
+		System::assert_last_event(Event::TemplateRemoved { template_id: 0, who: BOB }.into());
+		assert_eq!(EdgeConnect::command_template(0), None);
+		assert_noop!(
+			EdgeConnect::remove_template(RuntimeOrigin::signed(BOB), 0),
+			Error::<Test>::UnknownTemplate
+		);
+	});
+}
+
+#[test]
+fn send_command_without_fee_charges_nothing() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let issuance = Balances::total_issuance();
+
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		assert_eq!(Balances::free_balance(ALICE), ENDOWMENT - DEPOSIT);
+		assert_eq!(Balances::total_issuance(), issuance);
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::EdgeConnect(Event::CommandFeePaid { .. })
+		)));
+	});
+}
+
+#[test]
+fn send_command_pays_fee_to_destination() {
+	new_test_ext().execute_with(|| {
+		let treasury = sr25519::Public([3u8; 32]);
+		System::set_block_number(1);
+		CommandFee::set(5);
+		FeeDestination::set(Some(treasury));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		assert_eq!(Balances::free_balance(ALICE), ENDOWMENT - DEPOSIT - 5);
+		assert_eq!(Balances::free_balance(treasury), ENDOWMENT + 5);
+		System::assert_has_event(Event::CommandFeePaid { who: ALICE, amount: 5 }.into());
+	});
+}
+
+#[test]
+fn broadcast_command_queues_on_every_active_connection() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		CommandFee::set(5);
+		for endpoint in
+			[&b"http://edge-a.example"[..], b"http://edge-b.example", b"http://edge-c.example"]
+		{
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(endpoint),
+				None,
+				None,
+				PollMode::Push
+			));
+		}
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(BOB),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		// The second connection is paused, the queue of the third one is full.
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(1),
+			None
+		));
+		for _ in 0..2 {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(2),
+				command(b"busy")
+			));
+		}
+		let balance = Balances::free_balance(ALICE);
+
+		assert_ok!(EdgeConnect::broadcast_command(RuntimeOrigin::signed(ALICE), command(b"ping")));
+
+		System::assert_last_event(
+			Event::CommandBroadcast { broadcast_id: 0, count: 1, skipped: vec![ConnectionId(2)] }
+				.into(),
+		);
+		let queue = EdgeConnect::command_queue(ConnectionId(0));
+		assert_eq!(queue.len(), 1);
+		assert_eq!(queue[0].payload, command(b"ping"));
+		assert!(EdgeConnect::command_queue(ConnectionId(1)).is_empty());
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(2)).len(), 2);
+		assert!(EdgeConnect::command_queue(ConnectionId(3)).is_empty());
+		// Only the command that was queued is paid for.
+		assert_eq!(Balances::free_balance(ALICE), balance - 5);
+
+		assert_noop!(
+			EdgeConnect::broadcast_command(RuntimeOrigin::signed(ALICE), command(b"")),
+			Error::<Test>::InvalidCommand
+		);
+	});
+}
+
+#[test]
+fn broadcasts_are_tracked_until_every_command_settled() {
+	new_test_ext_with_connections(vec![
+		(ALICE, url(b"http://edge-a.example")),
+		(ALICE, url(b"http://edge-b.example")),
+	])
+	.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::broadcast_command(RuntimeOrigin::signed(ALICE), command(b"ping")));
+		System::assert_last_event(
+			Event::CommandBroadcast { broadcast_id: 0, count: 2, skipped: vec![] }.into(),
+		);
+		assert_eq!(EdgeConnect::command_broadcast(1), Some(0));
+		let results = EdgeConnect::broadcast_results(0);
+		assert_eq!(
+			results
+				.iter()
+				.map(|result| (result.command_id, result.status))
+				.collect::<Vec<_>>(),
+			vec![(0, CommandStatus::Queued), (1, CommandStatus::Queued)]
+		);
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		let results = EdgeConnect::broadcast_results(0);
+		assert_eq!(results[0].status, CommandStatus::Completed);
+		assert_eq!(results[0].response, Some(b"pong".to_vec()));
+		assert_eq!(results[1].response, None);
+		assert_eq!(EdgeConnect::broadcast(0).map(|broadcast| broadcast.pending), Some(1));
+
+		// A cancelled command no longer holds the broadcast up, and is no longer listed.
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 1));
+		System::assert_has_event(Event::BroadcastCompleted { broadcast_id: 0 }.into());
+		let results = EdgeConnect::broadcast_results(0);
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].connection, ConnectionId(0));
+
+		// A broadcast that queued nothing is done right away.
+		for connection in [0, 1] {
+			assert_ok!(EdgeConnect::pause_connection(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(connection),
+				None
+			));
+		}
+		assert_ok!(EdgeConnect::broadcast_command(RuntimeOrigin::signed(ALICE), command(b"ping")));
+		System::assert_last_event(Event::BroadcastCompleted { broadcast_id: 1 }.into());
+		assert_eq!(EdgeConnect::broadcast(1), None);
+		assert!(EdgeConnect::broadcast_results(1).is_empty());
+
+		// Once its last command is gone, so is the broadcast.
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert_eq!(EdgeConnect::broadcast(0), None);
+		assert_eq!(EdgeConnect::command_broadcast(0), None);
+	});
+}
+
+#[test]
+fn can_send_command_runs_the_send_command_checks() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_eq!(
+			EdgeConnect::can_send_command(&ALICE, ConnectionId(1)),
+			Err(Error::<Test>::ConnectionDoesNotExist.into())
+		);
+		assert_eq!(
+			EdgeConnect::can_send_command(&BOB, ConnectionId(0)),
+			Err(Error::<Test>::NotAuthorizedToSend.into())
+		);
+		assert_ok!(EdgeConnect::can_send_command(&ALICE, ConnectionId(0)));
+		// Nothing is queued by the check.
+		assert!(EdgeConnect::command_queue(ConnectionId(0)).is_empty());
+		assert_eq!(EdgeConnect::next_command_id(), 0);
+
+		for _ in 0..2 {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			));
+		}
+		assert_eq!(
+			EdgeConnect::can_send_command(&ALICE, ConnectionId(0)),
+			Err(Error::<Test>::CommandQueueFull.into())
+		);
+
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None
+		));
+		assert_eq!(
+			EdgeConnect::can_send_command(&ALICE, ConnectionId(0)),
+			Err(Error::<Test>::ConnectionNotActive.into())
+		);
+	});
+}
+
+#[test]
+fn send_command_is_rate_limited_per_block() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		// Up to `CommandsPerConnectionPerBlock` commands go through.
+		for payload in [&b"ping"[..], b"pong"] {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(payload)
+			));
+		}
+
+		// Cancelling frees up room in the queue, but not in the block.
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 0));
+		assert_eq!(
+			EdgeConnect::can_send_command(&ALICE, ConnectionId(0)),
+			Err(Error::<Test>::RateLimited.into())
+		);
+		assert_noop!(
+			EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"status")
+			),
+			Error::<Test>::RateLimited
+		);
+
+		// The quota is reset in the next block.
+		System::set_block_number(2);
+		EdgeConnect::on_initialize(2);
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"status")
+		));
+	});
+}
+
+#[test]
+fn send_command_burns_fee_without_destination() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		CommandFee::set(5);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let issuance = Balances::total_issuance();
+
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		assert_eq!(Balances::free_balance(ALICE), ENDOWMENT - DEPOSIT - 5);
+		assert_eq!(Balances::total_issuance(), issuance - 5);
+		System::assert_has_event(Event::CommandFeePaid { who: ALICE, amount: 5 }.into());
+	});
+}
+
+#[test]
+fn send_command_requires_the_fee() {
+	new_test_ext().execute_with(|| {
+		CommandFee::set(ENDOWMENT);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_noop!(
+			EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			),
+			Error::<Test>::InsufficientBalance
+		);
+	});
+}
+
+#[test]
+fn send_command_fails_for_unknown_connection() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			),
+			Error::<Test>::ConnectionDoesNotExist.with_weight(rejected())
+		);
+	});
+}
+
+#[test]
+fn send_command_refunds_early_rejections_only() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert!(rejected().all_lt(<() as WeightInfo>::send_command()));
+
+		let error = EdgeConnect::send_command(
+			RuntimeOrigin::signed(BOB),
+			ConnectionId(0),
+			command(b"ping"),
+		)
+		.unwrap_err();
+		assert_eq!(error.post_info.actual_weight, Some(rejected()));
+
+		// Commands that make it past the connection checks pay in full.
+		let info = EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping"),
+		)
+		.unwrap();
+		assert_eq!(info.actual_weight, None);
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			None
+		));
+		let error = EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping"),
+		)
+		.unwrap_err();
+		assert_eq!(error.error, Error::<Test>::ConnectionNotActive.into());
+		assert_eq!(error.post_info.actual_weight, None);
+	});
+}
+
+#[test]
+fn send_command_requires_active_connection() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::set_connection_status(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			ConnectionStatus::Paused,
+			None
+		));
+
+		assert_noop!(
+			EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			),
+			Error::<Test>::ConnectionNotActive
+		);
+	});
+}
+
+#[test]
+fn send_command_rejects_oversized_commands() {
+	new_test_ext().execute_with(|| {
+		let max = <<Test as crate::Config>::MaxCommandLength as Get<u32>>::get() as usize;
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(&vec![b'c'; max])
+		));
+
+		assert_noop!(
+			EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(&vec![b'c'; max + 1])
+			),
+			Error::<Test>::CommandTooLong
+		);
+	});
+}
+
+#[test]
+fn send_command_rejects_full_queue() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		for _ in 0..<Test as crate::Config>::MaxQueuedCommands::get() {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			));
+		}
+
+		assert_noop!(
+			EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			),
+			Error::<Test>::CommandQueueFull
+		);
+	});
+}
+
+fn response(raw: &[u8]) -> BoundedVec<u8, <Test as crate::Config>::MaxResponseLength> {
+	raw.to_vec().try_into().unwrap()
+}
+
+#[test]
+fn receive_response_stores_response() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+
+		assert_eq!(EdgeConnect::responses(0), Some(response(b"pong")));
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Completed));
+		System::assert_has_event(
+			Event::CommandStatusChanged { command_id: 0, status: CommandStatus::Completed }.into(),
+		);
+		System::assert_last_event(
+			Event::ResponseReceived {
+				command_id: 0,
+				maybe_who: Some(OCW),
+				response_hash: H256(blake2_256(b"pong")),
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn recorded_responses_are_handed_to_on_response() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		assert_eq!(ReceivedResponses::get(), vec![(0, b"pong".to_vec())]);
+
+		// Rejected responses are not handed on.
+		assert_noop!(
+			EdgeConnect::receive_response(RuntimeOrigin::signed(OCW), 0, response(b"again"), None),
+			Error::<Test>::CommandAlreadyCompleted
+		);
+
+		// Hooks get the whole body even when only its hash is kept on chain.
+		HashResponses::set(true);
+		dispatch(1);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			1,
+			response(b"pong too"),
+			None
+		));
+		assert_eq!(
+			ReceivedResponses::get(),
+			vec![(0, b"pong".to_vec()), (1, b"pong too".to_vec())]
+		);
+	});
+}
+
+#[test]
+fn hashed_responses_keep_the_body_off_chain() {
+	let mut t = new_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(1);
+		HashResponses::set(true);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+
+		assert_eq!(EdgeConnect::responses(0), None);
+		assert_eq!(
+			EdgeConnect::response_hash(0),
+			Some(ResponseHash {
+				hash: H256(blake2_256(b"pong")),
+				algorithm: HashAlgorithm::Blake2_256
+			})
+		);
+		// The event carries the same hash, for light clients to check the body against.
+		System::assert_last_event(
+			Event::ResponseReceived {
+				command_id: 0,
+				maybe_who: Some(OCW),
+				response_hash: EdgeConnect::response_hash(0).unwrap().hash,
+			}
+			.into(),
+		);
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Completed));
+	});
+
+	// Indexed data reaches the offchain database once the block is imported.
+	t.persist_offchain_overlay();
+	let (offchain, _state) = testing::TestOffchainExt::with_offchain_db(t.offchain_db());
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.execute_with(|| {
+		assert_eq!(EdgeConnect::response_body(H256(blake2_256(b"pong"))), Some(b"pong".to_vec()));
+		assert_eq!(EdgeConnect::response_body(H256(blake2_256(b"ping"))), None);
+	});
+}
+
+#[test]
+fn response_hashes_name_the_configured_hasher() {
+	let mut t = new_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(1);
+		HashResponses::set(true);
+		Sha256Responses::set(true);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+
+		let hash = H256(sha2_256(b"pong"));
+		assert_eq!(
+			EdgeConnect::response_hash(0),
+			Some(ResponseHash { hash, algorithm: HashAlgorithm::Sha256 })
+		);
+		System::assert_last_event(
+			Event::ResponseReceived { command_id: 0, maybe_who: Some(OCW), response_hash: hash }
+				.into(),
+		);
+	});
+
+	// The body is found under the hash it was stored with.
+	t.persist_offchain_overlay();
+	let (offchain, _state) = testing::TestOffchainExt::with_offchain_db(t.offchain_db());
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.execute_with(|| {
+		assert_eq!(EdgeConnect::response_body(H256(sha2_256(b"pong"))), Some(b"pong".to_vec()));
+	});
+}
+
+#[test]
+fn dispatches_are_logged_off_chain() {
+	let mut t = new_test_ext_with_connections(vec![
+		(ALICE, url(b"http://edge-a.example")),
+		(ALICE, url(b"http://edge-b.example")),
+	]);
+	t.execute_with(|| {
+		System::set_block_number(1);
+		for connection in [0, 0, 1, 1] {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(connection),
+				command(b"ping")
+			));
+		}
+		for (block, command_id) in [(2, 0), (2, 2), (3, 1), (5, 3)] {
+			System::set_block_number(block);
+			assert_ok!(EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				command_id,
+				CommandStatus::Dispatched
+			));
+		}
+		// Moving on without dispatching leaves the log alone.
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			3,
+			CommandStatus::AwaitingResponse
+		));
+	});
+
+	t.persist_offchain_overlay();
+	let (offchain, _state) = testing::TestOffchainExt::with_offchain_db(t.offchain_db());
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.execute_with(|| {
+		let logged = |from, to| {
+			EdgeConnect::dispatch_log(from, to)
+				.into_iter()
+				.map(|entry| (entry.block, entry.command_id, entry.connection.0))
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(logged(2, 3), vec![(2, 0, 0), (2, 2, 1), (3, 1, 0)]);
+		assert_eq!(logged(3, 10), vec![(3, 1, 0), (5, 3, 1)]);
+		assert_eq!(logged(4, 4), vec![]);
+		assert_eq!(logged(5, 1), vec![]);
+		assert_eq!(EdgeConnect::dispatch_log(5, 5)[0].payload_hash, H256(blake2_256(b"ping")));
+	});
+}
+
+#[test]
+fn receive_response_checks_the_server_signature() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let server = sr25519::Pair::from_seed(&[7; 32]);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::set_server_pubkey(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			Some(server.public()),
+			None
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let sign = |command_id, response: &[u8]| {
+			server.sign(&EdgeConnect::response_signing_payload(command_id, response))
+		};
+		assert_noop!(
+			EdgeConnect::receive_response(RuntimeOrigin::signed(OCW), 0, response(b"pong"), None),
+			Error::<Test>::InvalidResponseSignature
+		);
+		assert_noop!(
+			EdgeConnect::receive_response(
+				RuntimeOrigin::signed(OCW),
+				0,
+				response(b"pong"),
+				Some(sign(0, b"pang"))
+			),
+			Error::<Test>::InvalidResponseSignature
+		);
+		assert_noop!(
+			EdgeConnect::receive_response(
+				RuntimeOrigin::signed(OCW),
+				0,
+				response(b"pong"),
+				Some(
+					sr25519::Pair::from_seed(&[8; 32])
+						.sign(&EdgeConnect::response_signing_payload(0, b"pong"))
+				)
+			),
+			Error::<Test>::InvalidResponseSignature
+		);
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			Some(sign(0, b"pong"))
+		));
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Completed));
+	});
+}
+
+#[test]
+fn edge_server_can_submit_its_responses_directly() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let server = sr25519::Pair::from_seed(&[7; 32]).public();
+		let endpoints = [b"http://edge-a.example", b"http://edge-b.example"];
+		for (connection, endpoint) in endpoints.into_iter().enumerate() {
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(endpoint),
+				None,
+				None,
+				PollMode::Push
+			));
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(connection as u32),
+				command(b"ping")
+			));
+		}
+
+		// Without a registered key, the connection has no server origin.
+		assert_noop!(
+			EdgeConnect::submit_server_response(
+				RuntimeOrigin::signed(server),
+				0,
+				response(b"pong")
+			),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(EdgeConnect::set_server_pubkey(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			Some(server),
+			None
+		));
+		assert_noop!(
+			EdgeConnect::submit_server_response(RuntimeOrigin::signed(BOB), 0, response(b"pong")),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			EdgeConnect::submit_server_response(RuntimeOrigin::signed(ALICE), 0, response(b"pong")),
+			DispatchError::BadOrigin
+		);
+		// The key only answers for the commands of its own connection.
+		assert_noop!(
+			EdgeConnect::submit_server_response(
+				RuntimeOrigin::signed(server),
+				1,
+				response(b"pong")
+			),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			EdgeConnect::submit_server_response(
+				RuntimeOrigin::signed(server),
+				2,
+				response(b"pong")
+			),
+			Error::<Test>::UnknownCommandId
+		);
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::submit_server_response(
+			RuntimeOrigin::signed(server),
+			0,
+			response(b"pong")
+		));
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Completed));
+		System::assert_has_event(
+			Event::ResponseReceived {
+				command_id: 0,
+				maybe_who: Some(server),
+				response_hash: H256(blake2_256(b"pong")),
+			}
+			.into(),
+		);
+		assert_noop!(
+			EdgeConnect::submit_server_response(
+				RuntimeOrigin::signed(server),
+				0,
+				response(b"pong")
+			),
+			Error::<Test>::CommandAlreadyCompleted
+		);
+	});
+}
+
+#[test]
+fn receive_response_rejects_unknown_and_completed_commands() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EdgeConnect::receive_response(RuntimeOrigin::signed(OCW), 0, response(b"pong"), None),
+			Error::<Test>::UnknownCommandId
+		);
+
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+
+		assert_noop!(
+			EdgeConnect::receive_response(RuntimeOrigin::signed(OCW), 0, response(b"pong"), None),
+			Error::<Test>::CommandAlreadyCompleted
+		);
+	});
+}
+
+#[test]
+fn receive_response_requires_an_ocw_authority_and_a_dispatched_command() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// Nobody besides the offchain worker authorities can settle the command, not even its
+		// submitter.
+		assert_noop!(
+			EdgeConnect::receive_response(RuntimeOrigin::signed(ALICE), 0, response(b"pong"), None),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			EdgeConnect::receive_response(RuntimeOrigin::signed(OCW), 0, response(b"pong"), None),
+			Error::<Test>::CommandNotDispatched
+		);
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::AwaitingResponse
+		));
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Completed));
+	});
+}
+
+#[test]
+fn signed_payload_response_is_validated_and_stored() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let pair = sr25519::Pair::from_seed(&[7u8; 32]);
+		crate::OcwAuthorities::<Test>::insert(pair.public(), ());
+		let payload = ResponsePayload {
+			block_number: 1,
+			command_id: 0,
+			response: b"pong".to_vec(),
+			server_signature: None,
+			public: pair.public(),
+		};
+		let signature = payload.using_encoded(|bytes| pair.sign(bytes));
+
+		let call = Call::submit_response_unsigned_with_signed_payload {
+			response_payload: payload.clone(),
+			signature: signature.clone(),
+		};
+		assert!(EdgeConnect::validate_unsigned(TransactionSource::External, &call).is_ok());
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::submit_response_unsigned_with_signed_payload(
+			RuntimeOrigin::none(),
+			payload,
+			signature
+		));
+		assert_eq!(EdgeConnect::responses(0), Some(response(b"pong")));
+		assert_eq!(EdgeConnect::next_unsigned_at(), 1 + 128);
+		System::assert_last_event(
+			Event::ResponseReceived {
+				command_id: 0,
+				maybe_who: None,
+				response_hash: H256(blake2_256(b"pong")),
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn signed_payload_rejects_oversized_responses() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let max = <<Test as crate::Config>::MaxResponseLength as Get<u32>>::get() as usize;
+		let pair = sr25519::Pair::from_seed(&[7u8; 32]);
+		let payload = ResponsePayload {
+			block_number: 1,
+			command_id: 0,
+			response: vec![b'r'; max + 1],
+			server_signature: None,
+			public: pair.public(),
+		};
+		let signature = payload.using_encoded(|bytes| pair.sign(bytes));
+
+		assert_noop!(
+			EdgeConnect::submit_response_unsigned_with_signed_payload(
+				RuntimeOrigin::none(),
+				payload,
+				signature
+			),
+			Error::<Test>::ResponseTooLong
+		);
+	});
+}
+
+#[test]
+fn signed_payload_with_forged_signature_is_rejected() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let pair = sr25519::Pair::from_seed(&[7u8; 32]);
+		let forger = sr25519::Pair::from_seed(&[8u8; 32]);
+		let payload = ResponsePayload {
+			block_number: 1,
+			command_id: 0,
+			response: b"pong".to_vec(),
+			server_signature: None,
+			public: pair.public(),
+		};
+		let signature = payload.using_encoded(|bytes| forger.sign(bytes));
+
+		let call = Call::submit_response_unsigned_with_signed_payload {
+			response_payload: payload,
+			signature,
+		};
+		assert_eq!(
+			EdgeConnect::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::BadProof.into()
+		);
+	});
+}
+
+/// A response to `command_id` in a payload signed by `pair`, as the offchain worker would submit
+/// it at `block_number`.
+fn signed_response_call(pair: &sr25519::Pair, block_number: u64, command_id: u64) -> Call<Test> {
+	let payload = ResponsePayload {
+		block_number,
+		command_id,
+		response: b"pong".to_vec(),
+		server_signature: None,
+		public: pair.public(),
+	};
+	let signature = payload.using_encoded(|bytes| pair.sign(bytes));
+	Call::submit_response_unsigned_with_signed_payload { response_payload: payload, signature }
+}
+
+#[test]
+fn validate_unsigned_only_accepts_authorities_once_per_interval() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		let pair = sr25519::Pair::from_seed(&[7u8; 32]);
+
+		// A properly signed payload is still turned down unless its key is an authority.
+		let call = signed_response_call(&pair, 1, 0);
+		assert_eq!(
+			EdgeConnect::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::BadSigner.into()
+		);
+
+		crate::OcwAuthorities::<Test>::insert(pair.public(), ());
+		assert!(EdgeConnect::validate_unsigned(TransactionSource::External, &call).is_ok());
+		assert_eq!(
+			EdgeConnect::validate_unsigned(
+				TransactionSource::External,
+				&signed_response_call(&pair, 2, 0)
+			),
+			InvalidTransaction::Future.into()
+		);
+
+		// Once a response went in, the next one has to wait for `UnsignedInterval` blocks.
+		crate::NextUnsignedAt::<Test>::put(129);
+		System::set_block_number(128);
+		assert_eq!(
+			EdgeConnect::validate_unsigned(
+				TransactionSource::External,
+				&signed_response_call(&pair, 128, 0)
+			),
+			InvalidTransaction::Stale.into()
+		);
+		System::set_block_number(129);
+		assert!(EdgeConnect::validate_unsigned(
+			TransactionSource::External,
+			&signed_response_call(&pair, 129, 0)
+		)
+		.is_ok());
+	});
+}
+
+#[test]
+fn validate_unsigned_rejects_settled_and_unknown_commands() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let pair = sr25519::Pair::from_seed(&[7u8; 32]);
+		crate::OcwAuthorities::<Test>::insert(pair.public(), ());
+		let call = signed_response_call(&pair, 1, 0);
+		assert_eq!(
+			EdgeConnect::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Call.into()
+		);
+
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		let valid = EdgeConnect::validate_unsigned(TransactionSource::External, &call).unwrap();
+		assert_eq!(valid.provides, vec![("ocw-edge:", (b"submit_response", 0u64)).encode()]);
+
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		assert_eq!(
+			EdgeConnect::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
+	});
+}
+
+#[test]
+fn update_command_status_follows_lifecycle() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Dispatched
+		));
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::AwaitingResponse
+		));
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::AwaitingResponse));
+		System::assert_last_event(
+			Event::CommandStatusChanged { command_id: 0, status: CommandStatus::AwaitingResponse }
+				.into(),
+		);
+
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::TimedOut
+		));
+		assert_noop!(
+			EdgeConnect::receive_response(RuntimeOrigin::signed(OCW), 0, response(b"pong"), None),
+			Error::<Test>::CommandAlreadyCompleted
+		);
+	});
+}
+
+#[test]
+fn update_command_status_requires_an_ocw_authority() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		for who in [ALICE, BOB] {
+			for status in [CommandStatus::Dispatched, CommandStatus::Failed] {
+				assert_noop!(
+					EdgeConnect::update_command_status(RuntimeOrigin::signed(who), 0, status),
+					DispatchError::BadOrigin
+				);
+			}
+		}
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Queued));
+
+		// Authorities removed by governance can no longer report either.
+		assert_ok!(EdgeConnect::remove_ocw_authority(RuntimeOrigin::root(), OCW));
+		assert_noop!(
+			EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				0,
+				CommandStatus::Dispatched
+			),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn update_command_status_rejects_invalid_transitions() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				0,
+				CommandStatus::Dispatched
+			),
+			Error::<Test>::UnknownCommandId
+		);
+
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// Responses are the only way to complete a command.
+		assert_noop!(
+			EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				0,
+				CommandStatus::Completed
+			),
+			Error::<Test>::InvalidStatusTransition
+		);
+		// Commands cannot skip ahead or move back.
+		assert_noop!(
+			EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				0,
+				CommandStatus::AwaitingResponse
+			),
+			Error::<Test>::InvalidStatusTransition
+		);
+		assert_noop!(
+			EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				0,
+				CommandStatus::Queued
+			),
+			Error::<Test>::InvalidStatusTransition
+		);
+	});
+}
+
+#[test]
+fn commands_expire_once_their_validity_passed() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(5);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_noop!(
+			EdgeConnect::send_raw_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping"),
+				SendOptions { valid_until: Some(4), ..Default::default() }
+			),
+			Error::<Test>::CommandAlreadyExpired.with_weight(rejected())
+		);
+		assert_ok!(EdgeConnect::send_raw_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping"),
+			SendOptions { valid_until: Some(5), ..Default::default() }
+		));
+		assert_ok!(EdgeConnect::send_raw_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"pong"),
+			SendOptions { valid_until: Some(5), ..Default::default() }
+		));
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(0))[0].valid_until, Some(5));
+
+		// The command may still be dispatched in its last valid block.
+		assert_noop!(
+			EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				0,
+				CommandStatus::Expired
+			),
+			Error::<Test>::InvalidStatusTransition
+		);
+
+		System::set_block_number(6);
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			1,
+			CommandStatus::Dispatched
+		));
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Expired
+		));
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Expired));
+		System::assert_last_event(Event::CommandExpired { command_id: 0 }.into());
+
+		// Commands that already left for CyberHub are past expiring.
+		assert_noop!(
+			EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				1,
+				CommandStatus::Expired
+			),
+			Error::<Test>::InvalidStatusTransition
+		);
+	});
+}
+
+#[test]
+fn transaction_type_follows_the_submit_strategy() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let keystore = MemoryKeystore::new();
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+
+	t.execute_with(|| {
+		// Nothing is sent without a key to sign with.
+		assert_eq!(EdgeConnect::choose_transaction_type(4), TransactionType::None);
+	});
+
+	keystore.sr25519_generate_new(crate::KEY_TYPE, None).unwrap();
+	t.register_extension(KeystoreExt::new(keystore));
+	t.execute_with(|| {
+		assert_eq!(EdgeConnect::choose_transaction_type(4), TransactionType::Signed);
+		// `GracePeriod` is five blocks.
+		assert_eq!(EdgeConnect::choose_transaction_type(8), TransactionType::None);
+		OcwSubmitStrategy::set(SubmitStrategy::UnsignedForAny);
+		assert_eq!(EdgeConnect::choose_transaction_type(9), TransactionType::UnsignedForAny);
+		OcwSubmitStrategy::set(SubmitStrategy::UnsignedForAll);
+		assert_eq!(EdgeConnect::choose_transaction_type(14), TransactionType::UnsignedForAll);
+	});
+}
+
+#[test]
+fn fetch_from_cyberhub_posts_payload() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+
+	state.write().expect_request(testing::PendingRequest {
+		method: "POST".into(),
+		uri: "http://edge.example".into(),
+		body: b"ping".to_vec(),
+		response: Some(b"pong".to_vec()),
+		sent: true,
+		..Default::default()
+	});
+
+	t.execute_with(|| {
+		assert_eq!(
+			EdgeConnect::fetch_from_cyberhub("http://edge.example", Some(b"ping")),
+			Ok(Some(b"pong".to_vec()))
+		);
+	});
+}
+
+#[test]
+fn requests_are_abandoned_after_the_configured_timeout() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+
+	t.execute_with(|| {
+		state.write().timestamp = Timestamp::from_unix_millis(1_000);
+		assert_eq!(EdgeConnect::request_deadline(), Timestamp::from_unix_millis(4_000));
+
+		HttpRequestTimeout::set(500);
+		assert_eq!(EdgeConnect::request_deadline(), Timestamp::from_unix_millis(1_500));
+	});
+}
+
+#[test]
+fn requests_carry_the_command_id() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"say \"hi\"")
+		));
+
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		assert_eq!(
+			EdgeConnect::encode_request(&command),
+			br#"{"command_id":0,"nonce":0,"command":"say \"hi\""}"#.to_vec()
+		);
+	});
+}
+
+#[test]
+fn decode_response_checks_the_echoed_command_id() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			EdgeConnect::decode_response(7, br#"{"command_id":7,"status":"ok","body":"pong"}"#),
+			Ok(CommandOutcome::Ok(b"pong".to_vec(), None))
+		);
+		assert_eq!(
+			EdgeConnect::decode_response(7, br#"{"command_id":8,"status":"ok","body":"pong"}"#),
+			Err("CyberHub response does not match the command")
+		);
+		assert_eq!(
+			EdgeConnect::decode_response(7, br#"{"command_id":7,"status":"error","body":"busy"}"#),
+			Ok(CommandOutcome::Error(b"busy".to_vec()))
+		);
+		// Fields CyberHub adds later are ignored, escaped strings are unescaped.
+		assert_eq!(
+			EdgeConnect::decode_response(
+				7,
+				br#"{"command_id":7,"status":"ok","body":"say \"hi\"","took_ms":3}"#
+			),
+			Ok(CommandOutcome::Ok(br#"say "hi""#.to_vec(), None))
+		);
+		for malformed in [
+			&br#"{"status":"ok","body":"pong"}"#[..],
+			br#"{"command_id":7,"status":"ok"}"#,
+			br#"{"command_id":7,"status":"done","body":"pong"}"#,
+			b"pong",
+		] {
+			assert_eq!(
+				EdgeConnect::decode_response(7, malformed),
+				Err("CyberHub response is not a valid envelope")
+			);
+		}
+	});
+}
+
+#[test]
+fn decode_response_reads_server_signatures() {
+	new_test_ext().execute_with(|| {
+		let signature = sr25519::Signature([7; 64]);
+		let body = format!(
+			r#"{{"command_id":7,"status":"ok","body":"pong","signature":"0x{}"}}"#,
+			HexDisplay::from(&signature.0)
+		);
+		assert_eq!(
+			EdgeConnect::decode_response(7, body.as_bytes()),
+			Ok(CommandOutcome::Ok(b"pong".to_vec(), Some(signature)))
+		);
+		for malformed in [
+			&br#"{"command_id":7,"status":"ok","body":"pong","signature":"0x0707"}"#[..],
+			br#"{"command_id":7,"status":"ok","body":"pong","signature":"not hex"}"#,
+		] {
+			assert_eq!(
+				EdgeConnect::decode_response(7, malformed),
+				Err("CyberHub response has a malformed signature")
+			);
+		}
+	});
+}
+
+#[test]
+fn wire_messages_round_trip() {
+	let request = wire::CommandRequest {
+		command_id: 7,
+		nonce: 3,
+		command: "say \"hi\"\n".into(),
+		schema_hash: None,
+		ack_only: false,
+	};
+	let json = wire::to_json(&request);
+	assert_eq!(json, br#"{"command_id":7,"nonce":3,"command":"say \"hi\"\n"}"#.to_vec());
+	assert_eq!(wire::from_json::<wire::CommandRequest>(&json), Ok(request));
+
+	let response = wire::CommandResponse {
+		command_id: 7,
+		status: wire::ResponseStatus::Error,
+		body: "busy".repeat(100).into(),
+		signature: None,
+	};
+	let json = wire::to_json(&response);
+	assert_eq!(wire::from_json::<wire::CommandResponse>(&json), Ok(response));
+}
+
+#[test]
+fn offchain_worker_skips_locked_connections() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// Another worker is busy with the connection, so this run leaves it alone.
+		let key = EdgeConnect::lock_key(ConnectionId(0));
+		let mut lock = StorageLock::<Time>::new(&key);
+		let guard = lock.try_lock().unwrap();
+		EdgeConnect::offchain_worker(1);
+		assert!(pool_state.read().transactions.is_empty());
+
+		// Once the lock is released the command is picked up.
+		drop(guard);
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(vec![]),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(6);
+		assert_eq!(pool_state.read().transactions.len(), 1);
+	});
+}
+
+#[test]
+fn offchain_worker_expires_commands_instead_of_sending_them() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore.sr25519_generate_new(crate::KEY_TYPE, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_raw_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping"),
+			SendOptions { valid_until: Some(2), ..Default::default() }
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"pong")
+		));
+
+		// Only the command without an expiry goes out.
+		let command = EdgeConnect::command_queue(ConnectionId(0))[1].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(vec![]),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(4);
+
+		let calls: Vec<_> = pool_state
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.collect();
+		assert_eq!(
+			calls,
+			vec![
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::Expired,
+				}),
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 1,
+					status: CommandStatus::Dispatched,
+				}),
+			]
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_holds_commands_back_while_in_flight() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore.sr25519_generate_new(crate::KEY_TYPE, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		for payload in [b"ping", b"pong"] {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(payload)
+			));
+		}
+		for status in [CommandStatus::Dispatched, CommandStatus::AwaitingResponse] {
+			assert_ok!(EdgeConnect::update_command_status(RuntimeOrigin::signed(OCW), 0, status));
+		}
+
+		// The edge server is still busy with the first command.
+		MaxInFlightPerConnection::set(1);
+		EdgeConnect::offchain_worker(4);
+		assert!(pool_state.read().transactions.is_empty());
+
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		let command = EdgeConnect::command_queue(ConnectionId(0))[1].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(vec![]),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(9);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert_eq!(
+			Extrinsic::decode(&mut &*tx).unwrap().call,
+			RuntimeCall::EdgeConnect(Call::update_command_status {
+				command_id: 1,
+				status: CommandStatus::Dispatched,
+			})
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_drains_long_polled_responses() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore.sr25519_generate_new(crate::KEY_TYPE, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::LongPoll
+		));
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0)).unwrap().poll_mode,
+			PollMode::LongPoll
+		);
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		let drained = |responses: &[wire::CommandResponse]| testing::PendingRequest {
+			method: "GET".into(),
+			uri: "http://edge.example/responses".into(),
+			response: Some(if responses.is_empty() {
+				Vec::new()
+			} else {
+				wire::to_json(responses)
+			}),
+			sent: true,
+			..Default::default()
+		};
+
+		// The command is sent once, whatever the edge server answers is not its result.
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(b"accepted".to_vec()),
+			sent: true,
+			..Default::default()
+		});
+		state.write().expect_request(drained(&[]));
+		EdgeConnect::offchain_worker(4);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		assert_eq!(
+			Extrinsic::decode(&mut &*tx).unwrap().call,
+			RuntimeCall::EdgeConnect(Call::update_command_status {
+				command_id: 0,
+				status: CommandStatus::Dispatched,
+			})
+		);
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Dispatched
+		));
+
+		// From then on it awaits its result, which is drained along with results for other
+		// commands that are dropped.
+		let result = |command_id| wire::CommandResponse {
+			command_id,
+			status: wire::ResponseStatus::Ok,
+			body: "pong".into(),
+			signature: None,
+		};
+		state.write().expect_request(drained(&[result(7), result(0)]));
+		EdgeConnect::offchain_worker(9);
+
+		let calls: Vec<_> = pool_state
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.collect();
+		assert_eq!(
+			calls,
+			vec![
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::AwaitingResponse,
+				}),
+				RuntimeCall::EdgeConnect(Call::receive_response {
+					command_id: 0,
+					response: response(b"pong"),
+					server_signature: None,
+				}),
+			]
+		);
+	});
+}
+
+#[test]
+fn update_endpoint_switches_the_poll_mode() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let poll_mode = || EdgeConnect::connection_info(ConnectionId(0)).unwrap().poll_mode;
+
+		assert_ok!(EdgeConnect::update_endpoint(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			url(b"http://edge-b.example"),
+			None,
+			None
+		));
+		assert_eq!(poll_mode(), PollMode::Push);
+
+		assert_ok!(EdgeConnect::update_endpoint(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			url(b"http://edge-c.example"),
+			Some(PollMode::LongPoll),
+			None
+		));
+		assert_eq!(poll_mode(), PollMode::LongPoll);
+	});
+}
+
+#[test]
+fn offchain_worker_does_not_resend_answered_commands() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Dispatched
+		));
+
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(br#"{"command_id":0,"status":"ok","body":"pong"}"#.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(1);
+		assert_eq!(pool_state.read().transactions.len(), 1);
+		assert_eq!(EdgeConnect::dispatched_watermark(ConnectionId(0)), Some(0));
+
+		// The response has not landed yet, CyberHub is not asked a second time.
+		EdgeConnect::offchain_worker(6);
+		assert!(state.read().requests.is_empty());
+		assert_eq!(pool_state.read().transactions.len(), 1);
+
+		// Once the command is completed on chain the watermark is dropped.
+		System::set_block_number(11);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		EdgeConnect::offchain_worker(11);
+		assert_eq!(EdgeConnect::dispatched_watermark(ConnectionId(0)), None);
+	});
+}
+
+#[test]
+fn signed_responses_are_not_fetched_twice() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(4);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Dispatched
+		));
+
+		// Block 4 goes for a signed transaction.
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(br#"{"command_id":0,"status":"ok","body":"pong"}"#.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(4);
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert!(tx.signature.is_some());
+		assert_eq!(
+			tx.call,
+			RuntimeCall::EdgeConnect(Call::receive_response {
+				command_id: 0,
+				response: response(b"pong"),
+				server_signature: None,
+			})
+		);
+		assert_eq!(EdgeConnect::dispatched_watermark(ConnectionId(0)), Some(0));
+
+		// So does block 12, the response has not landed yet and CyberHub is left alone.
+		EdgeConnect::offchain_worker(12);
+		assert!(state.read().requests.is_empty());
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+#[test]
+fn failed_requests_are_retried_with_backoff() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+
+	t.execute_with(|| {
+		System::set_block_number(10);
+		assert!(EdgeConnect::retry_due(0, 10));
+
+		// The first failure holds the command off for two blocks, the next one for four.
+		assert!(EdgeConnect::schedule_retry(0));
+		assert_eq!(EdgeConnect::retry_state(0), Some((1, 12)));
+		assert!(!EdgeConnect::retry_due(0, 11));
+		assert!(EdgeConnect::retry_due(0, 12));
+
+		System::set_block_number(12);
+		assert!(EdgeConnect::schedule_retry(0));
+		assert_eq!(EdgeConnect::retry_state(0), Some((2, 16)));
+
+		// `MaxCommandRetries` is two, the third failure gives up on the command.
+		System::set_block_number(16);
+		assert!(!EdgeConnect::schedule_retry(0));
+		assert_eq!(EdgeConnect::retry_state(0), None);
+		assert!(EdgeConnect::retry_due(0, 16));
+	});
+}
+
+#[test]
+fn cyberhub_answers_are_submitted_as_responses() {
+	let (mut t, hub) = new_offchain_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		hub.expect_command(
+			"http://edge.example",
+			&command,
+			200,
+			br#"{"command_id":0,"status":"ok","body":"pong"}"#,
+		);
+		EdgeConnect::offchain_worker(1);
+		hub.assert_requests_made();
+
+		let calls: Vec<_> = hub.submitted().into_iter().map(|tx| tx.call).collect();
+		assert_eq!(
+			calls,
+			vec![
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::Dispatched,
+				}),
+				RuntimeCall::EdgeConnect(Call::receive_response {
+					command_id: 0,
+					response: response(b"pong"),
+					server_signature: None,
+				}),
+			]
+		);
+		for call in calls {
+			assert_ok!(call.dispatch(RuntimeOrigin::signed(OCW)));
+		}
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Completed));
+	});
+}
+
+#[test]
+fn paused_offchain_worker_submits_nothing() {
+	let (mut t, hub) = new_offchain_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		assert_noop!(
+			EdgeConnect::set_ocw_paused(RuntimeOrigin::signed(ALICE), true),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(EdgeConnect::set_ocw_paused(RuntimeOrigin::root(), true));
+		assert!(EdgeConnect::ocw_paused());
+		System::assert_last_event(Event::OcwPauseToggled { paused: true }.into());
+
+		// No request is expected, so any would fail the test.
+		EdgeConnect::offchain_worker(1);
+		hub.assert_requests_made();
+		assert!(hub.submitted().is_empty());
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Queued));
+
+		// Once started again, the queued command is delivered.
+		assert_ok!(EdgeConnect::set_ocw_paused(RuntimeOrigin::root(), false));
+		System::assert_last_event(Event::OcwPauseToggled { paused: false }.into());
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		hub.expect_command(
+			"http://edge.example",
+			&command,
+			200,
+			br#"{"command_id":0,"status":"ok","body":"pong"}"#,
+		);
+		EdgeConnect::offchain_worker(1);
+		hub.assert_requests_made();
+		assert!(!hub.submitted().is_empty());
+	});
+}
+
+#[test]
+fn ack_only_commands_are_acknowledged_instead_of_answered() {
+	let (mut t, hub) = new_offchain_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		for ack_only in [true, false] {
+			assert_ok!(EdgeConnect::send_raw_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"reboot"),
+				SendOptions { ack_only, ..Default::default() }
+			));
+		}
+		let queue = EdgeConnect::command_queue(ConnectionId(0));
+		assert!(queue[0].ack_only && !queue[1].ack_only);
+
+		// Commands sent `ack_only` never await a response.
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Dispatched
+		));
+		assert_noop!(
+			EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				0,
+				CommandStatus::AwaitingResponse
+			),
+			Error::<Test>::InvalidStatusTransition
+		);
+
+		// A `202 Accepted` acknowledges them, other commands keep waiting on their result
+		// whatever CyberHub says.
+		hub.expect_command("http://edge.example", &queue[0], 202, b"");
+		hub.expect_command(
+			"http://edge.example",
+			&queue[1],
+			200,
+			br#"{"command_id":1,"status":"ack","body":""}"#,
+		);
+		EdgeConnect::offchain_worker(1);
+		hub.assert_requests_made();
+
+		let calls: Vec<_> = hub.submitted().into_iter().map(|tx| tx.call).collect();
+		assert_eq!(
+			calls,
+			vec![
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::Acknowledged,
+				}),
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 1,
+					status: CommandStatus::Dispatched,
+				}),
+			]
+		);
+		for call in calls {
+			assert_ok!(call.dispatch(RuntimeOrigin::signed(OCW)));
+		}
+		System::assert_has_event(Event::CommandAcknowledged { command_id: 0 }.into());
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Acknowledged));
+		assert_eq!(EdgeConnect::in_flight_commands(ConnectionId(0)), 0);
+
+		assert_noop!(
+			EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				1,
+				CommandStatus::Acknowledged
+			),
+			Error::<Test>::InvalidStatusTransition
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_only_runs_on_validators() {
+	let (mut t, hub) = new_offchain_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// Other nodes leave the command to the validators.
+		hub.set_validator(false);
+		EdgeConnect::offchain_worker(1);
+		assert!(hub.submitted().is_empty());
+
+		// Unless they are asked to take part too.
+		OcwRunOnNonAuthorities::set(true);
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		hub.expect_command(
+			"http://edge.example",
+			&command,
+			200,
+			br#"{"command_id":0,"status":"ok","body":"pong"}"#,
+		);
+		EdgeConnect::offchain_worker(1);
+		hub.assert_requests_made();
+		assert_eq!(hub.submitted().len(), 2);
+	});
+}
+
+#[test]
+fn cyberhub_errors_are_retried() {
+	let (mut t, hub) = new_offchain_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// A server error answers nothing, the command is held off for two blocks.
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		hub.expect_command("http://edge.example", &command, 500, b"");
+		EdgeConnect::offchain_worker(1);
+		hub.assert_requests_made();
+		assert!(hub.submitted().is_empty());
+		assert_eq!(EdgeConnect::retry_state(0), Some((1, 3)));
+
+		// The next run after the grace period sends it again, and this time it is answered.
+		hub.expect_command(
+			"http://edge.example",
+			&command,
+			200,
+			br#"{"command_id":0,"status":"ok","body":"pong"}"#,
+		);
+		EdgeConnect::offchain_worker(6);
+		hub.assert_requests_made();
+		let answer = RuntimeCall::EdgeConnect(Call::receive_response {
+			command_id: 0,
+			response: response(b"pong"),
+			server_signature: None,
+		});
+		assert!(hub.submitted().into_iter().any(|tx| tx.call == answer));
+		assert_eq!(EdgeConnect::retry_state(0), None);
+	});
+}
+
+#[test]
+fn payloads_are_encoded_for_transport_only() {
+	let (mut t, hub) = new_offchain_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(1);
+		SealPayloads::set(true);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::set_server_pubkey(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			Some(sr25519::Public([7; 32])),
+			None
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// CyberHub gets the sealed payload, the chain keeps the one submitted.
+		let sealed = format!("{}:gnip", "07".repeat(32));
+		let request = wire::CommandRequest {
+			command_id: 0,
+			nonce: 0,
+			command: sealed.into(),
+			schema_hash: None,
+			ack_only: false,
+		};
+		hub.expect("POST", "http://edge.example", wire::to_json(&request), 200, b"");
+		EdgeConnect::offchain_worker(1);
+		hub.assert_requests_made();
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(0))[0].payload, command(b"ping"));
+	});
+}
+
+#[test]
+fn template_schemas_go_along_with_their_commands() {
+	let (mut t, hub) = new_offchain_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let schema_hash = H256::repeat_byte(0xab);
+		assert_ok!(EdgeConnect::register_template(
+			RuntimeOrigin::signed(ALICE),
+			command(b"ping").try_into().unwrap(),
+			Some(schema_hash)
+		));
+		assert_ok!(EdgeConnect::send_command_from_template(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			0,
+			Default::default()
+		));
+		assert_eq!(EdgeConnect::command_schema(0), Some(schema_hash));
+
+		// CyberHub is told which shape the response should have.
+		let body = format!(
+			r#"{{"command_id":0,"nonce":0,"command":"ping","schema_hash":"0x{}"}}"#,
+			"ab".repeat(32)
+		);
+		hub.expect("POST", "http://edge.example", body.into_bytes(), 200, b"");
+		EdgeConnect::offchain_worker(1);
+		hub.assert_requests_made();
+
+		// The schema is forgotten with the command.
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 0));
+		assert_eq!(EdgeConnect::command_schema(0), None);
+	});
+}
+
+#[test]
+fn failing_a_command_emits_command_failed() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Failed
+		));
+		System::assert_has_event(
+			Event::CommandStatusChanged { command_id: 0, status: CommandStatus::Failed }.into(),
+		);
+		System::assert_last_event(Event::CommandFailed { command_id: 0 }.into());
+	});
+}
+
+#[test]
+fn unanswered_commands_time_out() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		System::set_block_number(2);
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Dispatched
+		));
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::AwaitingResponse
+		));
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(0))[0].dispatched_at, Some(2));
+
+		// `CommandTimeout` is ten blocks.
+		System::set_block_number(12);
+		EdgeConnect::on_initialize(12);
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::AwaitingResponse));
+
+		System::set_block_number(13);
+		EdgeConnect::on_initialize(13);
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::TimedOut));
+		System::assert_last_event(Event::CommandTimedOut { command_id: 0 }.into());
+	});
+}
+
+#[test]
+fn timeouts_move_to_the_next_block_once_one_is_full() {
+	new_test_ext_with_connections(vec![(ALICE, url(b"http://edge.example"))]).execute_with(|| {
+		System::set_block_number(2);
+		for command_id in 0..2 {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			));
+			dispatch(command_id);
+			assert_ok!(EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				command_id,
+				CommandStatus::AwaitingResponse
+			));
+		}
+		// `MaxPrunePerBlock` is one.
+		assert_eq!(crate::CommandDeadlines::<Test>::get(13).into_inner(), vec![0]);
+		assert_eq!(crate::CommandDeadlines::<Test>::get(14).into_inner(), vec![1]);
+
+		System::set_block_number(13);
+		EdgeConnect::on_initialize(13);
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::TimedOut));
+		assert_eq!(EdgeConnect::command_status(1), Some(CommandStatus::AwaitingResponse));
+		assert!(!crate::CommandDeadlines::<Test>::contains_key(13));
+
+		System::set_block_number(14);
+		EdgeConnect::on_initialize(14);
+		assert_eq!(EdgeConnect::command_status(1), Some(CommandStatus::TimedOut));
+	});
+}
+
+#[test]
+fn settled_commands_are_pruned_after_retention() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		for _ in 0..2 {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			));
+		}
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			1,
+			CommandStatus::Failed
+		));
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(0))[0].settled_at, Some(1));
+
+		// `CommandRetentionBlocks` is five.
+		EdgeConnect::on_initialize(6);
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(0)).len(), 2);
+
+		// Only `MaxPrunePerBlock` commands go at a time.
+		EdgeConnect::on_initialize(7);
+		let queue = EdgeConnect::command_queue(ConnectionId(0));
+		assert_eq!(queue.iter().map(|command| command.id).collect::<Vec<_>>(), vec![1]);
+		assert_eq!(EdgeConnect::command_status(0), None);
+		assert_eq!(EdgeConnect::responses(0), None);
+
+		EdgeConnect::on_initialize(8);
+		assert!(EdgeConnect::command_queue(ConnectionId(0)).is_empty());
+		assert_eq!(EdgeConnect::command_status(1), None);
+	});
+}
+
+#[test]
+fn commands_retried_since_they_settled_are_not_pruned() {
+	new_test_ext_with_connections(vec![(ALICE, url(b"http://edge.example"))]).execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Failed
+		));
+		assert_eq!(crate::CommandPrunes::<Test>::get(7).into_inner(), vec![0]);
+		assert_ok!(EdgeConnect::retry_command(RuntimeOrigin::signed(ALICE), 0));
+
+		System::set_block_number(7);
+		EdgeConnect::on_initialize(7);
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Queued));
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(0)).len(), 1);
+		assert!(!crate::CommandPrunes::<Test>::contains_key(7));
+	});
+}
+
+#[test]
+fn commands_of_lists_the_history_of_a_connection() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		for edge in [&b"http://edge-a.example"[..], b"http://edge-b.example"] {
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(edge),
+				None,
+				None,
+				PollMode::Push
+			));
+		}
+		for connection in [0, 1, 0] {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(connection),
+				command(b"ping")
+			));
+		}
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+
+		assert_eq!(
+			EdgeConnect::commands_of(ConnectionId(0), None, 10),
+			vec![(0, CommandStatus::Completed), (2, CommandStatus::Queued)]
+		);
+		assert_eq!(
+			EdgeConnect::commands_of(ConnectionId(1), None, 10),
+			vec![(1, CommandStatus::Queued)]
+		);
+
+		// Pages hold at least one command and go on after the last command of the previous one.
+		assert_eq!(
+			EdgeConnect::commands_of(ConnectionId(0), None, 0),
+			vec![(0, CommandStatus::Completed)]
+		);
+		assert_eq!(
+			EdgeConnect::commands_of(ConnectionId(0), Some(0), 1),
+			vec![(2, CommandStatus::Queued)]
+		);
+		assert!(EdgeConnect::commands_of(ConnectionId(0), Some(2), 1).is_empty());
+
+		// Commands leave the history once pruned.
+		EdgeConnect::on_initialize(7);
+		assert_eq!(
+			EdgeConnect::commands_of(ConnectionId(0), None, 10),
+			vec![(2, CommandStatus::Queued)]
+		);
+		assert_ok!(EdgeConnect::do_try_state());
+	});
+}
+
+#[test]
+fn connection_details_of_is_paginated() {
+	new_test_ext().execute_with(|| {
+		for endpoint in
+			[&b"http://edge-a.example"[..], b"http://edge-b.example", b"http://edge-c.example"]
+		{
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(endpoint),
+				None,
+				None,
+				PollMode::Push
+			));
+		}
+		let ids = |page: &ConnectionsPage<_, _>| {
+			page.connections.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+		};
+
+		let page = EdgeConnect::connection_details_of(ALICE, None, 2);
+		assert_eq!(ids(&page), vec![ConnectionId(0), ConnectionId(1)]);
+		assert_eq!(page.next, Some(ConnectionId(1)));
+
+		// Removing a listed connection does not shift the next page.
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		let page = EdgeConnect::connection_details_of(ALICE, page.next, 2);
+		assert_eq!(ids(&page), vec![ConnectionId(2)]);
+		assert_eq!(page.next, None);
+
+		// Pages are never empty while there are connections left, nor larger than the cap.
+		let page = EdgeConnect::connection_details_of(ALICE, None, 0);
+		assert_eq!(ids(&page), vec![ConnectionId(1)]);
+		assert_eq!(page.next, Some(ConnectionId(1)));
+		assert_eq!(EdgeConnect::connection_details_of(ALICE, None, u32::MAX).connections.len(), 2);
+	});
+}
+
+#[test]
+fn connection_details_of_lists_owned_connections() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(3);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			Some(b"lab".to_vec()),
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(BOB),
+			url(b"http://edge-b.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_eq!(
+			EdgeConnect::connection_details_of(ALICE, None, 10),
+			ConnectionsPage {
+				connections: vec![(
+					ConnectionId(0),
+					ConnectionDetails {
+						endpoints: vec![url(b"http://edge-a.example")],
+						label: Some(b"lab".to_vec()),
+						owner: ALICE,
+						created_at: 3,
+						last_seen: 3,
+						status: ConnectionStatus::Active,
+						tags: Vec::new(),
+						server_pubkey: None,
+						active_endpoint: 0,
+						last_lifecycle_block: 3,
+						expires_at: None,
+						generation: 0,
+						poll_mode: PollMode::Push,
+						version: 0,
+						in_flight: 0,
+						health: ConnectionHealth::Healthy,
+					}
+				)],
+				next: None,
+			}
+		);
+		assert!(EdgeConnect::connection_details_of(sr25519::Public([3u8; 32]), None, 10)
+			.connections
+			.is_empty());
+	});
+}
+
+#[test]
+fn offchain_worker_reports_dispatched_commands() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(vec![]),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(1);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert!(tx.signature.is_some());
+		assert_eq!(
+			tx.call,
+			RuntimeCall::EdgeConnect(Call::update_command_status {
+				command_id: 0,
+				status: CommandStatus::Dispatched,
+			})
+		);
+
+		assert_ok!(tx.call.dispatch(RuntimeOrigin::signed(OCW)));
+		System::assert_last_event(
+			Event::CommandDispatched { command_id: 0, connection: ConnectionId(0) }.into(),
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_fails_commands_with_oversized_responses() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let max = <<Test as crate::Config>::MaxResponseLength as Get<u32>>::get() as usize;
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(
+				format!(r#"{{"command_id":0,"status":"ok","body":"{}"}}"#, "r".repeat(max + 1))
+					.into_bytes(),
+			),
+			sent: true,
+			..Default::default()
+		});
+
+		assert_eq!(
+			EdgeConnect::fetch_response(&["http://edge.example"], &command),
+			Err("CyberHub response is too long")
+		);
+
+		// The command is reported as dispatched, then failed, and no response is submitted.
+		let calls: Vec<_> = pool_state
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.collect();
+		assert_eq!(
+			calls,
+			vec![
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::Dispatched,
+				}),
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::Failed,
+				}),
+			]
+		);
+	});
+}
+
+#[test]
+fn fetch_response_returns_ok_envelopes() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(br#"{"command_id":0,"status":"ok","body":"pong"}"#.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+
+		assert_eq!(
+			EdgeConnect::fetch_response(&["http://edge.example"], &command),
+			Ok(Some((response(b"pong"), None)))
+		);
+
+		// Only the dispatch is reported, the response is left for the caller to submit.
+		let calls: Vec<_> = pool_state
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.collect();
+		assert_eq!(
+			calls,
+			vec![RuntimeCall::EdgeConnect(Call::update_command_status {
+				command_id: 0,
+				status: CommandStatus::Dispatched,
+			})]
+		);
+	});
+}
+
+#[test]
+fn signed_responses_carry_the_server_signature() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		let server = sr25519::Pair::from_seed(&[7; 32]);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::set_server_pubkey(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			Some(server.public()),
+			None
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		let signature = server.sign(&EdgeConnect::response_signing_payload(0, b"pong"));
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(
+				format!(
+					r#"{{"command_id":0,"status":"ok","body":"pong","signature":"{}"}}"#,
+					HexDisplay::from(&signature.0)
+				)
+				.into_bytes(),
+			),
+			sent: true,
+			..Default::default()
+		});
+
+		assert_ok!(EdgeConnect::fetch_response_and_send_signed(|| {
+			EdgeConnect::fetch_responses(&["http://edge.example"], &[command])
+		}));
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let call = Extrinsic::decode(&mut &*tx).unwrap().call;
+		assert_eq!(
+			call,
+			RuntimeCall::EdgeConnect(Call::receive_response {
+				command_id: 0,
+				response: response(b"pong"),
+				server_signature: Some(signature),
+			})
+		);
+		dispatch(0);
+		assert_ok!(call.dispatch(RuntimeOrigin::signed(OCW)));
+	});
+}
+
+/// Has the offchain worker deliver a `ping` under `strategy`, returning the transaction it
+/// submitted CyberHub's answer with.
+fn submitted_response(strategy: SubmitStrategy) -> Extrinsic {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore.sr25519_generate_new(crate::KEY_TYPE, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		OcwSubmitStrategy::set(strategy);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(br#"{"command_id":0,"status":"ok","body":"pong"}"#.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(4);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		Extrinsic::decode(&mut &*tx).unwrap()
+	})
+}
+
+#[test]
+fn signed_strategy_submits_signed_responses() {
+	let tx = submitted_response(SubmitStrategy::Signed);
+	assert!(tx.signature.is_some());
+	assert_eq!(
+		tx.call,
+		RuntimeCall::EdgeConnect(Call::receive_response {
+			command_id: 0,
+			response: response(b"pong"),
+			server_signature: None,
+		})
+	);
+}
+
+#[test]
+fn unsigned_for_any_strategy_submits_signed_payloads() {
+	let tx = submitted_response(SubmitStrategy::UnsignedForAny);
+	assert!(tx.signature.is_none());
+	assert!(matches!(
+		tx.call,
+		RuntimeCall::EdgeConnect(Call::submit_response_unsigned_with_signed_payload {
+			response_payload: ResponsePayload { command_id: 0, .. },
+			..
+		})
+	));
+}
+
+#[test]
+fn unsigned_for_all_strategy_submits_signed_payloads() {
+	let tx = submitted_response(SubmitStrategy::UnsignedForAll);
+	assert!(tx.signature.is_none());
+	assert!(matches!(
+		tx.call,
+		RuntimeCall::EdgeConnect(Call::submit_response_unsigned_with_signed_payload {
+			response_payload: ResponsePayload { command_id: 0, .. },
+			..
+		})
+	));
+}
+
+#[test]
+fn fetch_response_reports_error_envelopes() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		// CyberHub errors are cut down to `MaxResponseLength` rather than rejected.
+		let max = <<Test as crate::Config>::MaxResponseLength as Get<u32>>::get() as usize;
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(
+				format!(r#"{{"command_id":0,"status":"error","body":"{}"}}"#, "e".repeat(max + 1))
+					.into_bytes(),
+			),
+			sent: true,
+			..Default::default()
+		});
+
+		assert_eq!(
+			EdgeConnect::fetch_response(&["http://edge.example"], &command),
+			Err("CyberHub failed the command")
+		);
+
+		let calls: Vec<_> = pool_state
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.collect();
+		assert_eq!(
+			calls,
+			vec![
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::Dispatched,
+				}),
+				RuntimeCall::EdgeConnect(Call::report_command_error {
+					command_id: 0,
+					error: response(&vec![b'e'; max]),
+				}),
+			]
+		);
+	});
+}
+
+#[test]
+fn fetch_response_fails_commands_with_malformed_responses() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(br#"{"command_id":0,"status":"ok""#.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+
+		assert_eq!(
+			EdgeConnect::fetch_response(&["http://edge.example"], &command),
+			Err("CyberHub response is not a valid envelope")
+		);
+
+		// The command is not left waiting for a response that will never come.
+		let calls: Vec<_> = pool_state
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.collect();
+		assert_eq!(
+			calls,
+			vec![
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::Dispatched,
+				}),
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::Failed,
+				}),
+			]
+		);
+	});
+}
+
+#[test]
+fn report_command_error_fails_the_command() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// Only offchain worker authorities may report errors.
+		assert_noop!(
+			EdgeConnect::report_command_error(RuntimeOrigin::signed(BOB), 0, response(b"busy")),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(EdgeConnect::report_command_error(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"busy")
+		));
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Failed));
+		assert_eq!(EdgeConnect::command_error(0), Some(response(b"busy")));
+		System::assert_has_event(Event::CommandFailed { command_id: 0 }.into());
+
+		// Failed commands stay failed.
+		assert_noop!(
+			EdgeConnect::report_command_error(RuntimeOrigin::signed(OCW), 0, response(b"again")),
+			Error::<Test>::InvalidStatusTransition
+		);
+		assert_noop!(
+			EdgeConnect::report_command_error(RuntimeOrigin::signed(OCW), 1, response(b"busy")),
+			Error::<Test>::UnknownCommandId
+		);
+
+		// The error goes away with the command.
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert_eq!(EdgeConnect::command_error(0), None);
+	});
+}
+
+#[test]
+fn offchain_worker_batches_queued_commands() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		MaxCommandsPerBatch::set(2);
+		System::set_block_number(4);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"pong")
+		));
+
+		// Both commands go out in a single request, CyberHub fails the second one only.
+		let commands = EdgeConnect::command_queue(ConnectionId(0)).into_inner();
+		let expected = concat!(
+			r#"[{"command_id":0,"nonce":0,"command":"ping"},"#,
+			r#"{"command_id":1,"nonce":1,"command":"pong"}]"#,
+		);
+		assert_eq!(EdgeConnect::encode_batch_request(&commands), expected.as_bytes().to_vec());
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_batch_request(&commands),
+			response: Some(
+				concat!(
+					r#"[{"command_id":0,"status":"ok","body":"pong"},"#,
+					r#"{"command_id":1,"status":"error","body":"busy"}]"#,
+				)
+				.as_bytes()
+				.to_vec(),
+			),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(4);
+
+		let calls: Vec<_> = pool_state
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.collect();
+		assert_eq!(
+			calls,
+			vec![
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 0,
+					status: CommandStatus::Dispatched,
+				}),
+				RuntimeCall::EdgeConnect(Call::update_command_status {
+					command_id: 1,
+					status: CommandStatus::Dispatched,
+				}),
+				RuntimeCall::EdgeConnect(Call::report_command_error {
+					command_id: 1,
+					error: response(b"busy"),
+				}),
+				RuntimeCall::EdgeConnect(Call::receive_response {
+					command_id: 0,
+					response: response(b"pong"),
+					server_signature: None,
+				}),
+			]
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_delivers_higher_priorities_first() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(4);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"later")
+		));
+		assert_ok!(EdgeConnect::send_raw_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"now"),
+			SendOptions { priority: 7, ..Default::default() }
+		));
+
+		// The queue keeps insertion order, the urgent command is still delivered first.
+		let queue = EdgeConnect::command_queue(ConnectionId(0));
+		assert_eq!(queue.iter().map(|command| command.priority).collect::<Vec<_>>(), vec![0, 7]);
+		for (command, result) in [(&queue[1], "done"), (&queue[0], "waited")] {
+			state.write().expect_request(testing::PendingRequest {
+				method: "POST".into(),
+				uri: "http://edge.example".into(),
+				body: EdgeConnect::encode_request(command),
+				response: Some(
+					format!(r#"{{"command_id":{},"status":"ok","body":"{}"}}"#, command.id, result)
+						.into_bytes(),
+				),
+				sent: true,
+				..Default::default()
+			});
+		}
+		EdgeConnect::offchain_worker(4);
+
+		let responses: Vec<_> = pool_state
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.filter(|call| matches!(call, RuntimeCall::EdgeConnect(Call::receive_response { .. })))
+			.collect();
+		assert_eq!(
+			responses,
+			vec![
+				RuntimeCall::EdgeConnect(Call::receive_response {
+					command_id: 1,
+					response: response(b"done"),
+					server_signature: None,
+				}),
+				RuntimeCall::EdgeConnect(Call::receive_response {
+					command_id: 0,
+					response: response(b"waited"),
+					server_signature: None,
+				}),
+			]
+		);
+	});
+}
+
+#[test]
+fn cancel_command_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::grant_command_access(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(BOB),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(BOB),
+			ConnectionId(0),
+			command(b"pong")
+		));
+
+		// The submitter may cancel its own command.
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(BOB), 0));
+		System::assert_last_event(
+			Event::CommandCancelled { command_id: 0, connection: ConnectionId(0) }.into(),
+		);
+		assert_eq!(
+			EdgeConnect::command_queue(ConnectionId(0))
+				.iter()
+				.map(|command| command.id)
+				.collect::<Vec<_>>(),
+			vec![1]
+		);
+		assert_eq!(EdgeConnect::command_status(0), None);
+
+		// So may the owner of the connection.
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 1));
+		assert!(EdgeConnect::command_queue(ConnectionId(0)).is_empty());
+		assert_eq!(EdgeConnect::command_status(1), None);
+		assert_ok!(EdgeConnect::do_try_state());
+	});
+}
+
+#[test]
+fn queue_depth_alerts_are_rate_limited() {
+	new_test_ext().execute_with(|| {
+		QueueDepthAlertThreshold::set(2);
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		let send = |payload: &[u8]| {
+			EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(payload),
+			)
+		};
+		let alerts = || {
+			System::events()
+				.into_iter()
+				.filter(|record| {
+					matches!(record.event, RuntimeEvent::EdgeConnect(Event::QueueDepthHigh { .. }))
+				})
+				.count()
+		};
+
+		// The queue reaching the threshold raises the alert.
+		assert_ok!(send(b"ping"));
+		assert_eq!(alerts(), 0);
+		assert_ok!(send(b"pong"));
+		System::assert_last_event(
+			Event::QueueDepthHigh { connection: ConnectionId(0), depth: 2 }.into(),
+		);
+
+		// And it recovers once the queue drains below it.
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 0));
+		System::assert_last_event(
+			Event::QueueDepthRecovered { connection: ConnectionId(0), depth: 1 }.into(),
+		);
+
+		// Backing up again within `QueueDepthAlertInterval` stays quiet.
+		System::set_block_number(2);
+		EdgeConnect::on_initialize(2);
+		assert_ok!(send(b"ping"));
+		System::assert_last_event(
+			Event::CommandQueued { connection: ConnectionId(0), command_id: 2, nonce: 2 }.into(),
+		);
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 2));
+		System::assert_last_event(
+			Event::CommandCancelled { command_id: 2, connection: ConnectionId(0) }.into(),
+		);
+		assert_eq!(alerts(), 1);
+
+		// Once it has passed, the alert is raised again.
+		System::set_block_number(6);
+		EdgeConnect::on_initialize(6);
+		assert_ok!(send(b"ping"));
+		System::assert_last_event(
+			Event::QueueDepthHigh { connection: ConnectionId(0), depth: 2 }.into(),
+		);
+		assert_eq!(alerts(), 2);
+
+		// Removing the connection forgets about the alert.
+		assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert_eq!(QueueDepthAlert::<Test>::get(ConnectionId(0)), None);
+		assert_ok!(EdgeConnect::do_try_state());
+	});
+}
+
+#[test]
+fn cancel_command_rejects_invalid_cancellations() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"pong")
+		));
+
+		assert_noop!(
+			EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 7),
+			Error::<Test>::UnknownCommandId
+		);
+		assert_noop!(
+			EdgeConnect::cancel_command(RuntimeOrigin::signed(BOB), 0),
+			Error::<Test>::NotCommandSubmitter
+		);
+
+		// Commands that left for CyberHub, or got their answer, stay.
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Dispatched
+		));
+		assert_noop!(
+			EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 0),
+			Error::<Test>::CommandNotCancellable
+		);
+		dispatch(1);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			1,
+			response(b"ok"),
+			None
+		));
+		assert_noop!(
+			EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 1),
+			Error::<Test>::CommandNotCancellable
+		);
+	});
+}
+
+#[test]
+fn flush_command_queue_cancels_queued_commands() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			EdgeConnect::flush_command_queue(RuntimeOrigin::root(), ConnectionId(0)),
+			Error::<Test>::ConnectionDoesNotExist
+		);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		for _ in 0..2 {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			));
+		}
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::Dispatched
+		));
+
+		assert_noop!(
+			EdgeConnect::flush_command_queue(RuntimeOrigin::signed(BOB), ConnectionId(0)),
+			Error::<Test>::NotConnectionOwner
+		);
+
+		// The dispatched command is on its way to CyberHub, it stays.
+		assert_ok!(EdgeConnect::flush_command_queue(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Dispatched));
+		assert_eq!(EdgeConnect::command_status(1), Some(CommandStatus::Cancelled));
+		System::assert_has_event(
+			Event::CommandCancelled { command_id: 1, connection: ConnectionId(0) }.into(),
+		);
+		System::assert_last_event(
+			Event::QueueFlushed { connection: ConnectionId(0), count: 1 }.into(),
+		);
+
+		// Flushing an empty queue is not an error, and governance may flush any queue.
+		assert_ok!(EdgeConnect::flush_command_queue(RuntimeOrigin::root(), ConnectionId(0)));
+		System::assert_last_event(
+			Event::QueueFlushed { connection: ConnectionId(0), count: 0 }.into(),
+		);
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Dispatched));
+
+		// Cancelled commands are kept like other settled ones, and pruned with them once
+		// `CommandRetentionBlocks`, five, passed.
+		EdgeConnect::on_initialize(6);
+		assert_eq!(EdgeConnect::command_status(1), Some(CommandStatus::Cancelled));
+		EdgeConnect::on_initialize(7);
+		assert_eq!(EdgeConnect::command_status(1), None);
+		let queue: Vec<_> = EdgeConnect::command_queue(ConnectionId(0))
+			.iter()
+			.map(|command| command.id)
+			.collect();
+		assert_eq!(queue, vec![0]);
+	});
+}
+
+#[test]
+fn flush_command_queue_is_capped_per_call() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		for _ in 0..2 {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			));
+		}
+
+		// Only `MaxFlushPerCall` commands go per call, and only they are charged for.
+		let info = EdgeConnect::flush_command_queue(RuntimeOrigin::signed(ALICE), ConnectionId(0))
+			.unwrap();
+		assert_eq!(info.actual_weight, Some(<() as WeightInfo>::flush_command_queue(1)));
+		assert_eq!(EdgeConnect::command_status(0), Some(CommandStatus::Cancelled));
+		assert_eq!(EdgeConnect::command_status(1), Some(CommandStatus::Queued));
+
+		assert_ok!(EdgeConnect::flush_command_queue(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert_eq!(EdgeConnect::command_status(1), Some(CommandStatus::Cancelled));
+	});
+}
+
+#[test]
+fn retry_command_requeues_failed_commands() {
+	let mut t = new_test_ext_with_connections(vec![
+		(ALICE, url(b"http://edge-a.example")),
+		(ALICE, url(b"http://edge-b.example")),
+	]);
+	let (offchain, _state) = testing::TestOffchainExt::with_offchain_db(t.offchain_db());
+	t.register_extension(OffchainDbExt::new(offchain));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		for (connection, valid_until) in [(0, None), (0, None), (1, None), (1, Some(1))] {
+			assert_ok!(EdgeConnect::send_raw_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(connection),
+				command(b"ping"),
+				SendOptions { valid_until, ..Default::default() }
+			));
+		}
+		System::set_block_number(2);
+		for (command_id, status) in [
+			(0, CommandStatus::Failed),
+			(1, CommandStatus::Dispatched),
+			(1, CommandStatus::TimedOut),
+			(3, CommandStatus::Expired),
+		] {
+			assert_ok!(EdgeConnect::update_command_status(
+				RuntimeOrigin::signed(OCW),
+				command_id,
+				status
+			));
+		}
+		dispatch(2);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			2,
+			response(b"pong"),
+			None
+		));
+		// Requests for both commands failed a few times before.
+		for command_id in [0, 3] {
+			sp_io::offchain::local_storage_set(
+				StorageKind::PERSISTENT,
+				&EdgeConnect::retry_key(command_id),
+				&(3u32, 9u64).encode(),
+			);
+		}
+
+		assert_noop!(
+			EdgeConnect::retry_command(RuntimeOrigin::signed(BOB), 0),
+			Error::<Test>::NotCommandSubmitter
+		);
+		assert_noop!(
+			EdgeConnect::retry_command(RuntimeOrigin::signed(ALICE), 9),
+			Error::<Test>::UnknownCommandId
+		);
+		for command_id in [2, 3] {
+			assert_noop!(
+				EdgeConnect::retry_command(RuntimeOrigin::signed(ALICE), command_id),
+				Error::<Test>::CommandNotRetryable
+			);
+		}
+
+		for command_id in [0, 1] {
+			assert_ok!(EdgeConnect::retry_command(RuntimeOrigin::signed(ALICE), command_id));
+			System::assert_last_event(
+				Event::CommandRequeued { command_id, connection: ConnectionId(0) }.into(),
+			);
+			assert_eq!(EdgeConnect::command_status(command_id), Some(CommandStatus::Queued));
+		}
+		let commands = EdgeConnect::command_queue(ConnectionId(0));
+		assert!(commands.iter().all(|command| command.dispatched_at.is_none()));
+		assert!(commands.iter().all(|command| command.settled_at.is_none()));
+		assert_eq!(EdgeConnect::stats().total_queued_commands, 2);
+
+		// Commands that are queued again have nothing to retry.
+		assert_noop!(
+			EdgeConnect::retry_command(RuntimeOrigin::signed(ALICE), 0),
+			Error::<Test>::CommandNotRetryable
+		);
+	});
+
+	t.persist_offchain_overlay();
+	t.execute_with(|| {
+		assert_eq!(EdgeConnect::retry_state(0), None);
+		assert_eq!(EdgeConnect::retry_state(3), Some((3, 9)));
+	});
+}
+
+#[test]
+fn offchain_worker_skips_cancelled_commands() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(4);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"pong")
+		));
+		let cancelled = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		assert_ok!(EdgeConnect::cancel_command(RuntimeOrigin::signed(ALICE), 0));
+
+		// Only the remaining command is sent, the cancelled one is gone from every read.
+		let command = EdgeConnect::command_queue(ConnectionId(0))[0].clone();
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&command),
+			response: Some(vec![]),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(4);
+		assert_eq!(pool_state.read().transactions.len(), 1);
+
+		// A worker still holding on to the cancelled command gives up on it quietly.
+		state.write().expect_request(testing::PendingRequest {
+			method: "POST".into(),
+			uri: "http://edge.example".into(),
+			body: EdgeConnect::encode_request(&cancelled),
+			response: Some(br#"{"command_id":0,"status":"ok","body":"pong"}"#.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+		assert_eq!(
+			EdgeConnect::fetch_responses(&["http://edge.example"], &[cancelled]),
+			Ok(vec![])
+		);
+		assert_eq!(pool_state.read().transactions.len(), 1);
+	});
+}
+
+#[test]
+fn report_unreachable_is_rate_limited() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			EdgeConnect::report_unreachable(RuntimeOrigin::signed(OCW), ConnectionId(0)),
+			Error::<Test>::ConnectionDoesNotExist
+		);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_ok!(EdgeConnect::report_unreachable(RuntimeOrigin::signed(OCW), ConnectionId(0)));
+		System::assert_last_event(
+			Event::ConnectionUnreachable { connection: ConnectionId(0), block: 1 }.into(),
+		);
+
+		// Another report has to wait for `UnreachableReportInterval` blocks.
+		System::set_block_number(5);
+		assert_noop!(
+			EdgeConnect::report_unreachable(RuntimeOrigin::signed(OCW), ConnectionId(0)),
+			Error::<Test>::UnreachableReportTooSoon
+		);
+		System::set_block_number(6);
+		assert_ok!(EdgeConnect::report_unreachable(RuntimeOrigin::signed(OCW), ConnectionId(0)));
+		System::assert_last_event(
+			Event::ConnectionUnreachable { connection: ConnectionId(0), block: 6 }.into(),
+		);
+
+		assert_noop!(
+			EdgeConnect::report_unreachable(RuntimeOrigin::none(), ConnectionId(0)),
+			DispatchError::BadOrigin
+		);
+		// Only offchain worker authorities may report.
+		System::set_block_number(11);
+		assert_noop!(
+			EdgeConnect::report_unreachable(RuntimeOrigin::signed(BOB), ConnectionId(0)),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_reports_unreachable_connections_once_per_interval() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore.sr25519_generate_new(crate::KEY_TYPE, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// Edge servers answering with an error status are not unreachable.
+		assert_ok!(EdgeConnect::note_unreachable(0, &http::Error::Unknown));
+		assert!(pool_state.read().transactions.is_empty());
+
+		assert_ok!(EdgeConnect::note_unreachable(0, &http::Error::DeadlineReached));
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert_eq!(
+			Extrinsic::decode(&mut &*tx).unwrap().call,
+			RuntimeCall::EdgeConnect(Call::report_unreachable { connection: ConnectionId(0) })
+		);
+
+		// The report is not on chain yet, the worker still remembers it sent one.
+		assert_ok!(EdgeConnect::note_unreachable(0, &http::Error::IoError));
+		assert!(pool_state.read().transactions.is_empty());
+
+		System::set_block_number(6);
+		assert_ok!(EdgeConnect::note_unreachable(0, &http::Error::IoError));
+		assert_eq!(pool_state.read().transactions.len(), 1);
+	});
+}
+
+#[test]
+fn offchain_worker_records_endpoint_failovers_once() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore.sr25519_generate_new(crate::KEY_TYPE, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-a.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::add_endpoint(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			url(b"http://edge-b.example"),
+			None
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// Answers from the endpoint that answered before are nothing to report.
+		assert_ok!(EdgeConnect::note_endpoint(0, 0));
+		assert!(pool_state.read().transactions.is_empty());
+
+		assert_ok!(EdgeConnect::note_endpoint(0, 1));
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert_eq!(
+			Extrinsic::decode(&mut &*tx).unwrap().call,
+			RuntimeCall::EdgeConnect(Call::record_endpoint_failover {
+				connection: ConnectionId(0),
+				index: 1
+			})
+		);
+
+		// The failover is not on chain yet, the worker still remembers it reported it.
+		assert_ok!(EdgeConnect::note_endpoint(0, 1));
+		assert!(pool_state.read().transactions.is_empty());
+
+		// Once it is, the primary endpoint answering again is reported as well.
+		assert_ok!(EdgeConnect::record_endpoint_failover(
+			RuntimeOrigin::signed(OCW),
+			ConnectionId(0),
+			1
+		));
+		assert_ok!(EdgeConnect::note_endpoint(0, 1));
+		assert!(pool_state.read().transactions.is_empty());
+		assert_ok!(EdgeConnect::note_endpoint(0, 0));
+		assert_eq!(pool_state.read().transactions.len(), 1);
+	});
+}
+
+#[test]
+fn heartbeat_and_responses_update_last_seen() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		System::set_block_number(5);
+		assert_ok!(EdgeConnect::heartbeat(RuntimeOrigin::signed(OCW), ConnectionId(0)));
+		System::assert_last_event(
+			Event::ConnectionHeartbeat { connection: ConnectionId(0), block: 5 }.into(),
+		);
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)).unwrap().last_seen, 5);
+
+		// A response is as good a sign of life as a heartbeat.
+		System::set_block_number(8);
+		dispatch(0);
+		assert_ok!(EdgeConnect::receive_response(
+			RuntimeOrigin::signed(OCW),
+			0,
+			response(b"pong"),
+			None
+		));
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)).unwrap().last_seen, 8);
+
+		assert_noop!(
+			EdgeConnect::heartbeat(RuntimeOrigin::signed(OCW), ConnectionId(1)),
+			Error::<Test>::ConnectionDoesNotExist
+		);
+		assert_noop!(
+			EdgeConnect::heartbeat(RuntimeOrigin::none(), ConnectionId(0)),
+			DispatchError::BadOrigin
+		);
+		// Only offchain worker authorities may vouch for an edge server.
+		assert_noop!(
+			EdgeConnect::heartbeat(RuntimeOrigin::signed(BOB), ConnectionId(0)),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_pings_quiet_edge_servers() {
+	const PHRASE: &str =
+		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore
+		.sr25519_generate_new(crate::KEY_TYPE, Some(&format!("{}/edge", PHRASE)))
+		.unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example/"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		// Recently created connections are left alone.
+		EdgeConnect::offchain_worker(4);
+		assert!(pool_state.read().transactions.is_empty());
+
+		System::set_block_number(12);
+		state.write().expect_request(testing::PendingRequest {
+			method: "GET".into(),
+			uri: "http://edge.example/health".into(),
+			response: Some(b"ok".to_vec()),
+			sent: true,
+			..Default::default()
+		});
+		EdgeConnect::offchain_worker(12);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(
+			tx.call,
+			RuntimeCall::EdgeConnect(Call::heartbeat { connection: ConnectionId(0) })
+		);
+	});
+}
+
+#[test]
+fn quiet_connections_are_disconnected() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore.sr25519_generate_new(crate::KEY_TYPE, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	t.register_extension(OffchainDbExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		System::set_block_number(1);
+		for edge in
+			[&b"http://edge-a.example"[..], b"http://edge-b.example", b"http://edge-c.example"]
+		{
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(edge),
+				None,
+				None,
+				PollMode::Push
+			));
+		}
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(2),
+			None
+		));
+
+		// `StaleConnectionBlocks` is 20, the connections are still fine right at the threshold.
+		let stale = <<Test as crate::Config>::StaleConnectionBlocks as Get<u64>>::get();
+		System::set_block_number(1 + stale);
+		EdgeConnect::on_initialize(1 + stale);
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0)).unwrap().status,
+			ConnectionStatus::Active
+		);
+
+		// One block later they are stale, but only `MaxPrunePerBlock` connections are checked
+		// per block, taking turns.
+		let disconnected = || {
+			(0..3)
+				.filter(|id| {
+					EdgeConnect::connection_info(ConnectionId(*id)).unwrap().status
+						== ConnectionStatus::Disconnected
+				})
+				.count()
+		};
+		for block in 2 + stale..=4 + stale {
+			let before = disconnected();
+			System::set_block_number(block);
+			EdgeConnect::on_initialize(block);
+			assert!(disconnected() <= before + 1);
+		}
+		assert_eq!(disconnected(), 2);
+		assert!(System::events().iter().any(|record| {
+			record.event
+				== Event::ConnectionStaled { connection: ConnectionId(0), last_seen: 1 }.into()
+		}));
+		// Paused connections are left alone.
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(2)).unwrap().status,
+			ConnectionStatus::Paused
+		);
+
+		// The command queued on the disconnected connection is not sent to CyberHub.
+		EdgeConnect::offchain_worker(5 + stale);
+		assert!(pool_state.read().transactions.is_empty());
+
+		// Bringing the connection back gives it a fresh start.
+		assert_ok!(EdgeConnect::set_connection_status(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			ConnectionStatus::Active,
+			None
+		));
+		EdgeConnect::on_initialize(6 + stale);
+		assert_eq!(
+			EdgeConnect::connection_info(ConnectionId(0)).unwrap().status,
+			ConnectionStatus::Active
+		);
+	});
+}
+
+#[test]
+fn connections_are_removed_once_they_expire() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(2);
+		for expires_at in [1, 2] {
+			assert_noop!(
+				EdgeConnect::create_connection(
+					RuntimeOrigin::signed(ALICE),
+					url(b"http://edge-a.example"),
+					None,
+					Some(expires_at),
+					PollMode::Push
+				),
+				Error::<Test>::ExpiryInPast
+			);
+		}
+		for edge in [&b"http://edge-a.example"[..], b"http://edge-b.example"] {
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(edge),
+				None,
+				Some(4),
+				PollMode::Push
+			));
+		}
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-c.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)).unwrap().expires_at, Some(4));
+		assert_eq!(Balances::reserved_balance(ALICE), 3 * DEPOSIT);
+
+		System::set_block_number(3);
+		EdgeConnect::on_initialize(3);
+		assert!(EdgeConnect::connection_info(ConnectionId(0)).is_some());
+
+		// Only `MaxPrunePerBlock` connections expire per block, the others wait for the next.
+		System::set_block_number(4);
+		EdgeConnect::on_initialize(4);
+		System::assert_last_event(
+			Event::ConnectionExpired { connection: ConnectionId(0), who: ALICE }.into(),
+		);
+		assert!(EdgeConnect::connection_info(ConnectionId(0)).is_none());
+		assert_eq!(EdgeConnect::command_status(0), None);
+		assert!(EdgeConnect::connection_info(ConnectionId(1)).is_some());
+		assert_eq!(Balances::reserved_balance(ALICE), 2 * DEPOSIT);
+
+		System::set_block_number(5);
+		EdgeConnect::on_initialize(5);
+		System::assert_last_event(
+			Event::ConnectionExpired { connection: ConnectionId(1), who: ALICE }.into(),
+		);
+		assert_eq!(EdgeConnect::connections_of(ALICE).into_inner(), vec![ConnectionId(2)]);
+		assert_eq!(Balances::reserved_balance(ALICE), DEPOSIT);
+		assert_ok!(EdgeConnect::do_try_state());
+	});
+}
+
+#[test]
+fn create_connection_requires_an_authorized_domain() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"https://attacker.example/edge"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::DomainNotAuthorized
+		);
+		// The host is matched regardless of user info, port or case.
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"https://user@EDGE.example:8443/api?x=1"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"https://sub.edge.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::DomainNotAuthorized
+		);
+	});
+}
+
+#[test]
+fn ocw_authorities_are_governed_by_root() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			EdgeConnect::add_ocw_authority(RuntimeOrigin::signed(ALICE), ALICE),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(EdgeConnect::add_ocw_authority(RuntimeOrigin::root(), BOB));
+		System::assert_last_event(Event::OcwAuthorityAdded { who: BOB }.into());
+		assert_noop!(
+			EdgeConnect::add_ocw_authority(RuntimeOrigin::root(), BOB),
+			Error::<Test>::AlreadyOcwAuthority
+		);
+		assert!(crate::OcwAuthorities::<Test>::contains_key(BOB));
+
+		assert_noop!(
+			EdgeConnect::remove_ocw_authority(RuntimeOrigin::signed(BOB), BOB),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(EdgeConnect::remove_ocw_authority(RuntimeOrigin::root(), BOB));
+		System::assert_last_event(Event::OcwAuthorityRemoved { who: BOB }.into());
+		assert_noop!(
+			EdgeConnect::remove_ocw_authority(RuntimeOrigin::root(), BOB),
+			Error::<Test>::NotOcwAuthority
+		);
+		assert!(!crate::OcwAuthorities::<Test>::contains_key(BOB));
+	});
+}
+
+#[test]
+fn authorized_domains_are_governed_by_root() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			EdgeConnect::add_authorized_domain(
+				RuntimeOrigin::signed(ALICE),
+				b"new.example".to_vec()
+			),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(EdgeConnect::add_authorized_domain(
+			RuntimeOrigin::root(),
+			b"New.Example".to_vec()
+		));
+		System::assert_last_event(
+			Event::DomainAuthorized { domain: b"new.example".to_vec().try_into().unwrap() }.into(),
+		);
+		assert_noop!(
+			EdgeConnect::add_authorized_domain(RuntimeOrigin::root(), b"new.example".to_vec()),
+			Error::<Test>::DomainAlreadyAuthorized
+		);
+		assert_noop!(
+			EdgeConnect::add_authorized_domain(RuntimeOrigin::root(), vec![]),
+			Error::<Test>::InvalidDomain
+		);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://new.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		// Revoking a domain keeps existing connections but blocks new ones.
+		assert_ok!(EdgeConnect::remove_authorized_domain(
+			RuntimeOrigin::root(),
+			b"new.example".to_vec()
+		));
+		System::assert_last_event(
+			Event::DomainRevoked { domain: b"new.example".to_vec().try_into().unwrap() }.into(),
+		);
+		assert!(EdgeConnect::connection_info(ConnectionId(0)).is_some());
+		assert_noop!(
+			EdgeConnect::create_connection(
+				RuntimeOrigin::signed(BOB),
+				url(b"http://new.example"),
+				None,
+				None,
+				PollMode::Push
+			),
+			Error::<Test>::DomainNotAuthorized
+		);
+		assert_noop!(
+			EdgeConnect::remove_authorized_domain(RuntimeOrigin::root(), b"new.example".to_vec()),
+			Error::<Test>::DomainNotAuthorized
+		);
+	});
+}
+
+#[test]
+fn send_command_requires_command_access() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_noop!(
+			EdgeConnect::send_command(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				command(b"ping")
+			),
+			Error::<Test>::NotAuthorizedToSend.with_weight(rejected())
+		);
+
+		assert_ok!(EdgeConnect::grant_command_access(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		System::assert_last_event(
+			Event::CommandAccessGranted { connection: ConnectionId(0), who: BOB }.into(),
+		);
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(BOB),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		assert_ok!(EdgeConnect::revoke_command_access(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		System::assert_last_event(
+			Event::CommandAccessRevoked { connection: ConnectionId(0), who: BOB }.into(),
+		);
+		assert_noop!(
+			EdgeConnect::send_command(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				command(b"ping")
+			),
+			Error::<Test>::NotAuthorizedToSend.with_weight(rejected())
+		);
+	});
+}
+
+#[test]
+fn command_access_is_managed_by_the_owner() {
+	new_test_ext().execute_with(|| {
+		let charlie = sr25519::Public([3u8; 32]);
+		let dave = sr25519::Public([4u8; 32]);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+
+		assert_noop!(
+			EdgeConnect::grant_command_access(
+				RuntimeOrigin::signed(BOB),
+				ConnectionId(0),
+				BOB,
+				None
+			),
+			Error::<Test>::NotConnectionOwner
+		);
+		// The owner is always allowed, and so are accounts granted access before.
+		assert_noop!(
+			EdgeConnect::grant_command_access(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				ALICE,
+				None
+			),
+			Error::<Test>::AlreadyAuthorizedToSend
+		);
+		assert_ok!(EdgeConnect::grant_command_access(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			BOB,
+			None
+		));
+		assert_noop!(
+			EdgeConnect::grant_command_access(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				BOB,
+				None
+			),
+			Error::<Test>::AlreadyAuthorizedToSend
+		);
+		// `MaxAuthorizedSenders` is two.
+		assert_ok!(EdgeConnect::grant_command_access(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			charlie,
+			None
+		));
+		assert_noop!(
+			EdgeConnect::grant_command_access(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				dave,
+				None
+			),
+			Error::<Test>::TooManyAuthorizedSenders
+		);
+		assert_noop!(
+			EdgeConnect::revoke_command_access(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				dave,
+				None
+			),
+			Error::<Test>::NotAuthorizedToSend
+		);
+
+		// A new owner starts with a clean list.
+		assert_ok!(EdgeConnect::transfer_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			dave,
+			None
+		));
+		assert!(EdgeConnect::connection_info(ConnectionId(0))
+			.unwrap()
+			.authorized_senders
+			.is_empty());
+	});
+}
+
+#[test]
+fn leased_connections_are_kept_to_the_lessee() {
+	new_test_ext().execute_with(|| {
+		let carol = sr25519::Public([3u8; 32]);
+		System::set_block_number(1);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		for sender in [BOB, carol] {
+			assert_ok!(EdgeConnect::grant_command_access(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				sender,
+				None
+			));
+		}
+		let send = |who| {
+			EdgeConnect::send_command(RuntimeOrigin::signed(who), ConnectionId(0), command(b"ping"))
+		};
+
+		assert_noop!(
+			EdgeConnect::lease_connection(RuntimeOrigin::signed(BOB), ConnectionId(0), 1),
+			Error::<Test>::LeaseInPast
+		);
+		assert_noop!(
+			EdgeConnect::lease_connection(
+				RuntimeOrigin::signed(sr25519::Public([4u8; 32])),
+				ConnectionId(0),
+				3
+			),
+			Error::<Test>::NotAuthorizedToSend
+		);
+		assert_ok!(EdgeConnect::lease_connection(RuntimeOrigin::signed(BOB), ConnectionId(0), 3));
+		System::assert_last_event(
+			Event::ConnectionLeased { connection: ConnectionId(0), lessee: BOB, until: 3 }.into(),
+		);
+		assert_noop!(
+			EdgeConnect::lease_connection(RuntimeOrigin::signed(carol), ConnectionId(0), 5),
+			Error::<Test>::ConnectionLeased
+		);
+
+		// Only the lessee and the owner may send commands while the lease runs.
+		assert_noop!(send(carol), Error::<Test>::ConnectionLeased);
+		assert_ok!(send(BOB));
+		assert_ok!(send(ALICE));
+		assert_eq!(EdgeConnect::command_queue(ConnectionId(0)).len(), 2);
+
+		// The lease runs out on its own.
+		System::set_block_number(3);
+		EdgeConnect::on_initialize(3);
+		System::assert_last_event(
+			Event::LeaseReleased { connection: ConnectionId(0), lessee: BOB }.into(),
+		);
+		assert_eq!(EdgeConnect::lease(ConnectionId(0)), None);
+
+		// Or is released early by the lessee or the owner.
+		assert_ok!(EdgeConnect::lease_connection(RuntimeOrigin::signed(carol), ConnectionId(0), 5));
+		assert_noop!(
+			EdgeConnect::release_lease(RuntimeOrigin::signed(BOB), ConnectionId(0)),
+			Error::<Test>::NotLessee
+		);
+		assert_ok!(EdgeConnect::release_lease(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		System::assert_last_event(
+			Event::LeaseReleased { connection: ConnectionId(0), lessee: carol }.into(),
+		);
+		assert_noop!(
+			EdgeConnect::release_lease(RuntimeOrigin::signed(carol), ConnectionId(0)),
+			Error::<Test>::NotLeased
+		);
+	});
+}
+
+#[test]
+fn only_leases_due_in_the_block_are_read() {
+	new_test_ext_with_connections(vec![(ALICE, url(b"http://edge.example"))]).execute_with(|| {
+		System::set_block_number(1);
+		// Plenty of leases that run for a while yet.
+		for connection in 1..=50 {
+			crate::Leases::<Test>::insert(ConnectionId(connection), (BOB, 100));
+		}
+		assert_ok!(EdgeConnect::lease_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0), 5));
+		let db = frame_support::weights::constants::RocksDbWeight::get();
+
+		System::set_block_number(4);
+		assert_eq!(EdgeConnect::expire_leases(4), db.reads(1));
+
+		System::set_block_number(5);
+		assert_eq!(EdgeConnect::expire_leases(5), db.reads_writes(2, 2));
+		assert_eq!(EdgeConnect::lease(ConnectionId(0)), None);
+		assert_eq!(crate::Leases::<Test>::iter().count(), 50);
+
+		// A lease released early leaves its entry behind, which is skipped.
+		assert_ok!(EdgeConnect::lease_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0), 8));
+		assert_ok!(EdgeConnect::release_lease(RuntimeOrigin::signed(ALICE), ConnectionId(0)));
+		assert_ok!(EdgeConnect::lease_connection(RuntimeOrigin::signed(ALICE), ConnectionId(0), 9));
+		System::set_block_number(8);
+		EdgeConnect::on_initialize(8);
+		assert_eq!(EdgeConnect::lease(ConnectionId(0)), Some((ALICE, 9)));
+		System::set_block_number(9);
+		EdgeConnect::on_initialize(9);
+		assert_eq!(EdgeConnect::lease(ConnectionId(0)), None);
+	});
+}
+
+frame_support::parameter_types! {
+	pub const LegacyOwner: AccountId = BOB;
+}
+
+#[test]
+fn legacy_connection_is_migrated_to_v1() {
+	use crate::migrations::v1;
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<EdgeConnect>();
+		v1::Connection::<Test>::put(7);
+
+		v1::MigrateToV1::<Test, LegacyOwner>::on_runtime_upgrade();
+
+		assert_eq!(EdgeConnect::on_chain_storage_version(), 1);
+		assert!(!v1::Connection::<Test>::exists());
+		let info = EdgeConnect::connection_info(ConnectionId(0)).unwrap();
+		assert_eq!(info.owner, BOB);
+		assert!(info.endpoints.is_empty());
+		assert_eq!(info.status, ConnectionStatus::Disconnected);
+		assert_eq!(info.deposit, 0);
+		assert_eq!(EdgeConnect::connections_of(BOB).to_vec(), vec![ConnectionId(0)]);
+		assert_eq!(EdgeConnect::next_connection_id(), ConnectionId(1));
+
+		// Running it again is a no-op.
+		v1::MigrateToV1::<Test, LegacyOwner>::on_runtime_upgrade();
+		assert_eq!(EdgeConnect::next_connection_id(), ConnectionId(1));
+	});
+}
+
+#[test]
+fn undecodable_legacy_connection_is_dropped_by_v1() {
+	use crate::migrations::v1;
+	use frame_support::{
+		storage::unhashed,
+		traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+	};
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<EdgeConnect>();
+		// Too short to be the legacy `u32`.
+		unhashed::put_raw(&v1::Connection::<Test>::hashed_key(), &[1, 2]);
+
+		v1::MigrateToV1::<Test, LegacyOwner>::on_runtime_upgrade();
+
+		assert_eq!(EdgeConnect::on_chain_storage_version(), 1);
+		assert!(!v1::Connection::<Test>::exists());
+		assert_eq!(EdgeConnect::connection_info(ConnectionId(0)), None);
+		assert!(EdgeConnect::connections_of(BOB).is_empty());
+		assert_eq!(EdgeConnect::next_connection_id(), ConnectionId(0));
+	});
+}
+
+#[test]
+fn stats_are_counted_by_the_v2_migration() {
+	use crate::{migrations::v2, ActiveConnectionCount, TotalConnectionCount, TotalQueuedCommands};
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext_with_connections(vec![
+		(ALICE, url(b"http://edge-a.example")),
+		(BOB, url(b"http://edge-b.example")),
+	])
+	.execute_with(|| {
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+		assert_ok!(EdgeConnect::pause_connection(
+			RuntimeOrigin::signed(BOB),
+			ConnectionId(1),
+			None
+		));
+		let counted = EdgeConnect::stats();
+		TotalConnectionCount::<Test>::kill();
+		ActiveConnectionCount::<Test>::kill();
+		TotalQueuedCommands::<Test>::kill();
+		StorageVersion::new(1).put::<EdgeConnect>();
+
+		v2::MigrateToV2::<Test>::on_runtime_upgrade();
+
+		assert_eq!(EdgeConnect::on_chain_storage_version(), 2);
+		assert_eq!(
+			EdgeConnect::stats(),
+			EdgeConnectStats {
+				total_connections: 2,
+				active_connections: 1,
+				total_queued_commands: 1
+			}
+		);
+		assert_eq!(EdgeConnect::stats(), counted);
+	});
+}
+
+#[test]
+fn in_flight_commands_are_counted_by_the_v3_migration() {
+	use crate::{migrations::v3, InFlightCommands};
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext_with_connections(vec![
+		(ALICE, url(b"http://edge-a.example")),
+		(BOB, url(b"http://edge-b.example")),
+	])
+	.execute_with(|| {
+		for (connection, owner) in [(0, ALICE), (0, ALICE), (1, BOB)] {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(owner),
+				ConnectionId(connection),
+				command(b"ping")
+			));
+		}
+		for command_id in [0, 2] {
+			for status in [CommandStatus::Dispatched, CommandStatus::AwaitingResponse] {
+				assert_ok!(EdgeConnect::update_command_status(
+					RuntimeOrigin::signed(OCW),
+					command_id,
+					status
+				));
+			}
+		}
+		let _ = InFlightCommands::<Test>::clear(u32::MAX, None);
+		StorageVersion::new(2).put::<EdgeConnect>();
+
+		v3::MigrateToV3::<Test>::on_runtime_upgrade();
+
+		assert_eq!(EdgeConnect::on_chain_storage_version(), 3);
+		assert_eq!(EdgeConnect::in_flight_commands(ConnectionId(0)), 1);
+		assert_eq!(EdgeConnect::in_flight_commands(ConnectionId(1)), 1);
+		assert_ok!(EdgeConnect::do_try_state());
+	});
+}
+
+#[test]
+fn commands_are_indexed_by_the_v4_migration() {
+	use crate::{migrations::v4, ConnectionCommands};
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext_with_connections(vec![
+		(ALICE, url(b"http://edge-a.example")),
+		(BOB, url(b"http://edge-b.example")),
+	])
+	.execute_with(|| {
+		for (connection, owner) in [(0, ALICE), (1, BOB)] {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(owner),
+				ConnectionId(connection),
+				command(b"ping")
+			));
+		}
+		let _ = ConnectionCommands::<Test>::clear(u32::MAX, None);
+		StorageVersion::new(3).put::<EdgeConnect>();
+
+		v4::MigrateToV4::<Test>::on_runtime_upgrade();
+
+		assert_eq!(EdgeConnect::on_chain_storage_version(), 4);
+		assert_eq!(
+			EdgeConnect::commands_of(ConnectionId(0), None, 10),
+			vec![(0, CommandStatus::Queued)]
+		);
+		assert_eq!(
+			EdgeConnect::commands_of(ConnectionId(1), None, 10),
+			vec![(1, CommandStatus::Queued)]
+		);
+		assert_ok!(EdgeConnect::do_try_state());
+	});
+}
+
+#[test]
+fn response_hashes_are_tagged_by_the_v5_migration() {
+	use crate::{migrations::v5, ResponseHashes};
+	use frame_support::{
+		storage::unhashed,
+		traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+	};
+
+	new_test_ext().execute_with(|| {
+		let hash = H256(blake2_256(b"pong"));
+		unhashed::put(&ResponseHashes::<Test>::hashed_key_for(0), &hash);
+		StorageVersion::new(4).put::<EdgeConnect>();
+
+		v5::MigrateToV5::<Test>::on_runtime_upgrade();
+
+		assert_eq!(EdgeConnect::on_chain_storage_version(), 5);
+		assert_eq!(
+			EdgeConnect::response_hash(0),
+			Some(ResponseHash { hash, algorithm: HashAlgorithm::Blake2_256 })
+		);
+	});
+}
+
+#[test]
+fn commands_are_scheduled_by_the_v6_migration() {
+	use crate::{
+		migrations::v6, CommandDeadlines, CommandPrunes, ConnectionExpiries, LeaseExpiries,
+	};
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext_with_connections(vec![(ALICE, url(b"http://edge.example"))]).execute_with(|| {
+		System::set_block_number(2);
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge-b.example"),
+			None,
+			Some(30),
+			PollMode::Push
+		));
+		for _ in 0..2 {
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(0),
+				command(b"ping")
+			));
+		}
+		dispatch(0);
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			0,
+			CommandStatus::AwaitingResponse
+		));
+		assert_ok!(EdgeConnect::update_command_status(
+			RuntimeOrigin::signed(OCW),
+			1,
+			CommandStatus::Failed
+		));
+		let _ = CommandDeadlines::<Test>::clear(u32::MAX, None);
+		let _ = CommandPrunes::<Test>::clear(u32::MAX, None);
+		let _ = ConnectionExpiries::<Test>::clear(u32::MAX, None);
+		assert_ok!(EdgeConnect::lease_connection(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			40
+		));
+		let _ = LeaseExpiries::<Test>::clear(u32::MAX, None);
+		StorageVersion::new(5).put::<EdgeConnect>();
+
+		v6::MigrateToV6::<Test>::on_runtime_upgrade();
+
+		assert_eq!(EdgeConnect::on_chain_storage_version(), 6);
+		assert_eq!(CommandDeadlines::<Test>::get(13).into_inner(), vec![0]);
+		assert_eq!(CommandPrunes::<Test>::get(8).into_inner(), vec![1]);
+		assert_eq!(ConnectionExpiries::<Test>::get(30).into_inner(), vec![ConnectionId(1)]);
+		assert_eq!(LeaseExpiries::<Test>::get(40).into_inner(), vec![ConnectionId(0)]);
+	});
+}
+
+#[test]
+fn try_state_holds_across_the_connection_lifecycle() {
+	new_test_ext_with_connections(vec![(ALICE, url(b"http://edge-a.example"))]).execute_with(
+		|| {
+			assert_ok!(EdgeConnect::create_connection(
+				RuntimeOrigin::signed(ALICE),
+				url(b"http://edge.example"),
+				None,
+				None,
+				PollMode::Push
+			));
+			assert_ok!(EdgeConnect::send_command(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(1),
+				command(b"ping")
+			));
+			assert_ok!(EdgeConnect::do_try_state());
+
+			assert_ok!(EdgeConnect::transfer_connection(
+				RuntimeOrigin::signed(ALICE),
+				ConnectionId(1),
+				BOB,
+				None
+			));
+			assert_ok!(EdgeConnect::do_try_state());
+
+			assert_ok!(EdgeConnect::remove_connection(RuntimeOrigin::signed(BOB), ConnectionId(1)));
+			assert_ok!(EdgeConnect::do_try_state());
+		},
+	);
+}
+
+#[test]
+fn try_state_detects_inconsistencies() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EdgeConnect::create_connection(
+			RuntimeOrigin::signed(ALICE),
+			url(b"http://edge.example"),
+			None,
+			None,
+			PollMode::Push
+		));
+		assert_ok!(EdgeConnect::send_command(
+			RuntimeOrigin::signed(ALICE),
+			ConnectionId(0),
+			command(b"ping")
+		));
+
+		// A command pointing at a connection that is gone.
+		crate::CommandConnection::<Test>::insert(0, ConnectionId(5));
+		assert!(EdgeConnect::do_try_state().is_err());
+		crate::CommandConnection::<Test>::insert(0, ConnectionId(0));
+		assert_ok!(EdgeConnect::do_try_state());
+
+		// A connection missing from its owner's index.
+		crate::ConnectionsOf::<Test>::remove(ALICE);
+		assert!(EdgeConnect::do_try_state().is_err());
+		crate::ConnectionsOf::<Test>::insert(
+			ALICE,
+			BoundedVec::truncate_from(vec![ConnectionId(0)]),
+		);
+		assert_ok!(EdgeConnect::do_try_state());
+
+		// Running totals that drifted from what is stored.
+		crate::TotalQueuedCommands::<Test>::put(2);
+		assert!(EdgeConnect::do_try_state().is_err());
+		crate::TotalQueuedCommands::<Test>::put(1);
+		crate::ActiveConnectionCount::<Test>::put(0);
+		assert!(EdgeConnect::do_try_state().is_err());
+		crate::ActiveConnectionCount::<Test>::put(1);
+		crate::InFlightCommands::<Test>::insert(ConnectionId(0), 1);
+		assert!(EdgeConnect::do_try_state().is_err());
+		crate::InFlightCommands::<Test>::remove(ConnectionId(0));
+		assert_ok!(EdgeConnect::do_try_state());
+
+		// An id at or past the next one to be handed out.
+		crate::NextConnectionId::<Test>::put(ConnectionId(0));
+		assert!(EdgeConnect::do_try_state().is_err());
 	});
 }
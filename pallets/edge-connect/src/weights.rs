@@ -0,0 +1,1094 @@
+//! Weights for pallet_edge_connect
+//!
+//! These weights are placeholders derived from the storage accessed by each call. Regenerate
+//! them on reference hardware with:
+//!
+//! ```text
+//! ./target/release/cyborg-node benchmark pallet \
+//!     --chain dev \
+//!     --pallet pallet_edge_connect \
+//!     --extrinsic '*' \
+//!     --steps 50 \
+//!     --repeat 20 \
+//!     --output pallets/edge-connect/src/weights.rs
+//! ```
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_edge_connect.
+pub trait WeightInfo {
+	fn create_connection() -> Weight;
+	fn remove_connection(c: u32, ) -> Weight;
+	fn send_command() -> Weight;
+	fn set_connection_status() -> Weight;
+	fn receive_response() -> Weight;
+	fn submit_response_unsigned_with_signed_payload() -> Weight;
+	fn pause_connection() -> Weight;
+	fn resume_connection() -> Weight;
+	fn transfer_connection() -> Weight;
+	fn rename_connection() -> Weight;
+	fn update_command_status() -> Weight;
+	fn add_authorized_domain() -> Weight;
+	fn remove_authorized_domain() -> Weight;
+	fn grant_command_access() -> Weight;
+	fn revoke_command_access() -> Weight;
+	fn cancel_command() -> Weight;
+	fn heartbeat() -> Weight;
+	fn force_remove_connection(c: u32, ) -> Weight;
+	fn report_command_error() -> Weight;
+	fn set_connection_tags() -> Weight;
+	fn broadcast_command(c: u32, ) -> Weight;
+	fn set_metadata() -> Weight;
+	fn clear_metadata() -> Weight;
+	fn report_unreachable() -> Weight;
+	fn set_server_pubkey() -> Weight;
+	fn add_endpoint() -> Weight;
+	fn remove_endpoint() -> Weight;
+	fn record_endpoint_failover() -> Weight;
+	fn send_command_rejected() -> Weight;
+	fn flush_command_queue(c: u32, ) -> Weight;
+	fn retry_command() -> Weight;
+	fn update_endpoint() -> Weight;
+	fn remove_connections(n: u32, ) -> Weight;
+	fn register_template() -> Weight;
+	fn remove_template() -> Weight;
+	fn send_command_from_template() -> Weight;
+	fn lease_connection() -> Weight;
+	fn release_lease() -> Weight;
+	fn submit_server_response() -> Weight;
+	fn set_ocw_paused() -> Weight;
+	fn add_ocw_authority() -> Weight;
+	fn remove_ocw_authority() -> Weight;
+}
+
+/// Weights for pallet_edge_connect using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:0)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:1)
+	/// Storage: EdgeConnect NextConnectionId (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: EdgeConnect LastConnectionCreatedAt (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:0 w:1)
+	/// Storage: EdgeConnect TotalConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect FreeConnectionIds (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionGeneration (r:1 w:0)
+	/// Storage: EdgeConnect ConnectionExpiries (r:1 w:1)
+	fn create_connection() -> Weight {
+		Weight::from_parts(41_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(10_u64))
+			.saturating_add(T::DbWeight::get().writes(9_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:10)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:10)
+	/// Storage: EdgeConnect Responses (r:0 w:10)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:10)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:10)
+	/// Storage: EdgeConnect LastUnreachableReport (r:0 w:1)
+	/// Storage: EdgeConnect MetadataEntries (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionMetadata (r:16 w:16)
+	/// Storage: EdgeConnect TotalConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:0 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:10 w:10)
+	/// Storage: EdgeConnect Broadcasts (r:10 w:10)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:10 w:10)
+	/// Storage: EdgeConnect CommandByClientRef (r:0 w:10)
+	/// Storage: EdgeConnect RecentOutcomes (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:0 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:10)
+	/// Storage: EdgeConnect Leases (r:0 w:1)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:10)
+	/// Storage: EdgeConnect FreeConnectionIds (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionGeneration (r:1 w:1)
+	/// The range of component `c` is `[0, 10]`.
+	fn remove_connection(c: u32, ) -> Weight {
+		Weight::from_parts(34_000_000, 12_000)
+			// Standard Error: 3_000
+			.saturating_add(Weight::from_parts(5_000_000, 0).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(27_u64))
+			.saturating_add(T::DbWeight::get().reads((3_u64).saturating_mul(c.into())))
+			.saturating_add(T::DbWeight::get().writes(32_u64))
+			.saturating_add(T::DbWeight::get().writes((11_u64).saturating_mul(c.into())))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: System Account (r:2 w:2)
+	/// Storage: EdgeConnect NextCommandId (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:1)
+	/// Storage: EdgeConnect CommandsInBlock (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:1 w:1)
+	/// Storage: EdgeConnect CommandByClientRef (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:1)
+	/// Storage: EdgeConnect Leases (r:1 w:0)
+	/// Storage: EdgeConnect LastSeq (r:1 w:1)
+	fn send_command() -> Weight {
+		Weight::from_parts(47_000_000, 12_000)
+			.saturating_add(T::DbWeight::get().reads(12_u64))
+			.saturating_add(T::DbWeight::get().writes(14_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	fn set_connection_status() -> Weight {
+		Weight::from_parts(18_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn receive_response() -> Weight {
+		Weight::from_parts(80_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(10_u64))
+			.saturating_add(T::DbWeight::get().writes(8_u64))
+	}
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect NextUnsignedAt (r:0 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn submit_response_unsigned_with_signed_payload() -> Weight {
+		Weight::from_parts(81_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(9_u64))
+			.saturating_add(T::DbWeight::get().writes(9_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	fn pause_connection() -> Weight {
+		Weight::from_parts(18_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	fn resume_connection() -> Weight {
+		Weight::from_parts(18_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionsOf (r:2 w:2)
+	/// Storage: System Account (r:2 w:2)
+	/// Storage: EdgeConnect Leases (r:1 w:1)
+	fn transfer_connection() -> Weight {
+		Weight::from_parts(45_000_000, 6_196)
+			.saturating_add(T::DbWeight::get().reads(6_u64))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:0)
+	fn rename_connection() -> Weight {
+		Weight::from_parts(22_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect DispatchLogCursor (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandDeadlines (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn update_command_status() -> Weight {
+		Weight::from_parts(24_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(12_u64))
+			.saturating_add(T::DbWeight::get().writes(9_u64))
+	}
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:1)
+	fn add_authorized_domain() -> Weight {
+		Weight::from_parts(15_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:1)
+	fn remove_authorized_domain() -> Weight {
+		Weight::from_parts(15_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn grant_command_access() -> Weight {
+		Weight::from_parts(19_000_000, 4_000)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn revoke_command_access() -> Weight {
+		Weight::from_parts(19_000_000, 4_000)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect CommandConnection (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:1)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:1)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:1 w:1)
+	/// Storage: EdgeConnect CommandByClientRef (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:1)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:1)
+	fn cancel_command() -> Weight {
+		Weight::from_parts(27_000_000, 12_000)
+			.saturating_add(T::DbWeight::get().reads(9_u64))
+			.saturating_add(T::DbWeight::get().writes(14_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn heartbeat() -> Weight {
+		Weight::from_parts(17_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:10)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:10)
+	/// Storage: EdgeConnect Responses (r:0 w:10)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:10)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:10)
+	/// Storage: EdgeConnect LastUnreachableReport (r:0 w:1)
+	/// Storage: EdgeConnect MetadataEntries (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionMetadata (r:16 w:16)
+	/// Storage: EdgeConnect TotalConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:0 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:10 w:10)
+	/// Storage: EdgeConnect Broadcasts (r:10 w:10)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:10 w:10)
+	/// Storage: EdgeConnect CommandByClientRef (r:0 w:10)
+	/// Storage: EdgeConnect RecentOutcomes (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:0 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:10)
+	/// Storage: EdgeConnect Leases (r:0 w:1)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:10)
+	/// Storage: EdgeConnect FreeConnectionIds (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionGeneration (r:1 w:1)
+	/// The range of component `c` is `[0, 10]`.
+	fn force_remove_connection(c: u32, ) -> Weight {
+		Weight::from_parts(33_000_000, 12_000)
+			// Standard Error: 3_000
+			.saturating_add(Weight::from_parts(5_000_000, 0).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(27_u64))
+			.saturating_add(T::DbWeight::get().reads((3_u64).saturating_mul(c.into())))
+			.saturating_add(T::DbWeight::get().writes(32_u64))
+			.saturating_add(T::DbWeight::get().writes((11_u64).saturating_mul(c.into())))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn report_command_error() -> Weight {
+		Weight::from_parts(22_000_000, 4_100)
+			.saturating_add(T::DbWeight::get().reads(9_u64))
+			.saturating_add(T::DbWeight::get().writes(7_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn set_connection_tags() -> Weight {
+		Weight::from_parts(21_000_000, 4_400)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:100 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:100 w:100)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandId (r:1 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:100)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:100)
+	/// Storage: EdgeConnect CommandsInBlock (r:100 w:100)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:100 w:100)
+	/// Storage: EdgeConnect NextBroadcastId (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:0 w:100)
+	/// Storage: EdgeConnect Broadcasts (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:100 w:100)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:100)
+	/// The range of component `c` is `[0, 100]`.
+	fn broadcast_command(c: u32, ) -> Weight {
+		Weight::from_parts(20_000_000, 3_600)
+			// Standard Error: 4_000
+			.saturating_add(Weight::from_parts(20_000_000, 0).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().reads((5_u64).saturating_mul(c.into())))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
+			.saturating_add(T::DbWeight::get().writes((8_u64).saturating_mul(c.into())))
+			.saturating_add(Weight::from_parts(0, 12_000).saturating_mul(c.into()))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionMetadata (r:1 w:1)
+	/// Storage: EdgeConnect MetadataEntries (r:1 w:1)
+	fn set_metadata() -> Weight {
+		Weight::from_parts(23_000_000, 6_700)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionMetadata (r:1 w:1)
+	/// Storage: EdgeConnect MetadataEntries (r:1 w:1)
+	fn clear_metadata() -> Weight {
+		Weight::from_parts(22_000_000, 6_700)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect LastUnreachableReport (r:1 w:1)
+	fn report_unreachable() -> Weight {
+		Weight::from_parts(17_000_000, 4_400)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn set_server_pubkey() -> Weight {
+		Weight::from_parts(20_000_000, 4_400)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:3 w:1)
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:0)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:0)
+	fn add_endpoint() -> Weight {
+		Weight::from_parts(28_000_000, 4_400)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn remove_endpoint() -> Weight {
+		Weight::from_parts(21_000_000, 4_400)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn record_endpoint_failover() -> Weight {
+		Weight::from_parts(18_000_000, 4_400)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	fn send_command_rejected() -> Weight {
+		Weight::from_parts(11_000_000, 4_400)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:6 w:5)
+	/// Storage: EdgeConnect CommandStatusOf (r:15 w:5)
+	/// Storage: EdgeConnect CommandConnection (r:5 w:0)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:5 w:5)
+	/// Storage: EdgeConnect CommandPrunes (r:5 w:5)
+	/// Storage: EdgeConnect CommandBroadcast (r:5 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:5 w:5)
+	/// The range of component `c` is `[0, 5]`.
+	fn flush_command_queue(c: u32, ) -> Weight {
+		Weight::from_parts(24_000_000, 12_000)
+			// Standard Error: 3_000
+			.saturating_add(Weight::from_parts(6_000_000, 0).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(12_u64))
+			.saturating_add(T::DbWeight::get().reads((7_u64).saturating_mul(c.into())))
+			.saturating_add(T::DbWeight::get().writes((5_u64).saturating_mul(c.into())))
+	}
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:1)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	fn retry_command() -> Weight {
+		Weight::from_parts(26_000_000, 12_000)
+			.saturating_add(T::DbWeight::get().reads(7_u64))
+			.saturating_add(T::DbWeight::get().writes(7_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:3 w:1)
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:0)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:0)
+	fn update_endpoint() -> Weight {
+		Weight::from_parts(28_000_000, 4_400)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:10 w:10)
+	/// Storage: EdgeConnect ConnectionsOf (r:10 w:10)
+	/// Storage: System Account (r:10 w:10)
+	/// Storage: EdgeConnect CommandQueue (r:10 w:10)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:100)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:100)
+	/// Storage: EdgeConnect Responses (r:0 w:100)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:100)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:100)
+	/// Storage: EdgeConnect LastUnreachableReport (r:0 w:10)
+	/// Storage: EdgeConnect MetadataEntries (r:10 w:10)
+	/// Storage: EdgeConnect ConnectionMetadata (r:160 w:160)
+	/// Storage: EdgeConnect TotalConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:0 w:10)
+	/// Storage: EdgeConnect CommandBroadcast (r:100 w:100)
+	/// Storage: EdgeConnect Broadcasts (r:100 w:100)
+	/// Storage: EdgeConnect InFlightCommands (r:10 w:10)
+	/// Storage: EdgeConnect CommandClientRef (r:100 w:100)
+	/// Storage: EdgeConnect CommandByClientRef (r:0 w:100)
+	/// Storage: EdgeConnect RecentOutcomes (r:0 w:10)
+	/// Storage: EdgeConnect QueueDepthAlert (r:0 w:10)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:100)
+	/// Storage: EdgeConnect Leases (r:0 w:10)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:100)
+	/// Storage: EdgeConnect FreeConnectionIds (r:10 w:10)
+	/// Storage: EdgeConnect ConnectionGeneration (r:10 w:10)
+	/// The range of component `n` is `[1, 10]`.
+	fn remove_connections(n: u32, ) -> Weight {
+		Weight::from_parts(12_000_000, 0)
+			// Standard Error: 9_000
+			.saturating_add(Weight::from_parts(86_000_000, 12_000).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads((57_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes((142_u64).saturating_mul(n.into())))
+	}
+	/// Storage: EdgeConnect NextTemplateId (r:1 w:1)
+	/// Storage: EdgeConnect CommandTemplates (r:0 w:1)
+	fn register_template() -> Weight {
+		Weight::from_parts(16_000_000, 1_600)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect CommandTemplates (r:1 w:1)
+	fn remove_template() -> Weight {
+		Weight::from_parts(15_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect CommandTemplates (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: System Account (r:2 w:2)
+	/// Storage: EdgeConnect NextCommandId (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:1)
+	/// Storage: EdgeConnect CommandsInBlock (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:1 w:1)
+	/// Storage: EdgeConnect CommandByClientRef (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:1)
+	/// Storage: EdgeConnect Leases (r:1 w:0)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:1)
+	fn send_command_from_template() -> Weight {
+		Weight::from_parts(52_000_000, 12_000)
+			.saturating_add(T::DbWeight::get().reads(12_u64))
+			.saturating_add(T::DbWeight::get().writes(14_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect Leases (r:1 w:1)
+	/// Storage: EdgeConnect LeaseExpiries (r:1 w:1)
+	fn lease_connection() -> Weight {
+		Weight::from_parts(21_000_000, 6_400)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect Leases (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	fn release_lease() -> Weight {
+		Weight::from_parts(20_000_000, 6_400)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn submit_server_response() -> Weight {
+		Weight::from_parts(82_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(9_u64))
+			.saturating_add(T::DbWeight::get().writes(8_u64))
+	}
+	/// Storage: EdgeConnect OcwPaused (r:0 w:1)
+	fn set_ocw_paused() -> Weight {
+		Weight::from_parts(9_000_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:1)
+	fn add_ocw_authority() -> Weight {
+		Weight::from_parts(15_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:1)
+	fn remove_ocw_authority() -> Weight {
+		Weight::from_parts(15_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:0)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:1)
+	/// Storage: EdgeConnect NextConnectionId (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: EdgeConnect LastConnectionCreatedAt (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:0 w:1)
+	/// Storage: EdgeConnect TotalConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect FreeConnectionIds (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionGeneration (r:1 w:0)
+	/// Storage: EdgeConnect ConnectionExpiries (r:1 w:1)
+	fn create_connection() -> Weight {
+		Weight::from_parts(41_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(10_u64))
+			.saturating_add(RocksDbWeight::get().writes(9_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:10)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:10)
+	/// Storage: EdgeConnect Responses (r:0 w:10)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:10)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:10)
+	/// Storage: EdgeConnect LastUnreachableReport (r:0 w:1)
+	/// Storage: EdgeConnect MetadataEntries (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionMetadata (r:16 w:16)
+	/// Storage: EdgeConnect TotalConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:0 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:10 w:10)
+	/// Storage: EdgeConnect Broadcasts (r:10 w:10)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:10 w:10)
+	/// Storage: EdgeConnect CommandByClientRef (r:0 w:10)
+	/// Storage: EdgeConnect RecentOutcomes (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:0 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:10)
+	/// Storage: EdgeConnect Leases (r:0 w:1)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:10)
+	/// Storage: EdgeConnect FreeConnectionIds (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionGeneration (r:1 w:1)
+	/// The range of component `c` is `[0, 10]`.
+	fn remove_connection(c: u32, ) -> Weight {
+		Weight::from_parts(34_000_000, 12_000)
+			// Standard Error: 3_000
+			.saturating_add(Weight::from_parts(5_000_000, 0).saturating_mul(c.into()))
+			.saturating_add(RocksDbWeight::get().reads(27_u64))
+			.saturating_add(RocksDbWeight::get().reads((3_u64).saturating_mul(c.into())))
+			.saturating_add(RocksDbWeight::get().writes(32_u64))
+			.saturating_add(RocksDbWeight::get().writes((11_u64).saturating_mul(c.into())))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: System Account (r:2 w:2)
+	/// Storage: EdgeConnect NextCommandId (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:1)
+	/// Storage: EdgeConnect CommandsInBlock (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:1 w:1)
+	/// Storage: EdgeConnect CommandByClientRef (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:1)
+	/// Storage: EdgeConnect Leases (r:1 w:0)
+	/// Storage: EdgeConnect LastSeq (r:1 w:1)
+	fn send_command() -> Weight {
+		Weight::from_parts(47_000_000, 12_000)
+			.saturating_add(RocksDbWeight::get().reads(12_u64))
+			.saturating_add(RocksDbWeight::get().writes(14_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	fn set_connection_status() -> Weight {
+		Weight::from_parts(18_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn receive_response() -> Weight {
+		Weight::from_parts(80_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(10_u64))
+			.saturating_add(RocksDbWeight::get().writes(8_u64))
+	}
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect NextUnsignedAt (r:0 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn submit_response_unsigned_with_signed_payload() -> Weight {
+		Weight::from_parts(81_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().writes(9_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	fn pause_connection() -> Weight {
+		Weight::from_parts(18_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	fn resume_connection() -> Weight {
+		Weight::from_parts(18_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionsOf (r:2 w:2)
+	/// Storage: System Account (r:2 w:2)
+	/// Storage: EdgeConnect Leases (r:1 w:1)
+	fn transfer_connection() -> Weight {
+		Weight::from_parts(45_000_000, 6_196)
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:0)
+	fn rename_connection() -> Weight {
+		Weight::from_parts(22_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect DispatchLogCursor (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandDeadlines (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn update_command_status() -> Weight {
+		Weight::from_parts(24_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(12_u64))
+			.saturating_add(RocksDbWeight::get().writes(9_u64))
+	}
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:1)
+	fn add_authorized_domain() -> Weight {
+		Weight::from_parts(15_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:1)
+	fn remove_authorized_domain() -> Weight {
+		Weight::from_parts(15_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn grant_command_access() -> Weight {
+		Weight::from_parts(19_000_000, 4_000)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn revoke_command_access() -> Weight {
+		Weight::from_parts(19_000_000, 4_000)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect CommandConnection (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:1)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:1)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:1 w:1)
+	/// Storage: EdgeConnect CommandByClientRef (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:1)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:1)
+	fn cancel_command() -> Weight {
+		Weight::from_parts(27_000_000, 12_000)
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().writes(14_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn heartbeat() -> Weight {
+		Weight::from_parts(17_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:10)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:10)
+	/// Storage: EdgeConnect Responses (r:0 w:10)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:10)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:10)
+	/// Storage: EdgeConnect LastUnreachableReport (r:0 w:1)
+	/// Storage: EdgeConnect MetadataEntries (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionMetadata (r:16 w:16)
+	/// Storage: EdgeConnect TotalConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:0 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:10 w:10)
+	/// Storage: EdgeConnect Broadcasts (r:10 w:10)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:10 w:10)
+	/// Storage: EdgeConnect CommandByClientRef (r:0 w:10)
+	/// Storage: EdgeConnect RecentOutcomes (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:0 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:10)
+	/// Storage: EdgeConnect Leases (r:0 w:1)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:10)
+	/// Storage: EdgeConnect FreeConnectionIds (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionGeneration (r:1 w:1)
+	/// The range of component `c` is `[0, 10]`.
+	fn force_remove_connection(c: u32, ) -> Weight {
+		Weight::from_parts(33_000_000, 12_000)
+			// Standard Error: 3_000
+			.saturating_add(Weight::from_parts(5_000_000, 0).saturating_mul(c.into()))
+			.saturating_add(RocksDbWeight::get().reads(27_u64))
+			.saturating_add(RocksDbWeight::get().reads((3_u64).saturating_mul(c.into())))
+			.saturating_add(RocksDbWeight::get().writes(32_u64))
+			.saturating_add(RocksDbWeight::get().writes((11_u64).saturating_mul(c.into())))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn report_command_error() -> Weight {
+		Weight::from_parts(22_000_000, 4_100)
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn set_connection_tags() -> Weight {
+		Weight::from_parts(21_000_000, 4_400)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:100 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:100 w:100)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandId (r:1 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:100)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:100)
+	/// Storage: EdgeConnect CommandsInBlock (r:100 w:100)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:100 w:100)
+	/// Storage: EdgeConnect NextBroadcastId (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:0 w:100)
+	/// Storage: EdgeConnect Broadcasts (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:100 w:100)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:100)
+	/// The range of component `c` is `[0, 100]`.
+	fn broadcast_command(c: u32, ) -> Weight {
+		Weight::from_parts(20_000_000, 3_600)
+			// Standard Error: 4_000
+			.saturating_add(Weight::from_parts(20_000_000, 0).saturating_mul(c.into()))
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().reads((5_u64).saturating_mul(c.into())))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+			.saturating_add(RocksDbWeight::get().writes((8_u64).saturating_mul(c.into())))
+			.saturating_add(Weight::from_parts(0, 12_000).saturating_mul(c.into()))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionMetadata (r:1 w:1)
+	/// Storage: EdgeConnect MetadataEntries (r:1 w:1)
+	fn set_metadata() -> Weight {
+		Weight::from_parts(23_000_000, 6_700)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionMetadata (r:1 w:1)
+	/// Storage: EdgeConnect MetadataEntries (r:1 w:1)
+	fn clear_metadata() -> Weight {
+		Weight::from_parts(22_000_000, 6_700)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect LastUnreachableReport (r:1 w:1)
+	fn report_unreachable() -> Weight {
+		Weight::from_parts(17_000_000, 4_400)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn set_server_pubkey() -> Weight {
+		Weight::from_parts(20_000_000, 4_400)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:3 w:1)
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:0)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:0)
+	fn add_endpoint() -> Weight {
+		Weight::from_parts(28_000_000, 4_400)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn remove_endpoint() -> Weight {
+		Weight::from_parts(21_000_000, 4_400)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	fn record_endpoint_failover() -> Weight {
+		Weight::from_parts(18_000_000, 4_400)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	fn send_command_rejected() -> Weight {
+		Weight::from_parts(11_000_000, 4_400)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:6 w:5)
+	/// Storage: EdgeConnect CommandStatusOf (r:15 w:5)
+	/// Storage: EdgeConnect CommandConnection (r:5 w:0)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:5 w:5)
+	/// Storage: EdgeConnect CommandPrunes (r:5 w:5)
+	/// Storage: EdgeConnect CommandBroadcast (r:5 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:5 w:5)
+	/// The range of component `c` is `[0, 5]`.
+	fn flush_command_queue(c: u32, ) -> Weight {
+		Weight::from_parts(24_000_000, 12_000)
+			// Standard Error: 3_000
+			.saturating_add(Weight::from_parts(6_000_000, 0).saturating_mul(c.into()))
+			.saturating_add(RocksDbWeight::get().reads(12_u64))
+			.saturating_add(RocksDbWeight::get().reads((7_u64).saturating_mul(c.into())))
+			.saturating_add(RocksDbWeight::get().writes((5_u64).saturating_mul(c.into())))
+	}
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:1)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	fn retry_command() -> Weight {
+		Weight::from_parts(26_000_000, 12_000)
+			.saturating_add(RocksDbWeight::get().reads(7_u64))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:3 w:1)
+	/// Storage: EdgeConnect AuthorizedDomains (r:1 w:0)
+	/// Storage: EdgeConnect ConnectionsOf (r:1 w:0)
+	fn update_endpoint() -> Weight {
+		Weight::from_parts(28_000_000, 4_400)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:10 w:10)
+	/// Storage: EdgeConnect ConnectionsOf (r:10 w:10)
+	/// Storage: System Account (r:10 w:10)
+	/// Storage: EdgeConnect CommandQueue (r:10 w:10)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:100)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:100)
+	/// Storage: EdgeConnect Responses (r:0 w:100)
+	/// Storage: EdgeConnect ResponseHashes (r:0 w:100)
+	/// Storage: EdgeConnect CommandErrors (r:0 w:100)
+	/// Storage: EdgeConnect LastUnreachableReport (r:0 w:10)
+	/// Storage: EdgeConnect MetadataEntries (r:10 w:10)
+	/// Storage: EdgeConnect ConnectionMetadata (r:160 w:160)
+	/// Storage: EdgeConnect TotalConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect ActiveConnectionCount (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:0 w:10)
+	/// Storage: EdgeConnect CommandBroadcast (r:100 w:100)
+	/// Storage: EdgeConnect Broadcasts (r:100 w:100)
+	/// Storage: EdgeConnect InFlightCommands (r:10 w:10)
+	/// Storage: EdgeConnect CommandClientRef (r:100 w:100)
+	/// Storage: EdgeConnect CommandByClientRef (r:0 w:100)
+	/// Storage: EdgeConnect RecentOutcomes (r:0 w:10)
+	/// Storage: EdgeConnect QueueDepthAlert (r:0 w:10)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:100)
+	/// Storage: EdgeConnect Leases (r:0 w:10)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:100)
+	/// Storage: EdgeConnect FreeConnectionIds (r:10 w:10)
+	/// Storage: EdgeConnect ConnectionGeneration (r:10 w:10)
+	/// The range of component `n` is `[1, 10]`.
+	fn remove_connections(n: u32, ) -> Weight {
+		Weight::from_parts(12_000_000, 0)
+			// Standard Error: 9_000
+			.saturating_add(Weight::from_parts(86_000_000, 12_000).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads((57_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes((142_u64).saturating_mul(n.into())))
+	}
+	/// Storage: EdgeConnect NextTemplateId (r:1 w:1)
+	/// Storage: EdgeConnect CommandTemplates (r:0 w:1)
+	fn register_template() -> Weight {
+		Weight::from_parts(16_000_000, 1_600)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect CommandTemplates (r:1 w:1)
+	fn remove_template() -> Weight {
+		Weight::from_parts(15_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect CommandTemplates (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: System Account (r:2 w:2)
+	/// Storage: EdgeConnect NextCommandId (r:1 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandConnection (r:0 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:0 w:1)
+	/// Storage: EdgeConnect CommandsInBlock (r:1 w:1)
+	/// Storage: EdgeConnect TotalQueuedCommands (r:1 w:1)
+	/// Storage: EdgeConnect NextCommandNonce (r:1 w:1)
+	/// Storage: EdgeConnect CommandByClientRef (r:1 w:1)
+	/// Storage: EdgeConnect CommandClientRef (r:0 w:1)
+	/// Storage: EdgeConnect QueueDepthAlert (r:1 w:1)
+	/// Storage: EdgeConnect ConnectionCommands (r:0 w:1)
+	/// Storage: EdgeConnect Leases (r:1 w:0)
+	/// Storage: EdgeConnect CommandSchemas (r:0 w:1)
+	fn send_command_from_template() -> Weight {
+		Weight::from_parts(52_000_000, 12_000)
+			.saturating_add(RocksDbWeight::get().reads(12_u64))
+			.saturating_add(RocksDbWeight::get().writes(14_u64))
+	}
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	/// Storage: EdgeConnect Leases (r:1 w:1)
+	/// Storage: EdgeConnect LeaseExpiries (r:1 w:1)
+	fn lease_connection() -> Weight {
+		Weight::from_parts(21_000_000, 6_400)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: EdgeConnect Leases (r:1 w:1)
+	/// Storage: EdgeConnect Connection (r:1 w:0)
+	fn release_lease() -> Weight {
+		Weight::from_parts(20_000_000, 6_400)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect CommandConnection (r:1 w:0)
+	/// Storage: EdgeConnect Connection (r:1 w:1)
+	/// Storage: EdgeConnect CommandStatusOf (r:1 w:1)
+	/// Storage: EdgeConnect Responses (r:0 w:1)
+	/// Storage: EdgeConnect CommandQueue (r:1 w:1)
+	/// Storage: EdgeConnect CommandBroadcast (r:1 w:0)
+	/// Storage: EdgeConnect Broadcasts (r:1 w:1)
+	/// Storage: EdgeConnect InFlightCommands (r:1 w:1)
+	/// Storage: EdgeConnect RecentOutcomes (r:1 w:1)
+	/// Storage: EdgeConnect CommandPrunes (r:1 w:1)
+	fn submit_server_response() -> Weight {
+		Weight::from_parts(82_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().writes(8_u64))
+	}
+	/// Storage: EdgeConnect OcwPaused (r:0 w:1)
+	fn set_ocw_paused() -> Weight {
+		Weight::from_parts(9_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:1)
+	fn add_ocw_authority() -> Weight {
+		Weight::from_parts(15_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: EdgeConnect OcwAuthorities (r:1 w:1)
+	fn remove_ocw_authority() -> Weight {
+		Weight::from_parts(15_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+}
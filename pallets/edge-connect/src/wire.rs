@@ -0,0 +1,100 @@
+//! JSON messages exchanged with CyberHub.
+//!
+//! These are kept apart from the on-chain types, so that the wire format can change without
+//! a storage migration.
+
+use crate::CommandId;
+use scale_info::prelude::string::String;
+use serde::{Deserialize, Serialize};
+use sp_std::{borrow::Cow, vec, vec::Vec};
+
+/// A command sent to CyberHub.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct CommandRequest<'a> {
+	/// Echoed back by CyberHub, so that its answer can be matched to the command.
+	pub command_id: CommandId,
+	/// Goes up by one with every command sent over the connection, so that CyberHub can turn
+	/// down commands it has already seen.
+	pub nonce: u64,
+	/// The command itself, as text.
+	#[serde(borrow)]
+	pub command: Cow<'a, str>,
+	/// The `0x` prefixed hex hash of the schema the response is expected to follow, for
+	/// commands sent from a template that names one.
+	#[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+	pub schema_hash: Option<Cow<'a, str>>,
+	/// Set for fire-and-forget commands, which CyberHub only acknowledges.
+	#[serde(default, skip_serializing_if = "core::ops::Not::not")]
+	pub ack_only: bool,
+}
+
+/// Whether CyberHub managed to run a command.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseStatus {
+	/// The command ran, the body holds its result.
+	Ok,
+	/// The command failed, the body describes the error.
+	Error,
+	/// CyberHub took the command on without a result, the body is left empty.
+	Ack,
+}
+
+/// The envelope CyberHub answers a command with.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct CommandResponse<'a> {
+	/// The command this answers.
+	pub command_id: CommandId,
+	/// Whether the command ran.
+	pub status: ResponseStatus,
+	/// The result of the command, or the error CyberHub ran into.
+	#[serde(borrow)]
+	pub body: Cow<'a, str>,
+	/// The hex encoded sr25519 signature of the edge server over the response, if it signs its
+	/// responses.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub signature: Option<Cow<'a, str>>,
+}
+
+/// Serializes `value` as JSON.
+pub fn to_json<V: Serialize + ?Sized>(value: &V) -> Vec<u8> {
+	let mut buffer = vec![0; 128];
+	loop {
+		if let Ok(len) = serde_json_core::to_slice(value, &mut buffer) {
+			buffer.truncate(len);
+			return buffer
+		}
+		// The serializer only fails once it runs out of room.
+		let len = buffer.len();
+		buffer.resize(len * 2, 0);
+	}
+}
+
+/// Deserializes a `V` from the whole of `json`, unescaping its strings.
+pub fn from_json<'a, V: Deserialize<'a>>(json: &'a [u8]) -> Result<V, serde_json_core::de::Error> {
+	// Unescaped strings are never longer than they are in the JSON text.
+	let mut scratch = vec![0; json.len()];
+	serde_json_core::from_slice_escaped(json, &mut scratch).map(|(value, _)| value)
+}
+
+/// Encodes `bytes` as a `0x` prefixed hex string.
+pub fn encode_hex(bytes: &[u8]) -> String {
+	let digit = |d: u8| char::from_digit(d.into(), 16).unwrap_or('0');
+	let mut hex = String::with_capacity(2 + 2 * bytes.len());
+	hex.push_str("0x");
+	for byte in bytes {
+		hex.push(digit(byte >> 4));
+		hex.push(digit(byte & 0xf));
+	}
+	hex
+}
+
+/// Decodes a hex string, with or without a `0x` prefix.
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+	let pairs = hex.strip_prefix("0x").unwrap_or(hex).as_bytes().chunks_exact(2);
+	if !pairs.remainder().is_empty() {
+		return None
+	}
+	let digit = |c: u8| (c as char).to_digit(16).map(|d| d as u8);
+	pairs.map(|pair| Some(digit(pair[0])? << 4 | digit(pair[1])?)).collect()
+}
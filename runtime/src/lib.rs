@@ -55,7 +55,7 @@ pub use pallet_timestamp::Call as TimestampCall;
 use pallet_transaction_payment::{ConstFeeMultiplier, CurrencyAdapter, Multiplier};
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
-pub use sp_runtime::{Perbill, Permill};
+pub use sp_runtime::{Perbill, Percent, Permill};
 
 pub use pallet_edge_connect;
 pub use pallet_worker_registration;
@@ -123,12 +123,13 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// The version of the runtime specification. A full node will not attempt to use its native
 	//   runtime in substitute for the on-chain Wasm runtime unless all of `spec_name`,
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
-	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
-	//   the compatible custom types.
-	spec_version: 100,
+	// This value started at 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
+	//   the compatible custom types, and is bumped with every upgrade so that the `Migrations`
+	//   run.
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 2,
 	state_version: 1,
 };
 
@@ -326,9 +327,43 @@ parameter_types! {
 	pub const GracePeriod: BlockNumber = 3;
 	pub const UnsignedInterval: BlockNumber = 3;
 	pub const UnsignedPriority: BlockNumber = 3;
-	pub const MaxCommand: u32 = 10;
-	pub const MaxResponses: u32 = 1000;
-	pub const MaxStringLength: u32 = 1000;
+	pub const MaxQueuedCommands: u32 = 10;
+	pub const MaxCommandLength: u32 = 1000;
+	pub const MaxTemplateParams: u32 = 8;
+	pub const MaxResponseLength: u32 = 1000;
+	pub const MaxUrlLength: u32 = 256;
+	pub const MaxEndpoints: u32 = 4;
+	pub const MaxConnectionsPerAccount: u32 = 100;
+	pub const MaxBulkRemove: u32 = 10;
+	pub const MaxFreeConnectionIds: u32 = 100;
+	pub const MaxLabelLength: u32 = 64;
+	pub const MaxAuthorizedSenders: u32 = 32;
+	pub const MaxTagLength: u32 = 32;
+	pub const MaxTagsPerConnection: u32 = 8;
+	pub const MaxMetadataKeyLength: u32 = 32;
+	pub const MaxMetadataValueLength: u32 = 128;
+	pub const MaxMetadataEntries: u32 = 16;
+	pub const MaxCommandRetries: u32 = 5;
+	pub const MaxCommandsPerBatch: u32 = 5;
+	pub const HttpRequestTimeout: u64 = 3_000;
+	pub const OcwSubmitStrategy: pallet_edge_connect::SubmitStrategy =
+		pallet_edge_connect::SubmitStrategy::UnsignedForAny;
+	pub const CommandTimeout: BlockNumber = 10 * MINUTES;
+	pub const CommandRetentionBlocks: BlockNumber = HOURS;
+	pub const MaxPrunePerBlock: u32 = 20;
+	pub const MaxFlushPerCall: u32 = 5;
+	pub const CommandsPerConnectionPerBlock: u32 = 5;
+	pub const MaxInFlightPerConnection: u32 = 5;
+	pub const HealthWindow: u32 = 20;
+	pub const DegradedBelow: Percent = Percent::from_percent(90);
+	pub const UnhealthyBelow: Percent = Percent::from_percent(50);
+	pub const StaleConnectionBlocks: BlockNumber = HOURS;
+	pub const UnreachableReportInterval: BlockNumber = 10 * MINUTES;
+	pub const QueueDepthAlertThreshold: u32 = 8;
+	pub const QueueDepthAlertInterval: BlockNumber = 10 * MINUTES;
+	pub const ConnectionCreationCooldown: BlockNumber = MINUTES;
+	pub const ConnectionDeposit: Balance = deposit(1, 512);
+	pub const CommandFee: Balance = MILLIUNIT;
 }
 
 // Configure the pallet edge-connect
@@ -339,9 +374,55 @@ impl pallet_edge_connect::Config for Runtime {
 	type GracePeriod = GracePeriod;
 	type UnsignedInterval = UnsignedInterval;
 	type UnsignedPriority = UnsignedPriority;
-	type MaxCommand = MaxCommand;
-	type MaxResponses = MaxResponses;
-	type MaxStringLength = MaxStringLength;
+	type MaxQueuedCommands = MaxQueuedCommands;
+	type MaxResponseLength = MaxResponseLength;
+	/// Keeping bodies off chain needs nodes running with offchain indexing enabled.
+	type HashResponses = ConstBool<false>;
+	type ResponseHasher = BlakeTwo256;
+	type MaxCommandLength = MaxCommandLength;
+	type MaxTemplateParams = MaxTemplateParams;
+	type MaxUrlLength = MaxUrlLength;
+	type MaxEndpoints = MaxEndpoints;
+	type MaxConnectionsPerAccount = MaxConnectionsPerAccount;
+	type MaxBulkRemove = MaxBulkRemove;
+	type MaxFreeConnectionIds = MaxFreeConnectionIds;
+	type MaxLabelLength = MaxLabelLength;
+	type MaxAuthorizedSenders = MaxAuthorizedSenders;
+	type MaxTagLength = MaxTagLength;
+	type MaxTagsPerConnection = MaxTagsPerConnection;
+	type MaxMetadataKeyLength = MaxMetadataKeyLength;
+	type MaxMetadataValueLength = MaxMetadataValueLength;
+	type MaxMetadataEntries = MaxMetadataEntries;
+	type MaxCommandRetries = MaxCommandRetries;
+	type MaxCommandsPerBatch = MaxCommandsPerBatch;
+	type HttpRequestTimeout = HttpRequestTimeout;
+	/// Responses are submitted without fees, under a payload signed by one local account.
+	type OcwSubmitStrategy = OcwSubmitStrategy;
+	type OcwRunOnNonAuthorities = ConstBool<false>;
+	type CommandTimeout = CommandTimeout;
+	type CommandRetentionBlocks = CommandRetentionBlocks;
+	type MaxPrunePerBlock = MaxPrunePerBlock;
+	type MaxFlushPerCall = MaxFlushPerCall;
+	type CommandsPerConnectionPerBlock = CommandsPerConnectionPerBlock;
+	type MaxInFlightPerConnection = MaxInFlightPerConnection;
+	type HealthWindow = HealthWindow;
+	type DegradedBelow = DegradedBelow;
+	type UnhealthyBelow = UnhealthyBelow;
+	type StaleConnectionBlocks = StaleConnectionBlocks;
+	type UnreachableReportInterval = UnreachableReportInterval;
+	type QueueDepthAlertThreshold = QueueDepthAlertThreshold;
+	type QueueDepthAlertInterval = QueueDepthAlertInterval;
+	type Currency = Balances;
+	type ConnectionCreationCooldown = ConnectionCreationCooldown;
+	type ConnectionDeposit = ConnectionDeposit;
+	type CommandFee = CommandFee;
+	/// Command fees are burnt.
+	type FeeDestination = ();
+	type GovernanceOrigin = frame_system::EnsureRoot<AccountId>;
+	type ServerOrigin = pallet_edge_connect::EnsureConnectionOrigin<Runtime>;
+	type OnResponse = ();
+	type PayloadCodec = ();
+	type WeightInfo = pallet_edge_connect::weights::SubstrateWeight<Runtime>;
 }
 
 
@@ -508,6 +589,22 @@ pub type UncheckedExtrinsic =
 	generic::UncheckedExtrinsic<Address, RuntimeCall, Signature, SignedExtra>;
 /// The payload being signed in transactions.
 pub type SignedPayload = generic::SignedPayload<RuntimeCall, SignedExtra>;
+
+parameter_types! {
+	/// Owner of the connection kept by chains that predate keyed connections.
+	pub LegacyConnectionOwner: AccountId = Sudo::key().unwrap_or_else(|| [0u8; 32].into());
+}
+
+/// Migrations to apply on runtime upgrade.
+pub type Migrations = (
+	pallet_edge_connect::migrations::v1::MigrateToV1<Runtime, LegacyConnectionOwner>,
+	pallet_edge_connect::migrations::v2::MigrateToV2<Runtime>,
+	pallet_edge_connect::migrations::v3::MigrateToV3<Runtime>,
+	pallet_edge_connect::migrations::v4::MigrateToV4<Runtime>,
+	pallet_edge_connect::migrations::v5::MigrateToV5<Runtime>,
+	pallet_edge_connect::migrations::v6::MigrateToV6<Runtime>,
+);
+
 /// Executive: handles dispatch to the various modules.
 pub type Executive = frame_executive::Executive<
 	Runtime,
@@ -515,6 +612,7 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	Migrations,
 >;
 
 type EventRecord = frame_system::EventRecord<
@@ -534,7 +632,7 @@ mod benches {
 		[pallet_balances, Balances]
 		[pallet_timestamp, Timestamp]
 		// [pallet_contracts, Contracts]
-		// [pallet_edge_connect, EdgeConnect]
+		[pallet_edge_connect, EdgeConnect]
 	);
 }
 
@@ -706,6 +804,76 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_edge_connect_runtime_api::EdgeConnectApi<Block, AccountId, BlockNumber> for Runtime {
+		fn connections_of(
+			account: AccountId,
+			start_after: Option<pallet_edge_connect::ConnectionId>,
+			limit: u32,
+		) -> pallet_edge_connect::ConnectionsPage<AccountId, BlockNumber> {
+			EdgeConnect::connection_details_of(account, start_after, limit)
+		}
+
+		fn command_status(
+			command_id: pallet_edge_connect::CommandId,
+		) -> Option<pallet_edge_connect::CommandStatus> {
+			EdgeConnect::command_status(command_id)
+		}
+
+		fn command_response(command_id: pallet_edge_connect::CommandId) -> Option<Vec<u8>> {
+			EdgeConnect::responses(command_id).map(|response| response.into_inner())
+		}
+
+		fn response_hash(
+			command_id: pallet_edge_connect::CommandId,
+		) -> Option<pallet_edge_connect::ResponseHash> {
+			EdgeConnect::response_hash(command_id)
+		}
+
+		fn command_schema(command_id: pallet_edge_connect::CommandId) -> Option<Hash> {
+			EdgeConnect::command_schema(command_id)
+		}
+
+		fn response_body(hash: Hash) -> Option<Vec<u8>> {
+			EdgeConnect::response_body(hash)
+		}
+
+		fn connections_by_tag(tag: Vec<u8>) -> Vec<pallet_edge_connect::ConnectionId> {
+			EdgeConnect::connections_by_tag(tag)
+		}
+
+		fn can_send_command(
+			account: AccountId,
+			connection: pallet_edge_connect::ConnectionId,
+		) -> Result<(), sp_runtime::DispatchError> {
+			EdgeConnect::can_send_command(&account, connection)
+		}
+
+		fn stats() -> pallet_edge_connect::EdgeConnectStats {
+			EdgeConnect::stats()
+		}
+
+		fn dispatch_log(
+			from: BlockNumber,
+			to: BlockNumber,
+		) -> Vec<pallet_edge_connect::DispatchLogEntry<BlockNumber>> {
+			EdgeConnect::dispatch_log(from, to)
+		}
+
+		fn broadcast_results(
+			broadcast_id: pallet_edge_connect::BroadcastId,
+		) -> Vec<pallet_edge_connect::BroadcastResult> {
+			EdgeConnect::broadcast_results(broadcast_id)
+		}
+
+		fn commands_of(
+			connection: pallet_edge_connect::ConnectionId,
+			start_after: Option<pallet_edge_connect::CommandId>,
+			limit: u32,
+		) -> Vec<(pallet_edge_connect::CommandId, pallet_edge_connect::CommandStatus)> {
+			EdgeConnect::commands_of(connection, start_after, limit)
+		}
+	}
+
 	impl pallet_contracts::ContractsApi<Block, AccountId, Balance, BlockNumber, Hash, EventRecord>
 		for Runtime
 	{